@@ -1,95 +1,573 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use dioxus::prelude::*;
-use dioxus_core::spawn_forever;
-use image::DynamicImage;
+use dioxus_core::{spawn_forever, Task};
+use image::{DynamicImage, GrayImage};
 
-use crate::types::{chars_per_line, AppEvent, BleCommand, FONT_CHOICES};
+use crate::barcode::Symbology;
+use crate::config::{self, Settings};
+use crate::escpos::{draw_ruler, printed_height, stack_pages, DitherMode, FitMode, GrayscaleMethod, RasterOptions, Rotation, RULER_STEP_PX};
+use crate::fetch::{fetch_image, open_image, IMAGE_EXTENSIONS};
+use crate::jobfile::{self, JobFile};
+use crate::printer::{parse_hex_bytes, PrintTiming};
+use crate::qr::{encode_qr, render_qr, QrEcLevel};
+use crate::text_render::{estimate_text, is_valid_timestamp_format, render_text_to_images, TextOptions};
+use crate::types::{chars_per_line, font_face_count, Alignment, BatteryCurve, BatteryMode, upsert_device, AppEvent, BleCommand, ConnectedPrinter, CropRect, DiscoveredDevice, FontChoice, ListStyle, LogLevel, PrintTarget, PrinterInfo, PrinterStatus, TextHistory, DOTS_PER_MM, FONT_CHOICES, PRINTER_WIDTH, TALL_IMAGE_PX};
 
 // ── Shared state passed into the app via context ──────────────────────────────
 
 pub struct AppState {
     pub cmd_tx: tokio::sync::mpsc::Sender<BleCommand>,
-    pub evt_rx: tokio::sync::mpsc::Receiver<AppEvent>,
+    /// Taken by the event pump on startup, so waiting for events never holds
+    /// the lock that command senders need.
+    pub evt_rx: Option<tokio::sync::mpsc::Receiver<AppEvent>>,
 }
 
+/// Segment heights (dots) offered for splitting long images; 0 = no split.
+const SEGMENT_HEIGHTS: [(u32, &str); 4] = [(0, "No split"), (800, "10 cm"), (1600, "20 cm"), (2400, "30 cm")];
+
+/// Edits closer together than this are undone as one step.
+const TYPING_BURST: Duration = Duration::from_secs(1);
+
+/// How long a slider must stay put before the slow previews follow it, so
+/// dragging it doesn't re-wrap the text or re-dither the image on every tick.
+const SLIDER_SETTLE: Duration = Duration::from_millis(150);
+
+/// Below this many characters per line, wrapped text is barely readable.
+const MIN_USABLE_COLUMNS: u32 = 12;
+
+/// Most copies one print button will queue.
+const MAX_COPIES: u8 = 50;
+
+/// Finished prints kept in the History card; older ones are dropped.
+const MAX_HISTORY: usize = 20;
+
+/// Text rendered for a history thumbnail: at least enough lines and
+/// characters to fill its square top even at the smallest font size.
+const THUMBNAIL_TEXT_LINES: usize = 48;
+const THUMBNAIL_TEXT_CHARS: usize = 3000;
+
+/// Interval between line feeds while "Hold to feed" is pressed.
+const FEED_REPEAT: Duration = Duration::from_millis(200);
+
+/// Picks one delay out of a PrintTiming for editing.
+type TimingField = fn(&mut PrintTiming) -> &mut u64;
+
+/// Inputs in the advanced timing panel: label, allowed range and the field edited.
+const TIMING_FIELDS: [(&str, u64, u64, TimingField); 6] = [
+    ("After init (ms)", 0, 5000, |t| &mut t.init_delay_ms),
+    ("After start (ms)", 0, 5000, |t| &mut t.start_delay_ms),
+    ("Print speed (bytes/s)", 500, 50_000, |t| &mut t.bytes_per_sec),
+    ("Min. after data (ms)", 0, 10_000, |t| &mut t.min_data_delay_ms),
+    ("After end (ms)", 0, 5000, |t| &mut t.end_delay_ms),
+    ("Between chunks (ms)", 0, 50, |t| &mut t.chunk_delay_ms),
+];
+
 // ── Root component ────────────────────────────────────────────────────────────
 
 #[component]
 pub fn App() -> Element {
+    // Settings restored from disk at startup (see config::load in main.rs)
+    let initial = use_context::<Settings>();
+
     // ── Reactive signals ──────────────────────────────────────────────────────
     let mut connected = use_signal(|| false);
     let mut scanning = use_signal(|| false);
-    let mut battery_pct: Signal<Option<u8>> = use_signal(|| None);
-    let mut log_entries: Signal<Vec<String>> = use_signal(Vec::new);
+    // The current connection attempt goes straight to a known printer, without a scan
+    let mut direct_connect = use_signal(|| false);
+    // Every printer currently connected, and which of them print jobs go to
+    let mut printers: Signal<Vec<ConnectedPrinter>> = use_signal(Vec::new);
+    let mut print_target = use_signal(PrintTarget::default);
+    // Printers found by the last scan, strongest signal first
+    let mut devices: Signal<Vec<DiscoveredDevice>> = use_signal(Vec::new);
+    // Bluetooth adapters reported by the BLE task, and the one used for scanning
+    let mut adapters: Signal<Vec<String>> = use_signal(Vec::new);
+    let mut adapter_idx = use_signal(|| initial.adapter_index);
+    // Last battery voltage reported, shown as a percentage using battery_curve
+    let mut battery_mv: Signal<Option<u32>> = use_signal(|| None);
+    let mut battery_curve = use_signal(|| initial.battery_curve);
+    let battery_pct = use_memo(move || (*battery_mv.read()).map(|mv| battery_curve.read().percent(mv)));
+    // Warn (and optionally refuse to print) when the battery drops this low
+    let mut low_battery_pct = use_signal(|| initial.low_battery_pct);
+    let mut block_low_battery = use_signal(|| initial.block_low_battery);
+    // Average BLE write round-trip in ms, shown as a link-quality badge
+    let mut link_ms: Signal<Option<u32>> = use_signal(|| None);
+    let mut printer_info: Signal<Option<PrinterInfo>> = use_signal(|| None);
+    // Fault reported by the printer (paper out, overheating, ...); blocks printing
+    let mut printer_fault = use_signal(PrinterStatus::default);
+    let mut log_entries: Signal<Vec<(LogLevel, String)>> = use_signal(Vec::new);
+    // Append a timestamped entry, dropping the oldest once the log is long
+    let mut push_log = move |level: LogLevel, msg: String| {
+        let ts = chrono::Local::now().format("%H:%M:%S").to_string();
+        log_entries.with_mut(|v| {
+            v.push((level, format!("[{}] {}", ts, msg)));
+            if v.len() > 200 {
+                v.drain(..50);
+            }
+        });
+    };
+    // Entries below this level are hidden (not dropped) from the log box
+    let mut log_filter = use_signal(|| LogLevel::Info);
+    // Case-insensitive substring filter over the displayed log entries
+    let mut log_search = use_signal(String::new);
     let mut text_input = use_signal(String::new);
+    // Earlier text_input contents for undo; a burst of typing is one step
+    let mut text_history = use_signal(TextHistory::default);
+    let mut last_text_edit: Signal<Option<Instant>> = use_signal(|| None);
+    // Whether the text box has focus (decides what ⌘+Enter prints)
+    let mut text_focused = use_signal(|| false);
     let mut current_image: Signal<Option<DynamicImage>> = use_signal(|| None);
-    // Base64-encoded PNG thumbnail for the WebView <img> tag
-    let mut image_preview_b64: Signal<Option<String>> = use_signal(|| None);
+    // Rotation applied to current_image; kept across prints so reprints match
+    let mut rotation = use_signal(Rotation::default);
+    // current_image with the rotation applied — what is previewed and printed
+    let rotated_image = use_memo(move || {
+        current_image.read().as_ref().map(|img| Arc::new(rotation.read().apply(img)))
+    });
+    // Options applied when converting the loaded image to a raster
+    let mut image_options = use_signal(RasterOptions::default);
+    // Mirror text and image prints for iron-on transfer paper
+    let mut mirror = use_signal(|| initial.mirror);
+    // Dark UI theme (swaps the CSS color variables)
+    let mut dark_mode = use_signal(|| initial.dark_mode);
+    // image_options plus the shared mirror setting, as sent with image jobs
+    let raster_options = use_memo(move || RasterOptions { mirror: *mirror.read(), ..*image_options.read() });
+    // raster_options once it has stopped changing for SLIDER_SETTLE; drives the
+    // preview, whose resize and dither are too slow to redo per tick
+    let mut settled_raster_options = use_signal(|| *raster_options.peek());
+    let mut raster_settle_task: Signal<Option<Task>> = use_signal(|| None);
+    use_effect(move || {
+        let options = *raster_options.read();
+        if let Some(task) = raster_settle_task.write().take() {
+            task.cancel();
+        }
+        raster_settle_task.set(Some(spawn(async move {
+            tokio::time::sleep(SLIDER_SETTLE).await;
+            settled_raster_options.set(options);
+        })));
+    });
+    // What will print (fitted and thresholded), as a PNG for the WebView <img> tag
+    let image_preview = use_memo(move || {
+        rotated_image.read().as_ref().and_then(|img| ImagePreview::render(img, &settled_raster_options.read()))
+    });
+    // Split long images into jobs of at most this many dots (None = one piece)
+    let mut segment_height: Signal<Option<u32>> = use_signal(|| None);
+    // Set once the user OKs printing an image taller than TALL_IMAGE_PX;
+    // cleared whenever the image, crop, or fit changes
+    let mut tall_confirmed = use_signal(|| false);
+    // Region of the loaded image to print (normalized), and the drag in progress:
+    // crop_drag holds the normalized point where the mouse went down.
+    let mut crop_rect = use_signal(CropRect::default);
+    let mut crop_drag: Signal<Option<(f32, f32)>> = use_signal(|| None);
+    // Printed height in dots of the cropped, fitted image
+    let image_print_height = use_memo(move || {
+        let img = rotated_image.read();
+        let (_, _, w, h) = crop_rect.read().to_pixels(img.as_ref()?.width(), img.as_ref()?.height());
+        Some(printed_height(w, h, &image_options.read()))
+    });
+    use_effect(move || {
+        image_print_height.read();
+        tall_confirmed.set(false);
+    });
+    // Rendered size of the preview, used to normalize mouse coordinates
+    let mut preview_size = use_signal(|| (1.0f64, 1.0f64));
+    // Images queued up for "Print all", in print order
+    let mut batch_images: Signal<Vec<DynamicImage>> = use_signal(Vec::new);
+    let batch_thumbs = use_memo(move || batch_images.read().iter().map(thumbnail_png_b64).collect::<Vec<_>>());
+    // Index of the batch thumbnail currently being dragged
+    let mut batch_drag: Signal<Option<usize>> = use_signal(|| None);
     let mut printing = use_signal(|| false);
+    // Finished text/image prints this session, newest first
+    let mut history: Signal<Vec<HistoryEntry>> = use_signal(Vec::new);
+    // The text/image print last sent; added to history once its queue drains
+    let mut pending_history: Signal<Option<HistoryEntry>> = use_signal(|| None);
     let mut print_progress: Signal<Option<(usize, usize)>> = use_signal(|| None);
+    // Which copy of a multi-copy print is being sent, and how many in all
+    let mut copy_progress: Signal<Option<(u8, u8)>> = use_signal(|| None);
+    // Copies queued by the text and image print buttons
+    let mut text_copies = use_signal(|| 1u8);
+    let mut image_copies = use_signal(|| 1u8);
     let mut last_error: Signal<Option<String>> = use_signal(|| None);
+    // Image URL field and whether a download is in flight
+    let mut image_url = use_signal(String::new);
+    let mut fetching_url = use_signal(|| false);
+    // True while the "Hold to feed" button is pressed
+    let mut feed_held = use_signal(|| false);
+    let mut auto_reconnect = use_signal(|| initial.auto_reconnect);
+    // A printer dropped off (rather than being disconnected); with
+    // auto-reconnect on, printing stays enabled and reconnects first
+    let mut link_dropped = use_signal(|| false);
+    // Custom printer name substring/regex; empty means the built-in pattern
+    let mut name_pattern = use_signal(|| initial.printer_name_pattern.clone());
+    // (peripheral id, name) of the last printer connected to, for quick reconnect
+    let mut last_printer: Signal<Option<(String, String)>> = use_signal(|| {
+        (!initial.last_printer_id.is_empty()).then(|| (initial.last_printer_id.clone(), initial.last_printer_name.clone()))
+    });
+    // Line feeds after each print so the paper can be torn off
+    let mut feed_lines = use_signal(|| initial.feed_lines);
+    // Periodic status ping while idle so the printer doesn't auto-sleep
+    let mut keep_alive = use_signal(|| initial.keep_alive);
+    let mut keep_alive_secs = use_signal(|| initial.keep_alive_secs);
+    // Fast transfers using write-without-response (off by default)
+    let mut fast_writes = use_signal(|| initial.write_without_response);
+    // Hex bytes typed into the advanced "send raw" panel
+    let mut raw_hex = use_signal(String::new);
+    // MAC address or platform id typed into the advanced "connect by address" panel
+    let mut manual_address = use_signal(String::new);
+    // Pauses between the steps of a print (advanced panel)
+    let mut print_timing = use_signal(|| initial.print_timing);
+    // Dry-run mode: jobs are written to files instead of the printer
+    let mut simulate = use_signal(|| initial.simulate);
+    // Jobs waiting in the BLE task's print queue behind the active one
+    let mut queued_jobs = use_signal(|| 0usize);
+    // The BLE task has a rendered job cached for "Reprint last"
+    let mut has_last_job = use_signal(|| false);
 
     // ── Font / size signals ───────────────────────────────────────────────────
-    // font_idx: index into FONT_CHOICES; font_size_px: point size for rendering
-    let mut font_idx = use_signal(|| 0usize);
-    let mut font_size_px = use_signal(|| 28u32);
+    // fonts: built-in FONT_CHOICES followed by the fonts added with "Add font…"
+    // font_idx: index into fonts; font_size_px: point size for rendering
+    let mut fonts: Signal<Vec<FontChoice>> = use_signal(|| initial.font_choices());
+    // Where the saved font ended up in fonts, if it still loads
+    let restored_idx = use_hook(|| fonts.peek().iter().position(|f| f.path == initial.font_path()));
+    let mut font_idx = use_signal(|| restored_idx.unwrap_or(0));
+    // Face within a .ttc collection; reset whenever the font changes
+    let mut font_face = use_signal(|| if restored_idx.is_some() { initial.font_face } else { 0 });
+    let mut font_size_px = use_signal(|| initial.font_size_px);
+    // font_size_px once it has stopped changing for SLIDER_SETTLE; drives the
+    // column count and text estimate, which are too slow to redo per tick
+    let mut settled_font_size = use_signal(|| initial.font_size_px);
+    let mut settle_task: Signal<Option<Task>> = use_signal(|| None);
+    use_effect(move || {
+        let size = *font_size_px.read();
+        if let Some(task) = settle_task.write().take() {
+            task.cancel();
+        }
+        settle_task.set(Some(spawn(async move {
+            tokio::time::sleep(SLIDER_SETTLE).await;
+            settled_font_size.set(size);
+        })));
+    });
+    // Multiplier on the font's natural line height
+    let mut line_spacing = use_signal(|| initial.line_spacing);
+    let mut alignment = use_signal(|| initial.alignment);
+    // Keep leading whitespace and expand tabs (for code snippets)
+    let mut preserve_indent = use_signal(|| initial.preserve_indent);
+    let mut tab_width = use_signal(|| initial.tab_width);
+    // Table mode: one printed line per input line, no word wrap
+    let mut preformatted = use_signal(|| initial.preformatted);
+    // White text on a black bar
+    let mut highlight = use_signal(|| initial.highlight);
+    // Blank space either side of the text, in printer dots
+    let mut left_margin = use_signal(|| initial.left_margin);
+    let mut right_margin = use_signal(|| initial.right_margin);
+    // Optional date/time line printed above text jobs
+    let mut timestamp_header = use_signal(|| initial.timestamp_header);
+    let mut timestamp_format = use_signal(|| initial.timestamp_format.clone());
+    // Render the text box as light markdown (headings, bold, bullets)
+    let mut markdown = use_signal(|| initial.markdown);
+    // Bullets or numbers in front of each line (to-do and shopping lists)
+    let mut list_style = use_signal(|| initial.list_style);
+    // Pixels at least this light count as blank when trimming text pages
+    let mut white_threshold = use_signal(|| initial.white_threshold);
+    // Per-font baseline tweaks in pixels, keyed by font path
+    let mut baseline_offsets = use_signal(|| initial.baseline_offsets.clone());
+
+    // ── QR mode signals ───────────────────────────────────────────────────────
+    // When qr_mode is on, the text box content is printed as a QR code instead
+    let mut qr_mode = use_signal(|| false);
+    let mut qr_ec_level = use_signal(QrEcLevel::default);
+    let mut qr_module_px = use_signal(|| 6u32);
+
+    // ── Barcode signals ───────────────────────────────────────────────────────
+    let mut barcode_data = use_signal(String::new);
+    let mut barcode_symbology = use_signal(Symbology::default);
+
+    // ── Print command builders (shared by the cards and the 1:1 preview) ──────
+    let font_path = move || fonts.read()[*font_idx.read()].path.to_string();
+    let baseline_offset = move || baseline_offsets.read().get(&font_path()).copied().unwrap_or(0);
+    let text_options = move |font_size: u32| TextOptions {
+        font_path: font_path(),
+        face_index: *font_face.read(),
+        font_size: font_size as f32,
+        line_spacing: *line_spacing.read(),
+        alignment: *alignment.read(),
+        preserve_indent: *preserve_indent.read(),
+        preformatted: *preformatted.read(),
+        highlight: *highlight.read(),
+        tab_width: *tab_width.read(),
+        timestamp_format: timestamp_header.read().then(|| timestamp_format.read().clone()),
+        markdown: *markdown.read(),
+        list_style: *list_style.read(),
+        left_margin: *left_margin.read(),
+        right_margin: *right_margin.read(),
+        mirror: *mirror.read(),
+        white_threshold: *white_threshold.read(),
+        baseline_offset: baseline_offset(),
+    };
+    let text_command = move || {
+        let text = text_input.read().clone();
+        if *qr_mode.read() {
+            BleCommand::PrintQr {
+                text,
+                ec_level: *qr_ec_level.read(),
+                module_px: *qr_module_px.read(),
+            }
+        } else {
+            BleCommand::PrintText { text, options: text_options(*font_size_px.read()), copies: *text_copies.read() }
+        }
+    };
+    // Live word count and paper length for the text box
+    let text_estimate = use_memo(move || {
+        let text = text_input.read();
+        if text.trim().is_empty() || *qr_mode.read() {
+            return None;
+        }
+        // The settled size only, so dragging the slider doesn't re-wrap per tick
+        estimate_text(&text, &text_options(*settled_font_size.read())).ok()
+    });
+    // Restore the previous text box contents
+    let mut undo_text = move || {
+        let previous = text_history.write().undo();
+        if let Some(previous) = previous {
+            text_input.set(previous);
+            last_text_edit.set(None);
+        }
+    };
+    let image_command = move || {
+        let img = rotated_image.read().clone()?;
+        Some(BleCommand::PrintImage {
+            img,
+            options: *raster_options.read(),
+            crop: *crop_rect.read(),
+            segment_height: *segment_height.read(),
+            copies: *image_copies.read(),
+        })
+    };
+    // Remember a print as it's sent so it can join the history when done
+    let mut record_print = move |cmd: &BleCommand| pending_history.set(history_entry(cmd));
+
+    // ── 1:1 preview panel ─────────────────────────────────────────────────────
+    // Full-resolution rendering of exactly what will be sent, page by page
+    let mut full_preview: Signal<Option<PreviewSource>> = use_signal(|| None);
+    // Draw a pixel/millimetre ruler over the 1:1 preview (display only)
+    let mut show_ruler = use_signal(|| false);
+    let full_preview_pages = use_memo(move || {
+        let ruler = *show_ruler.read();
+        match *full_preview.read() {
+            None => Ok(Vec::new()),
+            Some(PreviewSource::Text) => render_command_preview(text_command(), ruler),
+            Some(PreviewSource::Image) => image_command().map_or(Ok(Vec::new()), |cmd| render_command_preview(cmd, ruler)),
+        }
+    });
+    // Outcome of the last "Save as image" or "Export job" (from a card or the
+    // preview panel): which job it was for, and the saved path or an error
+    let mut saved_bitmap: Signal<Option<(PreviewSource, Result<PathBuf, String>)>> = use_signal(|| None);
+    // Render a job exactly as it would print and write it to a PNG; works
+    // without a printer, so labels can be designed offline
+    let save_as_image = move |source: PreviewSource| {
+        let cmd = match source {
+            PreviewSource::Text => Some(text_command()),
+            PreviewSource::Image => image_command(),
+        };
+        if let Some(cmd) = cmd {
+            spawn(async move {
+                if let Some(result) = save_command_bitmap(cmd).await {
+                    saved_bitmap.set(Some((source, result)));
+                }
+            });
+        }
+    };
+    // Render a job to a .ctpjob file that prints byte for byte the same from
+    // any copy of the app, whatever its fonts or settings
+    let export_job = move |source: PreviewSource| {
+        let cmd = match source {
+            PreviewSource::Text => Some(text_command()),
+            PreviewSource::Image => image_command(),
+        };
+        if let Some(cmd) = cmd {
+            spawn(async move {
+                if let Some(result) = export_command_job(cmd).await {
+                    saved_bitmap.set(Some((source, result)));
+                }
+            });
+        }
+    };
+
+    // ── Persist settings whenever one of the controls changes ─────────────────
+    use_effect(move || {
+        let settings = Settings {
+            font_idx: *font_idx.read(),
+            font_face: *font_face.read(),
+            font_size_px: *font_size_px.read(),
+            line_spacing: *line_spacing.read(),
+            alignment: *alignment.read(),
+            preserve_indent: *preserve_indent.read(),
+            preformatted: *preformatted.read(),
+            highlight: *highlight.read(),
+            tab_width: *tab_width.read(),
+            left_margin: *left_margin.read(),
+            right_margin: *right_margin.read(),
+            timestamp_header: *timestamp_header.read(),
+            timestamp_format: timestamp_format.read().clone(),
+            markdown: *markdown.read(),
+            list_style: *list_style.read(),
+            auto_reconnect: *auto_reconnect.read(),
+            adapter_index: *adapter_idx.read(),
+            printer_name_pattern: name_pattern.read().clone(),
+            last_printer_id: last_printer.read().as_ref().map(|(id, _)| id.clone()).unwrap_or_default(),
+            last_printer_name: last_printer.read().as_ref().map(|(_, name)| name.clone()).unwrap_or_default(),
+            feed_lines: *feed_lines.read(),
+            keep_alive: *keep_alive.read(),
+            keep_alive_secs: *keep_alive_secs.read(),
+            write_without_response: *fast_writes.read(),
+            mirror: *mirror.read(),
+            white_threshold: *white_threshold.read(),
+            baseline_offsets: baseline_offsets.read().clone(),
+            print_timing: *print_timing.read(),
+            battery_curve: *battery_curve.read(),
+            low_battery_pct: *low_battery_pct.read(),
+            block_low_battery: *block_low_battery.read(),
+            simulate: *simulate.read(),
+            dark_mode: *dark_mode.read(),
+            custom_fonts: fonts.read()[FONT_CHOICES.len()..].iter().map(|f| f.path.to_string()).collect(),
+        };
+        if let Err(e) = config::save(&settings) {
+            log::warn!("Failed to save settings: {}", e);
+        }
+    });
 
     // ── Retrieve channels from context ────────────────────────────────────────
-    let state = use_context::<std::sync::Arc<tokio::sync::Mutex<AppState>>>();
+    let state = use_context::<Arc<tokio::sync::Mutex<AppState>>>();
 
     // ── BLE event pump: drains AppEvent channel and writes to signals ─────────
     // spawn_forever keeps this alive for the lifetime of the app.
     use_hook(|| {
         let state = state.clone();
         spawn_forever(async move {
+            let Some(mut evt_rx) = state.lock().await.evt_rx.take() else { return };
             loop {
-                let event = {
-                    let mut s = state.lock().await;
-                    s.evt_rx.recv().await
-                };
+                let event = evt_rx.recv().await;
                 match event {
-                    Some(AppEvent::Log(msg)) => {
-                        let ts = chrono::Local::now().format("%H:%M:%S").to_string();
-                        let entry = format!("[{}] {}", ts, msg);
-                        log_entries.with_mut(|v| {
-                            v.push(entry);
-                            if v.len() > 200 {
-                                v.drain(..50);
-                            }
-                        });
+                    Some(AppEvent::Adapters(names)) => {
+                        adapters.set(names);
+                    }
+                    Some(AppEvent::Log(level, msg)) => {
+                        push_log(level, msg);
                     }
                     Some(AppEvent::Connected) => {
                         connected.set(true);
+                        link_dropped.set(false);
                         scanning.set(false);
-                        let ts = chrono::Local::now().format("%H:%M:%S").to_string();
-                        log_entries.with_mut(|v| v.push(format!("[{}] Connected", ts)));
+                        push_log(LogLevel::Info, "Connected".into());
+                    }
+                    Some(AppEvent::ConnectedPrinters(list)) => {
+                        scanning.set(false);
+                        // Fall back to all printers if the chosen one went away
+                        let target_gone = match &*print_target.read() {
+                            PrintTarget::Printer(id) => !list.iter().any(|p| &p.id == id),
+                            PrintTarget::All => false,
+                        };
+                        if target_gone {
+                            print_target.set(PrintTarget::All);
+                            state.lock().await.cmd_tx.send(BleCommand::SetPrintTarget(PrintTarget::All)).await.ok();
+                        }
+                        printers.set(list);
+                    }
+                    Some(AppEvent::ConnectionLost) => {
+                        link_dropped.set(true);
+                    }
+                    Some(AppEvent::PrinterIdentity { id, name }) => {
+                        last_printer.set(Some((id, name)));
                     }
                     Some(AppEvent::Disconnected) => {
                         connected.set(false);
                         scanning.set(false);
-                        battery_pct.set(None);
+                        battery_mv.set(None);
+                        link_ms.set(None);
+                        printer_info.set(None);
+                        printer_fault.set(PrinterStatus::Ready);
                         printing.set(false);
                         print_progress.set(None);
+                        copy_progress.set(None);
+                        pending_history.set(None);
+                        queued_jobs.set(0);
+                    }
+                    Some(AppEvent::BatteryVoltage(mv)) => {
+                        battery_mv.set(Some(mv));
+                    }
+                    Some(AppEvent::LinkQuality(ms)) => {
+                        link_ms.set(Some(ms));
+                    }
+                    Some(AppEvent::PrinterInfo(info)) => {
+                        printer_info.set(Some(info));
                     }
-                    Some(AppEvent::BatteryLevel(pct)) => {
-                        battery_pct.set(Some(pct));
+                    Some(AppEvent::PrinterFault(status)) => {
+                        printer_fault.set(status);
+                        if status.is_fault() {
+                            push_log(LogLevel::Warn, format!("Printer fault: {}", status.label()));
+                        } else {
+                            push_log(LogLevel::Info, "Printer fault cleared".into());
+                        }
                     }
                     Some(AppEvent::ScanStarted) => {
                         scanning.set(true);
+                        direct_connect.set(false);
+                    }
+                    Some(AppEvent::ConnectStarted) => {
+                        scanning.set(true);
+                        direct_connect.set(true);
+                    }
+                    Some(AppEvent::DeviceDiscovered(device)) => {
+                        devices.with_mut(|v| upsert_device(v, device));
                     }
                     Some(AppEvent::PrintProgress { sent, total }) => {
                         print_progress.set(Some((sent, total)));
                         printing.set(true);
                     }
-                    Some(AppEvent::PrintComplete) => {
+                    Some(AppEvent::CopyProgress { copy, copies }) => {
+                        copy_progress.set(Some((copy, copies)));
+                    }
+                    Some(AppEvent::PrintComplete(summary)) => {
+                        printing.set(false);
+                        print_progress.set(None);
+                        // A print's pages and copies all count towards its one entry
+                        if let Some(entry) = pending_history.write().as_mut() {
+                            entry.bytes += summary.bytes;
+                        }
+                        if *queued_jobs.read() == 0 {
+                            copy_progress.set(None);
+                            if let Some(mut entry) = pending_history.take() {
+                                entry.time = chrono::Local::now().format("%H:%M:%S").to_string();
+                                history.with_mut(|h| {
+                                    h.insert(0, entry);
+                                    h.truncate(MAX_HISTORY);
+                                });
+                            }
+                        }
+                    }
+                    Some(AppEvent::ReprintAvailable) => {
+                        has_last_job.set(true);
+                    }
+                    Some(AppEvent::PrintCancelled) => {
                         printing.set(false);
                         print_progress.set(None);
-                        let ts = chrono::Local::now().format("%H:%M:%S").to_string();
-                        log_entries.with_mut(|v| v.push(format!("[{}] Print complete", ts)));
+                        copy_progress.set(None);
+                        pending_history.set(None);
+                    }
+                    Some(AppEvent::DuplicateSkipped { busy }) => {
+                        // The first print already finished; nothing more is coming
+                        if !busy {
+                            printing.set(false);
+                            pending_history.set(None);
+                        }
+                    }
+                    Some(AppEvent::QueueLength(n)) => {
+                        queued_jobs.set(n);
                     }
                     Some(AppEvent::Error(e)) => {
                         last_error.set(Some(e.clone()));
-                        let ts = chrono::Local::now().format("%H:%M:%S").to_string();
-                        log_entries.with_mut(|v| v.push(format!("[{}] Error: {}", ts, e)));
+                        push_log(LogLevel::Error, format!("Error: {}", e));
                         printing.set(false);
                         scanning.set(false);
                     }
@@ -100,6 +578,12 @@ pub fn App() -> Element {
     });
 
     // ── Derived display values ────────────────────────────────────────────────
+    let unconnected_devices: Vec<DiscoveredDevice> = devices
+        .read()
+        .iter()
+        .filter(|d| !printers.read().iter().any(|p| p.id == d.id))
+        .cloned()
+        .collect();
     let status_text = if *scanning.read() {
         "⟳ Scanning..."
     } else if *connected.read() {
@@ -127,42 +611,238 @@ pub fn App() -> Element {
         (pct, color)
     });
 
-    let can_print_text =
-        *connected.read() && !text_input.read().trim().is_empty() && !*printing.read();
+    // Round-trips above this suggest a weak or congested link.
+    const SLOW_LINK_MS: u32 = 150;
+    let link_display = (*link_ms.read()).map(|ms| {
+        let class = if ms > SLOW_LINK_MS { "link-badge link-slow" } else { "link-badge" };
+        (ms, class)
+    });
+
+    let info_display = printer_info.read().as_ref().map(|info| {
+        let mut parts = Vec::new();
+        if let Some(ref hv) = info.hw_version {
+            parts.push(format!("HW {}", hv));
+        }
+        if let Some(ref sv) = info.sw_version {
+            parts.push(format!("FW {}", sv));
+        }
+        if let Some(dpi) = info.dpi {
+            parts.push(format!("{} dpi", dpi));
+        }
+        parts.join(" · ")
+    });
+
+    // Printing while a job is active is allowed: the BLE task queues the new job.
+    let qr_on = *qr_mode.read();
+    let qr_ec = *qr_ec_level.read();
+    let qr_module = *qr_module_px.read();
+    // Capacity check so an oversized QR payload is flagged before printing
+    let qr_error = if qr_on && !text_input.read().is_empty() {
+        encode_qr(&text_input.read(), qr_ec).err()
+    } else {
+        None
+    };
+
+    // Printing is blocked while the printer reports a fault, and optionally
+    // while the battery is too low to finish a print without browning out
+    let fault = *printer_fault.read();
+    let low_battery = (*battery_pct.read()).filter(|&pct| pct < *low_battery_pct.read());
+    let battery_blocks = low_battery.is_some() && *block_low_battery.read() && !*simulate.read();
+    let reconnect_on_print = *link_dropped.read() && *auto_reconnect.read();
+    let can_print = (*connected.read() || *simulate.read() || reconnect_on_print) && !fault.is_fault() && !battery_blocks;
+
+    let can_print_text = can_print && !text_input.read().trim().is_empty() && qr_error.is_none();
+
+    // Very long prints need a confirmation click first
+    let tall_image_cm = (*image_print_height.read())
+        .filter(|&h| h > TALL_IMAGE_PX)
+        .map(|h| h as f32 / DOTS_PER_MM / 10.0);
+    let needs_tall_confirm = tall_image_cm.is_some() && !*tall_confirmed.read();
+    let can_print_image = can_print && current_image.read().is_some() && !needs_tall_confirm;
+    let batch_len = batch_images.read().len();
+    let can_print_batch = can_print && batch_len > 0;
 
-    let can_print_image = *connected.read() && current_image.read().is_some() && !*printing.read();
+    // Malformed barcode input is reported by the BLE task via AppEvent::Error
+    let can_print_barcode = can_print && !barcode_data.read().trim().is_empty();
+    let symbology = *barcode_symbology.read();
+    let barcode_placeholder = match symbology {
+        Symbology::Code128 => "Any printable ASCII text",
+        Symbology::Ean13 => "12 digits (check digit added) or 13 digits",
+    };
+
+    let queued = *queued_jobs.read();
 
     let progress_display = *print_progress.read();
 
+    let RasterOptions { brightness, contrast, auto_levels, sharpen, fit, dither, grayscale, .. } = *image_options.read();
+
+    let crop = *crop_rect.read();
+    let rotation_deg = rotation.read().degrees();
+    // The preview is paper-wide, so map the crop onto the span the image occupies
+    let (span_x, span_w) = image_preview.read().as_ref().map_or((0.0, 1.0), |p| (p.x_frac, p.w_frac));
+    // ...flipped too when the preview is mirrored
+    let crop_left = if *mirror.read() { 1.0 - crop.x - crop.w } else { crop.x };
+    let crop_style = format!(
+        "left: {:.2}%; top: {:.2}%; width: {:.2}%; height: {:.2}%;",
+        (span_x + crop_left * span_w) * 100.0, crop.y * 100.0, crop.w * span_w * 100.0, crop.h * 100.0
+    );
+
     // ── Font / size derived values ────────────────────────────────────────────
     let idx = *font_idx.read();
     let size = *font_size_px.read();
-    let font = &FONT_CHOICES[idx];
-    let font_path_str = font.path;
-    let css_family = font.css_family;
-    // Compute chars that fit the 384px printer width at the current size
-    let cols = chars_per_line(font_path_str, size as f32);
-    // Inline style for the textarea: dynamic font-family, font-size, and width
+    let spacing = *line_spacing.read();
+    let align = *alignment.read();
+    let keep_indent = *preserve_indent.read();
+    let table_mode = *preformatted.read();
+    let tabs = *tab_width.read();
+    let stamp = *timestamp_header.read();
+    let stamp_format_ok = is_valid_timestamp_format(&timestamp_format.read());
+    let face = *font_face.read();
+    let font = fonts.read()[idx].clone();
+    let css_family = &font.css_family;
+    // Faces in the selected font; the face picker only shows for collections
+    let face_count = font_face_count(&font.path);
+    // Chars that fit the 384px printer width at a given size, narrowed to
+    // the area left between the margins
+    let margins = (*left_margin.read()).min(TextOptions::MAX_MARGIN) + (*right_margin.read()).min(TextOptions::MAX_MARGIN);
+    let columns_at = |px: u32| chars_per_line(&font.path, face, px as f32) * (PRINTER_WIDTH - margins) / PRINTER_WIDTH;
+    let settled_size = *settled_font_size.read();
+    let cols = columns_at(settled_size);
+    // Largest size that still fits MIN_USABLE_COLUMNS, offered when the current one doesn't
+    let suggested_size =
+        (cols < MIN_USABLE_COLUMNS).then(|| (12..settled_size).rev().find(|&px| columns_at(px) >= MIN_USABLE_COLUMNS));
+    // @font-face rules for user-added fonts (built-ins are declared in STYLES)
+    let custom_font_css = custom_font_faces(&fonts.read()[FONT_CHOICES.len()..]);
+    // Inline style for the textarea: dynamic font-family, font-size, line-height, and width
+    let text_align = match align {
+        Alignment::Left => "left",
+        Alignment::Center => "center",
+        Alignment::Right => "right",
+    };
     let textarea_style = format!(
-        "font-family: '{}', monospace; font-size: {}px; width: {}ch;",
-        css_family, size, cols
+        "font-family: '{}', monospace; font-size: {}px; line-height: {:.2}; width: {}ch; text-align: {}; tab-size: {};",
+        css_family, size, 1.45 * spacing, cols, text_align, tabs
     );
 
     // ── Clones for event handlers ─────────────────────────────────────────────
     let state_ble = state.clone();
     let state_ble2 = state.clone();
+    let state_stop_scan = state.clone();
+    let state_add_printer = state.clone();
+    let state_disconnect_device = state.clone();
+    let state_print_target = state.clone();
+    let state_last_printer = state.clone();
+    let state_reset = state.clone();
+    let state_jog = state.clone();
+    let state_jog_hold = state.clone();
+    let state_jog_dot = state.clone();
+    let state_reprint = state.clone();
+    let state_print_job_file = state.clone();
+    let state_shortcut = state.clone();
+    let state_adapter = state.clone();
     let state_print_text = state.clone();
+    let state_print_clipboard = state.clone();
     let state_print_image = state.clone();
+    let state_print_batch = state.clone();
+    let state_print_barcode = state.clone();
+    let state_cancel = state.clone();
+    let state_reconnect = state.clone();
+    let state_test_page = state.clone();
+    let state_name_pattern = state.clone();
+    let state_feed = state.clone();
+    let state_keep_alive = state.clone();
+    let state_simulate = state.clone();
+    let state_fast_writes = state.clone();
+    let state_timing = state.clone();
+    let state_timing_reset = state.clone();
+    let state_send_raw = state.clone();
+    let state_connect_address = state.clone();
+    let state_keep_alive_secs = state.clone();
+    let state_connect_device = state.clone();
+    let state_print_preview = state.clone();
 
     rsx! {
         style { {STYLES} }
+        if *dark_mode.read() {
+            style { {DARK_THEME} }
+        }
+        style { {custom_font_css} }
+
+        div {
+            class: "container",
+            // Focusable by click, so the shortcut works with nothing else focused
+            tabindex: "-1",
+            // ⌘/Ctrl+Enter prints the text box (when focused or filled in), else the image
+            onkeydown: move |e| {
+                let mods = e.modifiers();
+                if e.key() != Key::Enter || !(mods.meta() || mods.ctrl()) || *printing.read() {
+                    return;
+                }
+                let text_active = *text_focused.read() || !text_input.read().trim().is_empty();
+                let cmd = if text_active && can_print_text {
+                    Some(text_command())
+                } else if can_print_image {
+                    image_command()
+                } else {
+                    None
+                };
+                if let Some(cmd) = cmd {
+                    e.prevent_default();
+                    record_print(&cmd);
+                    printing.set(true);
+                    last_error.set(None);
+                    let state = state_shortcut.clone();
+                    spawn(async move {
+                        let s = state.lock().await;
+                        s.cmd_tx.send(cmd).await.ok();
+                    });
+                }
+            },
 
-        div { class: "container",
+            if fault.is_fault() {
+                div { class: "fault-banner",
+                    "⚠ {fault.label()} — printing is paused until the printer reports ready"
+                }
+            }
+            if let Some(pct) = low_battery {
+                div { class: "fault-banner low-battery-banner",
+                    if battery_blocks {
+                        "🔋 Battery low ({pct}%) — printing is blocked until the printer is charged"
+                    } else {
+                        "🔋 Battery low ({pct}%) — charge the printer; prints may stop partway"
+                    }
+                }
+            }
 
             // ── Bluetooth section ─────────────────────────────────────────────
             section { class: "card",
                 h2 { class: "section-title", "Bluetooth Tools" }
 
+                // Only worth showing when there is a choice (e.g. built-in + USB dongle)
+                if adapters.read().len() > 1 {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "adapter-select", "Adapter" }
+                        select {
+                            id: "adapter-select",
+                            class: "control-select",
+                            disabled: *connected.read() || *scanning.read(),
+                            onchange: move |e| {
+                                if let Ok(i) = e.value().parse::<usize>() {
+                                    adapter_idx.set(i);
+                                    let state = state_adapter.clone();
+                                    spawn(async move {
+                                        let s = state.lock().await;
+                                        s.cmd_tx.send(BleCommand::SetAdapter(i)).await.ok();
+                                    });
+                                }
+                            },
+                            for (i, name) in adapters.read().iter().enumerate() {
+                                option { value: "{i}", selected: i == *adapter_idx.read(), "{name}" }
+                            }
+                        }
+                    }
+                }
+
                 div { class: "btn-row",
                     if !*connected.read() {
                         button {
@@ -172,6 +852,7 @@ pub fn App() -> Element {
                                 let state = state_ble.clone();
                                 scanning.set(true);
                                 last_error.set(None);
+                                devices.write().clear();
                                 spawn(async move {
                                     let s = state.lock().await;
                                     s.cmd_tx.send(BleCommand::ScanAndConnect).await.ok();
@@ -179,97 +860,1000 @@ pub fn App() -> Element {
                             },
                             if *scanning.read() { "Scanning..." } else { "Scan & Connect" }
                         }
+                        if let Some((id, name)) = last_printer.read().clone() {
+                            button {
+                                class: "btn btn-outline",
+                                disabled: *scanning.read(),
+                                title: "Connect without a full scan",
+                                onclick: move |_| {
+                                    let state = state_last_printer.clone();
+                                    let id = id.clone();
+                                    scanning.set(true);
+                                    last_error.set(None);
+                                    spawn(async move {
+                                        let s = state.lock().await;
+                                        s.cmd_tx.send(BleCommand::ConnectLast(id)).await.ok();
+                                    });
+                                },
+                                "Reconnect to {name} (last used)"
+                            }
+                        }
                     } else {
                         button {
                             class: "btn btn-secondary",
                             onclick: move |_| {
                                 let state = state_ble2.clone();
+                                link_dropped.set(false);
                                 spawn(async move {
                                     let s = state.lock().await;
                                     s.cmd_tx.send(BleCommand::Disconnect).await.ok();
                                 });
                             },
-                            "Disconnect"
+                            if printers.read().len() > 1 { "Disconnect all" } else { "Disconnect" }
+                        }
+                        button {
+                            class: "btn btn-outline",
+                            disabled: *scanning.read(),
+                            title: "Scan for another printer and connect it as well",
+                            onclick: move |_| {
+                                let state = state_add_printer.clone();
+                                scanning.set(true);
+                                last_error.set(None);
+                                devices.write().clear();
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::ScanAndConnect).await.ok();
+                                });
+                            },
+                            "Add printer"
+                        }
+                        button {
+                            class: "btn btn-outline",
+                            disabled: !can_print,
+                            onclick: move |_| {
+                                let state = state_test_page.clone();
+                                printing.set(true);
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::PrintTestPage).await.ok();
+                                });
+                            },
+                            "Test page"
+                        }
+                        button {
+                            class: "btn btn-outline",
+                            title: "Re-initialize the printer (ESC @) without reconnecting",
+                            onclick: move |_| {
+                                let state = state_reset.clone();
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::ResetPrinter).await.ok();
+                                });
+                            },
+                            "Reset printer"
                         }
                     }
                 }
 
-                p {
-                    class: "status-text",
-                    style: "color: {status_color}",
-                    "{status_text}"
+                // Paper jog: advance paper to tear it off or load a new roll
+                if *connected.read() {
+                    div { class: "btn-row",
+                        button {
+                            class: "btn btn-small",
+                            disabled: *printing.read(),
+                            onclick: move |_| {
+                                let state = state_jog.clone();
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::Feed(1)).await.ok();
+                                });
+                            },
+                            "+ Feed"
+                        }
+                        button {
+                            class: "btn btn-small",
+                            disabled: *printing.read(),
+                            onmousedown: move |_| {
+                                feed_held.set(true);
+                                let state = state_jog_hold.clone();
+                                spawn(async move {
+                                    while *feed_held.read() {
+                                        state.lock().await.cmd_tx.send(BleCommand::Feed(1)).await.ok();
+                                        tokio::time::sleep(FEED_REPEAT).await;
+                                    }
+                                });
+                            },
+                            onmouseup: move |_| feed_held.set(false),
+                            onmouseleave: move |_| feed_held.set(false),
+                            "Hold to feed"
+                        }
+                        button {
+                            class: "btn btn-small",
+                            disabled: *printing.read(),
+                            title: "Advance a single dot-line (1/8 mm) for precise tearing",
+                            onclick: move |_| {
+                                let state = state_jog_dot.clone();
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::FeedDots(1)).await.ok();
+                                });
+                            },
+                            "+1 dot"
+                        }
+                    }
                 }
 
-                if let Some((pct, color)) = battery_display {
-                    p {
-                        class: "battery-text",
-                        style: "color: {color}",
-                        "Battery: {pct}%"
+                // With several printers connected: pick which ones print, or drop one
+                if printers.read().len() > 1 {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "print-target", "Print to" }
+                        select {
+                            id: "print-target",
+                            class: "control-select",
+                            onchange: move |e| {
+                                let value = e.value();
+                                let target = if value.is_empty() { PrintTarget::All } else { PrintTarget::Printer(value) };
+                                print_target.set(target.clone());
+                                let state = state_print_target.clone();
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::SetPrintTarget(target)).await.ok();
+                                });
+                            },
+                            option { value: "", selected: *print_target.read() == PrintTarget::All, "All printers" }
+                            for printer in printers.read().iter() {
+                                option {
+                                    value: "{printer.id}",
+                                    selected: *print_target.read() == PrintTarget::Printer(printer.id.clone()),
+                                    "{printer.name}"
+                                }
+                            }
+                        }
+                    }
+                    div { class: "device-list",
+                        for printer in printers.read().iter().cloned() {
+                            div { class: "device-row",
+                                span { class: "device-name", "{printer.name}" }
+                                button {
+                                    class: "btn btn-small btn-outline",
+                                    onclick: {
+                                        let state = state_disconnect_device.clone();
+                                        move |_| {
+                                            let state = state.clone();
+                                            let id = printer.id.clone();
+                                            spawn(async move {
+                                                let s = state.lock().await;
+                                                s.cmd_tx.send(BleCommand::DisconnectDevice(id)).await.ok();
+                                            });
+                                        }
+                                    },
+                                    "Disconnect"
+                                }
+                            }
+                        }
                     }
                 }
 
-                if let Some(ref err) = *last_error.read() {
-                    p { class: "error-text", "Error: {err}" }
+                // Pick among several printers found by the last scan (any not yet connected)
+                if devices.read().len() > 1 && !unconnected_devices.is_empty() {
+                    div { class: "device-list",
+                        for device in unconnected_devices.iter().cloned() {
+                            div { class: "device-row",
+                                span { class: "device-name", "{device.name}" }
+                                span { class: "device-rssi",
+                                    if let Some(rssi) = device.rssi { "{rssi} dBm" } else { "— dBm" }
+                                }
+                                button {
+                                    class: "btn btn-small btn-outline",
+                                    disabled: *scanning.read(),
+                                    onclick: {
+                                        let state = state_connect_device.clone();
+                                        move |_| {
+                                            let state = state.clone();
+                                            let id = device.id.clone();
+                                            scanning.set(true);
+                                            last_error.set(None);
+                                            spawn(async move {
+                                                let s = state.lock().await;
+                                                s.cmd_tx.send(BleCommand::ConnectDevice(id)).await.ok();
+                                            });
+                                        }
+                                    },
+                                    "Connect"
+                                }
+                            }
+                        }
+                    }
                 }
-            }
-
-            // ── Text tools section ────────────────────────────────────────────
-            section { class: "card",
-                h2 { class: "section-title", "Text Tools" }
 
-                // Font selector
                 div { class: "control-row",
-                    label { class: "control-label", r#for: "font-select", "Font" }
-                    select {
-                        id: "font-select",
-                        class: "control-select",
-                        value: "{idx}",
+                    label { class: "control-label", r#for: "name-pattern", "Printer name" }
+                    input {
+                        id: "name-pattern",
+                        class: "control-text",
+                        r#type: "text",
+                        placeholder: "S (Pink|Blue|White|Black) Printer",
+                        value: "{name_pattern}",
+                        oninput: move |e| name_pattern.set(e.value()),
                         onchange: move |e| {
-                            if let Ok(v) = e.value().parse::<usize>() {
-                                font_idx.set(v);
-                            }
+                            let pattern = e.value();
+                            let state = state_name_pattern.clone();
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::SetPrinterNamePattern(pattern)).await.ok();
+                            });
                         },
-                        for (i, fc) in FONT_CHOICES.iter().enumerate() {
-                            option { value: "{i}", selected: i == idx, "{fc.label}" }
-                        }
                     }
                 }
 
-                // Font size slider
-                div { class: "control-row",
-                    label { class: "control-label", r#for: "font-size-slider",
-                        "Size: {size}px  ({cols} chars/line)"
+                label { class: "checkbox-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: *auto_reconnect.read(),
+                        onchange: move |e| {
+                            let enabled = e.checked();
+                            auto_reconnect.set(enabled);
+                            let state = state_reconnect.clone();
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::SetAutoReconnect(enabled)).await.ok();
+                            });
+                        },
                     }
+                    "Reconnect automatically if the printer drops"
+                }
+
+                label { class: "checkbox-row",
                     input {
-                        id: "font-size-slider",
-                        class: "control-slider",
-                        r#type: "range",
-                        min: "12",
-                        max: "48",
-                        step: "1",
-                        value: "{size}",
-                        oninput: move |e| {
-                            if let Ok(v) = e.value().parse::<u32>() {
-                                font_size_px.set(v);
-                            }
+                        r#type: "checkbox",
+                        checked: *simulate.read(),
+                        onchange: move |e| {
+                            let enabled = e.checked();
+                            simulate.set(enabled);
+                            let state = state_simulate.clone();
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::SetSimulate(enabled)).await.ok();
+                            });
                         },
                     }
+                    "Simulate (write prints to ~/ctp500-sim instead of the printer)"
                 }
 
-                // Textarea sized dynamically to match printer output
-                div { class: "text-input-wrap",
-                    textarea {
-                        class: "text-input",
-                        style: "{textarea_style}",
-                        placeholder: "Type or paste text to print...",
-                        rows: "5",
-                        value: "{text_input}",
-                        oninput: move |e| text_input.set(e.value()),
+                label {
+                    class: "checkbox-row",
+                    title: "Sends image data without waiting for each write to be acknowledged. Much faster on firmware that supports it, but can overrun the printer's buffer and garble or drop lines. Every 16th chunk still waits for a response.",
+                    input {
+                        r#type: "checkbox",
+                        checked: *fast_writes.read(),
+                        onchange: move |e| {
+                            let enabled = e.checked();
+                            fast_writes.set(enabled);
+                            let state = state_fast_writes.clone();
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::SetWriteWithoutResponse(enabled)).await.ok();
+                            });
+                        },
                     }
+                    "Fast transfers (write without response — may garble on some printers)"
                 }
 
-                button {
-                    class: "btn btn-outline",
+                label { class: "checkbox-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: *keep_alive.read(),
+                        onchange: move |e| {
+                            let enabled = e.checked();
+                            keep_alive.set(enabled);
+                            let secs = enabled.then(|| *keep_alive_secs.read());
+                            let state = state_keep_alive.clone();
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::SetKeepAlive(secs)).await.ok();
+                            });
+                        },
+                    }
+                    "Keep the printer awake"
+                }
+                if *keep_alive.read() {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "keep-alive-secs", "Ping every (s)" }
+                        input {
+                            id: "keep-alive-secs",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "10",
+                            max: "600",
+                            value: "{keep_alive_secs}",
+                            onchange: move |e| {
+                                if let Ok(v) = e.value().parse::<u32>() {
+                                    let secs = v.clamp(10, 600);
+                                    keep_alive_secs.set(secs);
+                                    let state = state_keep_alive_secs.clone();
+                                    spawn(async move {
+                                        let s = state.lock().await;
+                                        s.cmd_tx.send(BleCommand::SetKeepAlive(Some(secs))).await.ok();
+                                    });
+                                }
+                            },
+                        }
+                    }
+                }
+
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "feed-lines", "Feed after print (lines)" }
+                    input {
+                        id: "feed-lines",
+                        class: "control-number",
+                        r#type: "number",
+                        min: "0",
+                        max: "20",
+                        value: "{feed_lines}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<u8>() {
+                                let lines = v.min(20);
+                                feed_lines.set(lines);
+                                let state = state_feed.clone();
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::SetFeedLines(lines)).await.ok();
+                                });
+                            }
+                        },
+                    }
+                }
+
+                label { class: "checkbox-row",
+                    title: "Flip text and images left to right, for iron-on transfer paper. Applied after rotation, so the preview shows the final orientation.",
+                    input {
+                        r#type: "checkbox",
+                        checked: *mirror.read(),
+                        onchange: move |e| mirror.set(e.checked()),
+                    }
+                    "Mirror prints (iron-on transfers)"
+                }
+
+                label { class: "checkbox-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: *dark_mode.read(),
+                        onchange: move |e| dark_mode.set(e.checked()),
+                    }
+                    "Dark mode"
+                }
+
+                details { class: "advanced-panel",
+                    summary { "Advanced: print timing" }
+                    p { class: "info-text",
+                        "Pauses between the steps of each print. Lengthen them if the printer drops or truncates jobs; a few ms between chunks helps if long prints come out garbled near the end."
+                    }
+                    {TIMING_FIELDS.iter().enumerate().map(|(i, &(label, min, max, field))| {
+                        let state = state_timing.clone();
+                        let mut timing = *print_timing.read();
+                        let value = *field(&mut timing);
+                        rsx! {
+                            div { class: "control-row", key: "{i}",
+                                label { class: "control-label", r#for: "timing-{i}", "{label}" }
+                                input {
+                                    id: "timing-{i}",
+                                    class: "control-number",
+                                    r#type: "number",
+                                    min: "{min}",
+                                    max: "{max}",
+                                    value: "{value}",
+                                    onchange: move |e| {
+                                        if let Ok(v) = e.value().parse::<u64>() {
+                                            let mut timing = *print_timing.read();
+                                            *field(&mut timing) = v.clamp(min, max);
+                                            print_timing.set(timing);
+                                            let state = state.clone();
+                                            spawn(async move {
+                                                let s = state.lock().await;
+                                                s.cmd_tx.send(BleCommand::SetPrintTiming(timing)).await.ok();
+                                            });
+                                        }
+                                    },
+                                }
+                            }
+                        }
+                    })}
+                    button {
+                        class: "btn btn-outline",
+                        disabled: *print_timing.read() == PrintTiming::default(),
+                        onclick: move |_| {
+                            print_timing.set(PrintTiming::default());
+                            let state = state_timing_reset.clone();
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::SetPrintTiming(PrintTiming::default())).await.ok();
+                            });
+                        },
+                        "Reset timing"
+                    }
+                }
+
+                details { class: "advanced-panel",
+                    summary { "Advanced: battery" }
+                    p { class: "info-text",
+                        "Voltages the battery reads as empty and full, and how the level falls between them."
+                    }
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "battery-mode", "Curve" }
+                        select {
+                            id: "battery-mode",
+                            class: "control-select",
+                            onchange: move |e| {
+                                if let Some(mode) = BatteryMode::ALL.into_iter().find(|m| m.label() == e.value()) {
+                                    battery_curve.with_mut(|c| c.mode = mode);
+                                }
+                            },
+                            for mode in BatteryMode::ALL {
+                                option { value: mode.label(), selected: mode == battery_curve.read().mode, "{mode.label()}" }
+                            }
+                        }
+                    }
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "battery-min", "Empty / full (mV)" }
+                        input {
+                            id: "battery-min",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "2500",
+                            max: "4500",
+                            step: "10",
+                            value: "{battery_curve.read().min_mv}",
+                            onchange: move |e| {
+                                if let Ok(v) = e.value().parse::<u32>() {
+                                    battery_curve.with_mut(|c| c.min_mv = v.clamp(2500, c.max_mv.saturating_sub(100)));
+                                }
+                            },
+                        }
+                        input {
+                            class: "control-number",
+                            r#type: "number",
+                            min: "2600",
+                            max: "5000",
+                            step: "10",
+                            title: "Full",
+                            value: "{battery_curve.read().max_mv}",
+                            onchange: move |e| {
+                                if let Ok(v) = e.value().parse::<u32>() {
+                                    battery_curve.with_mut(|c| c.max_mv = v.clamp(c.min_mv + 100, 5000));
+                                }
+                            },
+                        }
+                    }
+                    button {
+                        class: "btn btn-outline",
+                        disabled: *battery_curve.read() == BatteryCurve::default(),
+                        onclick: move |_| battery_curve.set(BatteryCurve::default()),
+                        "Reset calibration"
+                    }
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "low-battery", "Low battery below (%)" }
+                        input {
+                            id: "low-battery",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "0",
+                            max: "50",
+                            value: "{low_battery_pct}",
+                            oninput: move |e| {
+                                if let Ok(v) = e.value().parse::<u8>() {
+                                    low_battery_pct.set(v.min(50));
+                                }
+                            },
+                        }
+                    }
+                    label { class: "checkbox-row",
+                        input {
+                            r#type: "checkbox",
+                            checked: *block_low_battery.read(),
+                            onchange: move |e| block_low_battery.set(e.checked()),
+                        }
+                        "Block printing while the battery is low"
+                    }
+                }
+
+                details { class: "advanced-panel",
+                    summary { "Advanced: connect by address" }
+                    p { class: "info-text",
+                        "Connect straight to a printer's MAC address (or the device id shown on macOS) without scanning. The system must have seen the printer before, e.g. from an earlier scan."
+                    }
+                    div { class: "control-row",
+                        input {
+                            class: "control-text",
+                            r#type: "text",
+                            placeholder: "AA:BB:CC:DD:EE:FF",
+                            spellcheck: "false",
+                            value: "{manual_address}",
+                            oninput: move |e| manual_address.set(e.value()),
+                        }
+                        button {
+                            class: "btn btn-small btn-outline",
+                            disabled: *scanning.read() || manual_address.read().trim().is_empty(),
+                            onclick: move |_| {
+                                let address = manual_address.read().trim().to_string();
+                                let state = state_connect_address.clone();
+                                last_error.set(None);
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::ConnectByAddress(address)).await.ok();
+                                });
+                            },
+                            "Connect"
+                        }
+                    }
+                }
+
+                details { class: "advanced-panel",
+                    summary { "Advanced: send raw bytes" }
+                    p { class: "info-text",
+                        "Hex bytes written to the printer as-is, e.g. 1b 40 to initialize. For experimenting with commands the app doesn't offer."
+                    }
+                    div { class: "control-row",
+                        input {
+                            class: "control-text",
+                            r#type: "text",
+                            placeholder: "1b 40",
+                            spellcheck: "false",
+                            value: "{raw_hex}",
+                            oninput: move |e| raw_hex.set(e.value()),
+                        }
+                        button {
+                            class: "btn btn-small btn-outline",
+                            disabled: !*connected.read() || *printing.read() || parse_hex_bytes(&raw_hex.read()).is_err(),
+                            onclick: move |_| {
+                                if let Ok(bytes) = parse_hex_bytes(&raw_hex.read()) {
+                                    let state = state_send_raw.clone();
+                                    spawn(async move {
+                                        let s = state.lock().await;
+                                        s.cmd_tx.send(BleCommand::SendRaw(bytes)).await.ok();
+                                    });
+                                }
+                            },
+                            "Send"
+                        }
+                    }
+                    if !raw_hex.read().trim().is_empty() {
+                        if let Err(e) = parse_hex_bytes(&raw_hex.read()) {
+                            p { class: "error-text", "{e}" }
+                        }
+                    }
+                }
+
+                p {
+                    class: "status-text",
+                    style: "color: {status_color}",
+                    "{status_text}"
+                }
+
+                if let Some((pct, color)) = battery_display {
+                    p {
+                        class: "battery-text",
+                        style: "color: {color}",
+                        "Battery: {pct}%"
+                    }
+                }
+
+                if let Some((ms, class)) = link_display {
+                    span { class: "{class}", title: "Average BLE write round-trip", "link: {ms}ms" }
+                }
+
+                if let Some(info) = info_display {
+                    p { class: "info-text", "{info}" }
+                }
+
+                if let Some(ref err) = *last_error.read() {
+                    p { class: "error-text", "Error: {err}" }
+                }
+
+                if queued > 0 {
+                    p { class: "status-text",
+                        if queued == 1 { "1 job queued" } else { "{queued} jobs queued" }
+                    }
+                }
+
+                if *printing.read() || queued > 0 {
+                    button {
+                        class: "btn btn-secondary",
+                        onclick: move |_| {
+                            let state = state_cancel.clone();
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::CancelPrint).await.ok();
+                            });
+                        },
+                        "Cancel print"
+                    }
+                }
+
+                if *has_last_job.read() {
+                    button {
+                        class: "btn btn-outline",
+                        disabled: !can_print,
+                        title: "Send the last job's exact bytes again",
+                        onclick: move |_| {
+                            let state = state_reprint.clone();
+                            printing.set(true);
+                            last_error.set(None);
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::Reprint).await.ok();
+                            });
+                        },
+                        "Reprint last"
+                    }
+                }
+                button {
+                    class: "btn btn-outline",
+                    disabled: !can_print,
+                    title: "Print a .ctpjob file exported from this app, exactly as it was rendered",
+                    onclick: move |_| {
+                        let state = state_print_job_file.clone();
+                        spawn(async move {
+                            let Some(file) = rfd::AsyncFileDialog::new()
+                                .add_filter("CTP500 print job", &[jobfile::EXTENSION])
+                                .pick_file()
+                                .await
+                            else {
+                                return;
+                            };
+                            match JobFile::load(file.path()) {
+                                Ok(job) => {
+                                    push_log(LogLevel::Info, format!("Loaded job \"{}\" (exported {})", job.name, job.created));
+                                    printing.set(true);
+                                    last_error.set(None);
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::PrintJobFile(job)).await.ok();
+                                }
+                                Err(e) => last_error.set(Some(e)),
+                            }
+                        });
+                    },
+                    "Print job file…"
+                }
+            }
+
+            // ── Text tools section ────────────────────────────────────────────
+            section { class: "card",
+                h2 { class: "section-title", "Text Tools" }
+
+                // Font selector
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "font-select", "Font" }
+                    select {
+                        id: "font-select",
+                        class: "control-select",
+                        value: "{idx}",
+                        onchange: move |e| {
+                            if let Ok(v) = e.value().parse::<usize>() {
+                                font_idx.set(v);
+                                font_face.set(0);
+                            }
+                        },
+                        for (i, fc) in fonts.read().iter().enumerate() {
+                            option { value: "{i}", selected: i == idx, "{fc.label}" }
+                        }
+                    }
+                    button {
+                        class: "btn btn-outline btn-small",
+                        onclick: move |_| {
+                            spawn(async move {
+                                if let Some(file) = rfd::AsyncFileDialog::new()
+                                    .add_filter("Fonts", &["ttf", "ttc", "otf"])
+                                    .pick_file()
+                                    .await
+                                {
+                                    let css_family = format!("CustomPrinter{}", fonts.read().len());
+                                    match FontChoice::from_file(file.path(), css_family) {
+                                        Ok(fc) => {
+                                            let new_idx = fonts.read().len();
+                                            fonts.write().push(fc);
+                                            font_idx.set(new_idx);
+                                            font_face.set(0);
+                                        }
+                                        Err(e) => last_error.set(Some(e)),
+                                    }
+                                }
+                            });
+                        },
+                        "Add font…"
+                    }
+                }
+
+                // Face selector, only for .ttc collections
+                if face_count > 1 {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "face-select", "Face" }
+                        select {
+                            id: "face-select",
+                            class: "control-select",
+                            value: "{face}",
+                            onchange: move |e| {
+                                if let Ok(v) = e.value().parse::<u32>() {
+                                    font_face.set(v);
+                                }
+                            },
+                            for i in 0..face_count {
+                                option { value: "{i}", selected: i == face, "Face {i}" }
+                            }
+                        }
+                    }
+                }
+
+                // Font size slider
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "font-size-slider",
+                        "Size: {size}px  ({cols} chars/line)"
+                    }
+                    input {
+                        id: "font-size-slider",
+                        class: "control-slider",
+                        r#type: "range",
+                        min: "12",
+                        max: "48",
+                        step: "1",
+                        value: "{size}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<u32>() {
+                                font_size_px.set(v);
+                            }
+                        },
+                    }
+                }
+
+                if let Some(suggested) = suggested_size {
+                    div { class: "columns-warning",
+                        p { "Only {cols} characters fit per line at this size, so most words will wrap." }
+                        if let Some(s) = suggested {
+                            button {
+                                class: "btn btn-small btn-outline",
+                                onclick: move |_| font_size_px.set(s),
+                                "Use {s}px"
+                            }
+                        }
+                    }
+                }
+
+                // Line spacing stepper
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "line-spacing", "Line spacing" }
+                    input {
+                        id: "line-spacing",
+                        class: "control-number",
+                        r#type: "number",
+                        min: "0.8",
+                        max: "3.0",
+                        step: "0.1",
+                        value: "{spacing:.1}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<f32>() {
+                                line_spacing.set(v.clamp(0.8, 3.0));
+                            }
+                        },
+                    }
+                }
+
+                // Alignment radio buttons
+                div { class: "control-row",
+                    span { class: "control-label", "Alignment" }
+                    div { class: "radio-group",
+                        for a in Alignment::ALL {
+                            label { class: "radio-option",
+                                input {
+                                    r#type: "radio",
+                                    name: "alignment",
+                                    checked: a == align,
+                                    onchange: move |_| alignment.set(a),
+                                }
+                                "{a.label()}"
+                            }
+                        }
+                    }
+                }
+
+                label { class: "checkbox-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: keep_indent,
+                        onchange: move |e| preserve_indent.set(e.checked()),
+                    }
+                    "Preserve indentation and tabs"
+                }
+                label { class: "checkbox-row",
+                    title: "Print each line as typed, without word wrap, shrinking the text if a line is too wide. Keeps table columns lined up in a monospace font.",
+                    input {
+                        r#type: "checkbox",
+                        checked: table_mode,
+                        onchange: move |e| preformatted.set(e.checked()),
+                    }
+                    "Preformatted / table (no word wrap)"
+                }
+                label { class: "checkbox-row",
+                    title: "Print the text white on a solid black bar, e.g. for a label header",
+                    input {
+                        r#type: "checkbox",
+                        checked: *highlight.read(),
+                        onchange: move |e| highlight.set(e.checked()),
+                    }
+                    "Highlight (white on black)"
+                }
+                if keep_indent || table_mode {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "tab-width", "Tab width" }
+                        input {
+                            id: "tab-width",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "1",
+                            max: "16",
+                            value: "{tabs}",
+                            oninput: move |e| {
+                                if let Ok(v) = e.value().parse::<usize>() {
+                                    tab_width.set(v.clamp(1, 16));
+                                }
+                            },
+                        }
+                    }
+                }
+
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "left-margin", "Margins (px)" }
+                    input {
+                        id: "left-margin",
+                        class: "control-number",
+                        r#type: "number",
+                        min: "0",
+                        max: "{TextOptions::MAX_MARGIN}",
+                        step: "8",
+                        title: "Left margin",
+                        value: "{left_margin}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<u32>() {
+                                left_margin.set(v.min(TextOptions::MAX_MARGIN));
+                            }
+                        },
+                    }
+                    input {
+                        class: "control-number",
+                        r#type: "number",
+                        min: "0",
+                        max: "{TextOptions::MAX_MARGIN}",
+                        step: "8",
+                        title: "Right margin",
+                        value: "{right_margin}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<u32>() {
+                                right_margin.set(v.min(TextOptions::MAX_MARGIN));
+                            }
+                        },
+                    }
+                }
+
+                details { class: "advanced-panel",
+                    summary { "Advanced: trimming" }
+                    p { class: "info-text",
+                        "Blank rows above and below the text are cut off. Lower this if faint specks stop a page from trimming."
+                    }
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "white-threshold", "White from" }
+                        input {
+                            id: "white-threshold",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "128",
+                            max: "255",
+                            value: "{white_threshold}",
+                            oninput: move |e| {
+                                if let Ok(v) = e.value().parse::<u8>() {
+                                    white_threshold.set(v.max(128));
+                                }
+                            },
+                        }
+                    }
+                }
+
+                details { class: "advanced-panel",
+                    summary { "Advanced: baseline" }
+                    p { class: "info-text",
+                        "Moves the text of the current font down (or up, if negative). Remembered per font."
+                    }
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "baseline-offset", "Offset (px)" }
+                        input {
+                            id: "baseline-offset",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "{-TextOptions::MAX_BASELINE_OFFSET}",
+                            max: "{TextOptions::MAX_BASELINE_OFFSET}",
+                            value: "{baseline_offset()}",
+                            oninput: move |e| {
+                                if let Ok(v) = e.value().parse::<i32>() {
+                                    let v = v.clamp(-TextOptions::MAX_BASELINE_OFFSET, TextOptions::MAX_BASELINE_OFFSET);
+                                    let path = font_path();
+                                    baseline_offsets.with_mut(|offsets| {
+                                        if v == 0 {
+                                            offsets.remove(&path);
+                                        } else {
+                                            offsets.insert(path, v);
+                                        }
+                                    });
+                                }
+                            },
+                        }
+                    }
+                }
+
+                label { class: "checkbox-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: stamp,
+                        onchange: move |e| timestamp_header.set(e.checked()),
+                    }
+                    "Prepend timestamp"
+                }
+                if stamp {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "timestamp-format", "Format" }
+                        input {
+                            id: "timestamp-format",
+                            class: "control-text",
+                            r#type: "text",
+                            title: "strftime format, e.g. %A %d %B %Y, %H:%M",
+                            value: "{timestamp_format}",
+                            oninput: move |e| timestamp_format.set(e.value()),
+                        }
+                    }
+                    if !stamp_format_ok {
+                        p { class: "error-text",
+                            "Invalid format; using {TextOptions::DEFAULT_TIMESTAMP_FORMAT}"
+                        }
+                    }
+                }
+
+                // Textarea sized dynamically to match printer output
+                div { class: "text-input-wrap",
+                    textarea {
+                        class: "text-input",
+                        style: "{textarea_style}",
+                        placeholder: "Type or paste text to print...",
+                        rows: "5",
+                        value: "{text_input}",
+                        oninput: move |e| {
+                            // Typing within TYPING_BURST of the last edit extends the same undo step
+                            let now = Instant::now();
+                            let new_burst = last_text_edit.read().is_none_or(|t| now - t > TYPING_BURST);
+                            if new_burst {
+                                let previous = text_input.read().clone();
+                                text_history.write().push(previous);
+                            }
+                            last_text_edit.set(Some(now));
+                            text_input.set(e.value());
+                        },
+                        onfocus: move |_| text_focused.set(true),
+                        onblur: move |_| text_focused.set(false),
+                        onkeydown: move |e| {
+                            let undo_key = e.key() == Key::Character("z".into())
+                                && (e.modifiers().meta() || e.modifiers().ctrl())
+                                && !e.modifiers().shift();
+                            if undo_key {
+                                e.prevent_default();
+                                undo_text();
+                            }
+                        },
+                    }
+                }
+
+                if let Some(est) = *text_estimate.read() {
+                    p { class: "text-estimate",
+                        "≈ {est.length_cm():.1} cm, {est.lines} lines · {est.words} words"
+                    }
+                }
+
+                button {
+                    class: "btn btn-outline",
                     onclick: move |_| {
                         spawn(async move {
                             if let Some(path) = rfd::AsyncFileDialog::new()
@@ -279,7 +1863,12 @@ pub fn App() -> Element {
                                 .await
                             {
                                 match std::fs::read_to_string(path.path()) {
-                                    Ok(content) => text_input.set(content),
+                                    Ok(content) => {
+                                        let previous = text_input.read().clone();
+                                        text_history.write().push(previous);
+                                        last_text_edit.set(None);
+                                        text_input.set(content);
+                                    }
                                     Err(e) => last_error.set(Some(format!("Failed to read file: {}", e))),
                                 }
                             }
@@ -288,100 +1877,662 @@ pub fn App() -> Element {
                     "Select a text file"
                 }
 
-                button {
-                    class: "btn btn-primary",
-                    disabled: !can_print_text,
-                    onclick: move |_| {
-                        let state = state_print_text.clone();
-                        let text = text_input.read().clone();
-                        let fp = FONT_CHOICES[*font_idx.read()].path.to_string();
-                        let fs = *font_size_px.read() as f32;
-                        printing.set(true);
-                        last_error.set(None);
-                        spawn(async move {
-                            let s = state.lock().await;
-                            s.cmd_tx.send(BleCommand::PrintText {
-                                text,
-                                font_path: fp,
-                                font_size: fs,
-                            }).await.ok();
-                        });
-                    },
-                    "Print your text!"
+                button {
+                    class: "btn btn-outline",
+                    disabled: !text_history.read().can_undo(),
+                    title: "Undo (⌘Z)",
+                    onclick: move |_| undo_text(),
+                    "Undo"
+                }
+
+                label { class: "checkbox-row",
+                    title: "# headings, **bold**, - bullets and --- rules",
+                    input {
+                        r#type: "checkbox",
+                        checked: *markdown.read(),
+                        onchange: move |e| markdown.set(e.checked()),
+                    }
+                    "Markdown"
+                }
+                if !*markdown.read() {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "list-style", "List" }
+                        select {
+                            id: "list-style",
+                            class: "control-select",
+                            onchange: move |e| {
+                                if let Some(style) = ListStyle::ALL.into_iter().find(|s| s.label() == e.value()) {
+                                    list_style.set(style);
+                                }
+                            },
+                            for style in ListStyle::ALL {
+                                option { value: style.label(), selected: style == *list_style.read(), "{style.label()}" }
+                            }
+                        }
+                    }
+                }
+
+                // QR mode: print the text box as a QR code
+                label { class: "checkbox-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: qr_on,
+                        onchange: move |e| qr_mode.set(e.checked()),
+                    }
+                    "Print as QR code"
+                }
+                if qr_on {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "qr-ec", "Error correction" }
+                        select {
+                            id: "qr-ec",
+                            class: "control-select",
+                            onchange: move |e| {
+                                if let Some(level) = e.value().parse::<usize>().ok().and_then(|i| QrEcLevel::ALL.get(i)) {
+                                    qr_ec_level.set(*level);
+                                }
+                            },
+                            for (i, level) in QrEcLevel::ALL.iter().enumerate() {
+                                option { value: "{i}", selected: *level == qr_ec, "{level.label()}" }
+                            }
+                        }
+                    }
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "qr-module", "Module size (px)" }
+                        input {
+                            id: "qr-module",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "1",
+                            max: "16",
+                            value: "{qr_module}",
+                            oninput: move |e| {
+                                if let Ok(v) = e.value().parse::<u32>() {
+                                    qr_module_px.set(v.clamp(1, 16));
+                                }
+                            },
+                        }
+                    }
+                    if let Some(ref err) = qr_error {
+                        p { class: "error-text", "{err}" }
+                    }
+                }
+
+                if !qr_on {
+                    div { class: "control-row",
+                        label { class: "control-label", r#for: "text-copies", "Copies" }
+                        input {
+                            id: "text-copies",
+                            class: "control-number",
+                            r#type: "number",
+                            min: "1",
+                            max: "{MAX_COPIES}",
+                            value: "{text_copies}",
+                            oninput: move |e| {
+                                if let Ok(v) = e.value().parse::<u8>() {
+                                    text_copies.set(v.clamp(1, MAX_COPIES));
+                                }
+                            },
+                        }
+                    }
+                }
+                button {
+                    class: "btn btn-primary",
+                    disabled: !can_print_text,
+                    onclick: move |_| {
+                        let state = state_print_text.clone();
+                        let cmd = text_command();
+                        record_print(&cmd);
+                        printing.set(true);
+                        last_error.set(None);
+                        spawn(async move {
+                            let s = state.lock().await;
+                            s.cmd_tx.send(cmd).await.ok();
+                        });
+                    },
+                    if qr_on { "Print QR code!" } else { "Print your text!" }
+                }
+                button {
+                    class: "btn btn-outline",
+                    disabled: !can_print,
+                    title: "Print the text on the clipboard with the current font settings",
+                    onclick: move |_| {
+                        let text = match clipboard_text() {
+                            Ok(text) => text,
+                            Err(e) => {
+                                last_error.set(Some(e));
+                                return;
+                            }
+                        };
+                        // Show what's printing in the text box, undoable like a file load
+                        let previous = text_input.read().clone();
+                        text_history.write().push(previous);
+                        last_text_edit.set(None);
+                        text_input.set(text.clone());
+                        let cmd = BleCommand::PrintText { text, options: text_options(*font_size_px.read()), copies: *text_copies.read() };
+                        record_print(&cmd);
+                        printing.set(true);
+                        last_error.set(None);
+                        let state = state_print_clipboard.clone();
+                        spawn(async move {
+                            let s = state.lock().await;
+                            s.cmd_tx.send(cmd).await.ok();
+                        });
+                    },
+                    "Print clipboard"
+                }
+                button {
+                    class: "btn btn-small btn-outline",
+                    disabled: text_input.read().trim().is_empty() || qr_error.is_some(),
+                    onclick: move |_| full_preview.set(Some(PreviewSource::Text)),
+                    "Preview at 1:1"
+                }
+                button {
+                    class: "btn btn-small btn-outline",
+                    disabled: text_input.read().trim().is_empty() || qr_error.is_some(),
+                    title: "Save the text as it would print, as a PNG. No printer needed.",
+                    onclick: move |_| save_as_image(PreviewSource::Text),
+                    "Save as image…"
+                }
+                button {
+                    class: "btn btn-small btn-outline",
+                    disabled: text_input.read().trim().is_empty() || qr_error.is_some(),
+                    title: "Save the text as a .ctpjob file that prints identically on another computer",
+                    onclick: move |_| export_job(PreviewSource::Text),
+                    "Export job…"
+                }
+                if full_preview.read().is_none() {
+                    SavedImageNotice { saved: saved_bitmap.read().clone(), source: PreviewSource::Text }
+                }
+            }
+
+            // ── Barcode section ───────────────────────────────────────────────
+            section { class: "card",
+                h2 { class: "section-title", "Barcode" }
+
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "barcode-symbology", "Symbology" }
+                    select {
+                        id: "barcode-symbology",
+                        class: "control-select",
+                        onchange: move |e| {
+                            if let Some(sym) = e.value().parse::<usize>().ok().and_then(|i| Symbology::ALL.get(i)) {
+                                barcode_symbology.set(*sym);
+                            }
+                        },
+                        for (i, sym) in Symbology::ALL.iter().enumerate() {
+                            option { value: "{i}", selected: *sym == symbology, "{sym.label()}" }
+                        }
+                    }
+                }
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "barcode-data", "Data" }
+                    input {
+                        id: "barcode-data",
+                        class: "control-text",
+                        r#type: "text",
+                        placeholder: "{barcode_placeholder}",
+                        value: "{barcode_data}",
+                        oninput: move |e| barcode_data.set(e.value()),
+                    }
+                }
+
+                button {
+                    class: "btn btn-primary",
+                    disabled: !can_print_barcode,
+                    onclick: move |_| {
+                        let state = state_print_barcode.clone();
+                        let cmd = BleCommand::PrintBarcode {
+                            data: barcode_data.read().trim().to_string(),
+                            symbology: *barcode_symbology.read(),
+                            font_path: fonts.read()[*font_idx.read()].path.to_string(),
+                        };
+                        printing.set(true);
+                        last_error.set(None);
+                        spawn(async move {
+                            let s = state.lock().await;
+                            s.cmd_tx.send(cmd).await.ok();
+                        });
+                    },
+                    "Print barcode!"
+                }
+            }
+
+            // ── Image tools section ───────────────────────────────────────────
+            section { class: "card",
+                h2 { class: "section-title", "Image Tools" }
+
+                div { class: "image-preview",
+                    if let Some(ref preview) = *image_preview.read() {
+                        // Drag across the preview to select the region to print
+                        div {
+                            class: "crop-area",
+                            onresize: move |e| {
+                                if let Ok(size) = e.get_border_box_size() {
+                                    preview_size.set((size.width.max(1.0), size.height.max(1.0)));
+                                }
+                            },
+                            onmousedown: move |e| {
+                                let start = image_point(e.element_coordinates(), *preview_size.read(), image_preview);
+                                crop_drag.set(Some(start));
+                                crop_rect.set(CropRect::from_points(start, start));
+                            },
+                            onmousemove: move |e| {
+                                if let Some(start) = *crop_drag.read() {
+                                    let end = image_point(e.element_coordinates(), *preview_size.read(), image_preview);
+                                    crop_rect.set(CropRect::from_points(start, end));
+                                }
+                            },
+                            onmouseup: move |_| {
+                                crop_drag.set(None);
+                                // A click without a drag resets to the whole image
+                                let rect = *crop_rect.read();
+                                if rect.w < 0.01 || rect.h < 0.01 {
+                                    crop_rect.set(CropRect::default());
+                                }
+                            },
+                            onmouseleave: move |_| crop_drag.set(None),
+                            img {
+                                src: "data:image/png;base64,{preview.b64}",
+                                class: "preview-img",
+                                alt: "Image preview",
+                                draggable: "false",
+                            }
+                            if !crop.is_full() {
+                                div { class: "crop-overlay", style: "{crop_style}" }
+                            }
+                        }
+                    } else {
+                        div { class: "preview-placeholder", "No image loaded" }
+                    }
+                }
+
+                button {
+                    class: "btn btn-outline",
+                    onclick: move |_| {
+                        spawn(async move {
+                            if let Some(file) = rfd::AsyncFileDialog::new()
+                                .add_filter("Images", IMAGE_EXTENSIONS)
+                                .add_filter("All files", &["*"])
+                                .pick_file()
+                                .await
+                            {
+                                match open_image(file.path()) {
+                                    Ok((img, note)) => {
+                                        if let Some(note) = note {
+                                            push_log(LogLevel::Info, note);
+                                        }
+                                        current_image.set(Some(img));
+                                        rotation.set(Rotation::None);
+                                        crop_rect.set(CropRect::default());
+                                    }
+                                    Err(e) => {
+                                        last_error.set(Some(format!("Failed to open image: {}", e)));
+                                    }
+                                }
+                            }
+                        });
+                    },
+                    "Select an image file"
+                }
+
+                div { class: "control-row",
+                    input {
+                        class: "control-text",
+                        r#type: "url",
+                        placeholder: "https://example.com/image.png",
+                        value: "{image_url}",
+                        oninput: move |e| image_url.set(e.value()),
+                    }
+                    button {
+                        class: "btn btn-small",
+                        disabled: *fetching_url.read() || image_url.read().trim().is_empty(),
+                        onclick: move |_| {
+                            let url = image_url.read().clone();
+                            fetching_url.set(true);
+                            spawn(async move {
+                                match fetch_image(&url).await {
+                                    Ok(img) => {
+                                        current_image.set(Some(img));
+                                        rotation.set(Rotation::None);
+                                        crop_rect.set(CropRect::default());
+                                        last_error.set(None);
+                                    }
+                                    Err(e) => {
+                                        last_error.set(Some(format!("Failed to load image URL: {}", e)));
+                                    }
+                                }
+                                fetching_url.set(false);
+                            });
+                        },
+                        if *fetching_url.read() { "Fetching…" } else { "Load URL" }
+                    }
+                }
+
+                div { class: "btn-row",
+                    button {
+                        class: "btn btn-small",
+                        disabled: current_image.read().is_none(),
+                        onclick: move |_| {
+                            let next = rotation.read().next();
+                            rotation.set(next);
+                            // The crop was drawn on the old orientation
+                            crop_rect.set(CropRect::default());
+                        },
+                        "Rotate ↻ ({rotation_deg}°)"
+                    }
+                    if !crop.is_full() {
+                        button {
+                            class: "btn btn-small",
+                            onclick: move |_| crop_rect.set(CropRect::default()),
+                            "Reset crop"
+                        }
+                    }
+                }
+
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "fit-mode", "Fit" }
+                    select {
+                        id: "fit-mode",
+                        class: "control-select",
+                        onchange: move |e| {
+                            if let Some(mode) = e.value().parse::<usize>().ok().and_then(|i| FitMode::ALL.get(i)) {
+                                image_options.write().fit = *mode;
+                            }
+                        },
+                        for (i, mode) in FitMode::ALL.iter().enumerate() {
+                            option { value: "{i}", selected: *mode == fit, "{mode.label()}" }
+                        }
+                    }
+                }
+
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "grayscale-method", "Grayscale" }
+                    select {
+                        id: "grayscale-method",
+                        class: "control-select",
+                        title: "How colors become gray; try another method if a colored logo loses detail",
+                        onchange: move |e| {
+                            if let Some(method) = e.value().parse::<usize>().ok().and_then(|i| GrayscaleMethod::ALL.get(i)) {
+                                image_options.write().grayscale = *method;
+                            }
+                        },
+                        for (i, method) in GrayscaleMethod::ALL.iter().enumerate() {
+                            option { value: "{i}", selected: *method == grayscale, "{method.label()}" }
+                        }
+                    }
+                }
+
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "dither-mode", "Dither" }
+                    select {
+                        id: "dither-mode",
+                        class: "control-select",
+                        onchange: move |e| {
+                            if let Some(mode) = e.value().parse::<usize>().ok().and_then(|i| DitherMode::ALL.get(i)) {
+                                image_options.write().dither = *mode;
+                            }
+                        },
+                        for (i, mode) in DitherMode::ALL.iter().enumerate() {
+                            option { value: "{i}", selected: *mode == dither, "{mode.label()}" }
+                        }
+                    }
+                }
+
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "segment-height", "Split every" }
+                    select {
+                        id: "segment-height",
+                        class: "control-select",
+                        onchange: move |e| segment_height.set(e.value().parse::<u32>().ok().filter(|&h| h > 0)),
+                        for (h, label) in SEGMENT_HEIGHTS {
+                            option { value: "{h}", selected: segment_height.read().unwrap_or(0) == h, "{label}" }
+                        }
+                    }
+                }
+
+                // Brightness / contrast sliders
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "brightness-slider",
+                        "Brightness: {brightness:+}"
+                    }
+                    input {
+                        id: "brightness-slider",
+                        class: "control-slider",
+                        r#type: "range",
+                        min: "-100",
+                        max: "100",
+                        step: "5",
+                        value: "{brightness}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<i32>() {
+                                image_options.write().brightness = v;
+                            }
+                        },
+                    }
+                }
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "contrast-slider",
+                        "Contrast: {contrast:.1}×"
+                    }
+                    input {
+                        id: "contrast-slider",
+                        class: "control-slider",
+                        r#type: "range",
+                        min: "0.5",
+                        max: "2.5",
+                        step: "0.1",
+                        value: "{contrast}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<f32>() {
+                                image_options.write().contrast = v;
+                            }
+                        },
+                    }
+                }
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "sharpen-slider",
+                        title: "Unsharp mask after scaling to the paper width, to recover detail the resize softened",
+                        if sharpen > 0.0 { "Sharpen: {sharpen:.1}" } else { "Sharpen: off" }
+                    }
+                    input {
+                        id: "sharpen-slider",
+                        class: "control-slider",
+                        r#type: "range",
+                        min: "0",
+                        max: "3",
+                        step: "0.1",
+                        value: "{sharpen}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<f32>() {
+                                image_options.write().sharpen = v;
+                            }
+                        },
+                    }
+                }
+
+                div { class: "btn-row",
+                    button {
+                        class: if auto_levels { "btn btn-small btn-primary" } else { "btn btn-small btn-outline" },
+                        title: "Stretch the image's tones to the full black-to-white range before printing",
+                        onclick: move |_| {
+                            let mut options = image_options.write();
+                            options.auto_levels = !options.auto_levels;
+                        },
+                        if auto_levels { "Auto levels: on" } else { "Auto levels" }
+                    }
+                }
+
+                label { class: "checkbox-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: image_options.read().invert,
+                        onchange: move |e| image_options.write().invert = e.checked(),
+                    }
+                    "Invert colors (white on black)"
                 }
-            }
-
-            // ── Image tools section ───────────────────────────────────────────
-            section { class: "card",
-                h2 { class: "section-title", "Image Tools" }
 
-                div { class: "image-preview",
-                    if let Some(ref b64) = *image_preview_b64.read() {
-                        img {
-                            src: "data:image/png;base64,{b64}",
-                            class: "preview-img",
-                            alt: "Image preview",
+                if let Some(cm) = tall_image_cm {
+                    div { class: "tall-warning",
+                        p { "This image will print about {cm:.0} cm long." }
+                        if needs_tall_confirm {
+                            button {
+                                class: "btn btn-small btn-outline",
+                                onclick: move |_| tall_confirmed.set(true),
+                                "Print it anyway"
+                            }
                         }
-                    } else {
-                        div { class: "preview-placeholder", "No image loaded" }
                     }
                 }
 
-                button {
-                    class: "btn btn-outline",
-                    onclick: move |_| {
-                        spawn(async move {
-                            if let Some(file) = rfd::AsyncFileDialog::new()
-                                .add_filter("Images", &["png", "jpg", "jpeg", "bmp"])
-                                .add_filter("All files", &["*"])
-                                .pick_file()
-                                .await
-                            {
-                                match image::open(file.path()) {
-                                    Ok(img) => {
-                                        let thumb = img.thumbnail(300, 100);
-                                        let mut buf = Vec::new();
-                                        if thumb.write_to(
-                                            &mut std::io::Cursor::new(&mut buf),
-                                            image::ImageFormat::Png,
-                                        ).is_ok() {
-                                            use base64::Engine;
-                                            let b64 = base64::engine::general_purpose::STANDARD.encode(&buf);
-                                            image_preview_b64.set(Some(b64));
-                                        }
-                                        current_image.set(Some(img));
-                                    }
-                                    Err(e) => {
-                                        last_error.set(Some(format!("Failed to open image: {}", e)));
-                                    }
-                                }
+                div { class: "control-row",
+                    label { class: "control-label", r#for: "image-copies", "Copies" }
+                    input {
+                        id: "image-copies",
+                        class: "control-number",
+                        r#type: "number",
+                        min: "1",
+                        max: "{MAX_COPIES}",
+                        value: "{image_copies}",
+                        oninput: move |e| {
+                            if let Ok(v) = e.value().parse::<u8>() {
+                                image_copies.set(v.clamp(1, MAX_COPIES));
                             }
-                        });
-                    },
-                    "Select an image file"
+                        },
+                    }
                 }
-
                 button {
                     class: "btn btn-primary",
                     disabled: !can_print_image,
                     onclick: move |_| {
                         let state = state_print_image.clone();
-                        if let Some(img) = current_image.read().clone() {
+                        if let Some(cmd) = image_command() {
+                            record_print(&cmd);
                             printing.set(true);
                             last_error.set(None);
                             spawn(async move {
                                 let s = state.lock().await;
-                                s.cmd_tx.send(BleCommand::PrintImage(img)).await.ok();
+                                s.cmd_tx.send(cmd).await.ok();
                             });
                         }
                     },
                     "Print your image!"
                 }
+                button {
+                    class: "btn btn-small btn-outline",
+                    disabled: current_image.read().is_none(),
+                    onclick: move |_| full_preview.set(Some(PreviewSource::Image)),
+                    "Preview at 1:1"
+                }
+                button {
+                    class: "btn btn-small btn-outline",
+                    disabled: current_image.read().is_none(),
+                    title: "Save the image as it would print, as a PNG. No printer needed.",
+                    onclick: move |_| save_as_image(PreviewSource::Image),
+                    "Save as image…"
+                }
+                button {
+                    class: "btn btn-small btn-outline",
+                    disabled: current_image.read().is_none(),
+                    title: "Save the image as a .ctpjob file that prints identically on another computer",
+                    onclick: move |_| export_job(PreviewSource::Image),
+                    "Export job…"
+                }
+                if full_preview.read().is_none() {
+                    SavedImageNotice { saved: saved_bitmap.read().clone(), source: PreviewSource::Image }
+                }
+
+                // ── Batch: several images printed as separate jobs ───────────
+                h3 { class: "subsection-title", "Batch" }
+                if batch_len > 0 {
+                    div { class: "batch-list",
+                        for (i, thumb) in batch_thumbs.read().iter().enumerate() {
+                            div {
+                                class: "batch-item",
+                                draggable: "true",
+                                ondragstart: move |_| batch_drag.set(Some(i)),
+                                ondragover: move |e| e.prevent_default(),
+                                ondrop: move |e| {
+                                    e.prevent_default();
+                                    if let Some(from) = batch_drag.take() {
+                                        if from != i {
+                                            batch_images.with_mut(|v| {
+                                                let img = v.remove(from);
+                                                v.insert(i, img);
+                                            });
+                                        }
+                                    }
+                                },
+                                ondragend: move |_| batch_drag.set(None),
+                                img { src: "data:image/png;base64,{thumb}", alt: "Batch image {i + 1}" }
+                                button {
+                                    class: "batch-remove",
+                                    title: "Remove",
+                                    onclick: move |_| {
+                                        batch_images.with_mut(|v| {
+                                            v.remove(i);
+                                        });
+                                    },
+                                    "×"
+                                }
+                            }
+                        }
+                    }
+                }
+                div { class: "btn-row",
+                    button {
+                        class: "btn btn-outline",
+                        onclick: move |_| {
+                            spawn(async move {
+                                if let Some(files) = rfd::AsyncFileDialog::new()
+                                    .add_filter("Images", IMAGE_EXTENSIONS)
+                                    .pick_files()
+                                    .await
+                                {
+                                    for file in files {
+                                        match open_image(file.path()) {
+                                            Ok((img, note)) => {
+                                                if let Some(note) = note {
+                                                    push_log(LogLevel::Info, note);
+                                                }
+                                                batch_images.write().push(img);
+                                            }
+                                            Err(e) => {
+                                                last_error.set(Some(format!("Failed to open image: {}", e)));
+                                            }
+                                        }
+                                    }
+                                }
+                            });
+                        },
+                        "Add images…"
+                    }
+                    button {
+                        class: "btn btn-primary",
+                        disabled: !can_print_batch,
+                        onclick: move |_| {
+                            let state = state_print_batch.clone();
+                            let images = batch_images.read().clone();
+                            let options = *raster_options.read();
+                            printing.set(true);
+                            last_error.set(None);
+                            spawn(async move {
+                                let s = state.lock().await;
+                                s.cmd_tx.send(BleCommand::PrintBatch { images, options }).await.ok();
+                            });
+                        },
+                        "Print all ({batch_len})"
+                    }
+                }
 
                 if let Some((sent, total)) = progress_display {
                     div { class: "progress-wrap",
                         p { class: "progress-label",
-                            "Sending... {sent}/{total} bytes"
+                            if let Some((copy, copies)) = *copy_progress.read() {
+                                "Sending copy {copy} of {copies}... {sent}/{total} bytes"
+                            } else {
+                                "Sending... {sent}/{total} bytes"
+                            }
                         }
                         div { class: "progress-bar-bg",
                             div {
@@ -393,28 +2544,428 @@ pub fn App() -> Element {
                 }
             }
 
+            // ── 1:1 preview panel ─────────────────────────────────────────────
+            if let Some(source) = *full_preview.read() {
+                div { class: "modal-backdrop",
+                    div { class: "modal",
+                        div { class: "modal-header",
+                            h2 { class: "section-title", "Print preview (1:1)" }
+                            button {
+                                class: "btn btn-small btn-secondary",
+                                onclick: move |_| {
+                                    full_preview.set(None);
+                                    saved_bitmap.set(None);
+                                },
+                                "Close"
+                            }
+                        }
+                        label { class: "checkbox-row",
+                            input {
+                                r#type: "checkbox",
+                                checked: *show_ruler.read(),
+                                onchange: move |e| show_ruler.set(e.checked()),
+                            }
+                            "Show ruler"
+                        }
+                        if *show_ruler.read() {
+                            p { class: "info-text",
+                                "Blue ticks every {RULER_STEP_PX} dots; red ticks every mm, longer at 5 and 10 mm. The ruler is not printed."
+                            }
+                        }
+                        div { class: "full-preview-scroll",
+                            match &*full_preview_pages.read() {
+                                Ok(pages) => rsx! {
+                                    for page in pages.iter() {
+                                        img { class: "full-preview-img", src: "data:image/png;base64,{page}" }
+                                    }
+                                },
+                                Err(e) => rsx! { p { class: "error-text", "{e}" } },
+                            }
+                        }
+                        button {
+                            class: "btn btn-primary",
+                            disabled: !can_print || (source == PreviewSource::Image && needs_tall_confirm),
+                            onclick: move |_| {
+                                let state = state_print_preview.clone();
+                                let cmd = match source {
+                                    PreviewSource::Text => Some(text_command()),
+                                    PreviewSource::Image => image_command(),
+                                };
+                                full_preview.set(None);
+                                saved_bitmap.set(None);
+                                if let Some(cmd) = cmd {
+                                    record_print(&cmd);
+                                    printing.set(true);
+                                    last_error.set(None);
+                                    spawn(async move {
+                                        let s = state.lock().await;
+                                        s.cmd_tx.send(cmd).await.ok();
+                                    });
+                                }
+                            },
+                            "Print this"
+                        }
+                        button {
+                            class: "btn btn-outline",
+                            title: "Save the exact black-and-white bitmap that would be sent, as a PNG",
+                            onclick: move |_| save_as_image(source),
+                            "Save bitmap…"
+                        }
+                        SavedImageNotice { saved: saved_bitmap.read().clone(), source }
+                    }
+                }
+            }
+
+            // ── Busy overlay: block the UI while scanning/connecting ─────────
+            // The BLE task handles one command at a time, so anything clicked
+            // now would only queue up behind the connection attempt
+            if *scanning.read() {
+                div { class: "busy-overlay",
+                    div { class: "busy-box",
+                        div { class: "spinner" }
+                        p { "Connecting to printer…" }
+                        if *direct_connect.read() {
+                            p { class: "info-text", "Connecting can take up to 20 seconds" }
+                        } else {
+                            p { class: "info-text", "Scanning can take up to 10 seconds" }
+                        }
+                        button {
+                            class: "btn btn-small btn-outline",
+                            onclick: move |_| {
+                                let state = state_stop_scan.clone();
+                                spawn(async move {
+                                    let s = state.lock().await;
+                                    s.cmd_tx.send(BleCommand::StopScan).await.ok();
+                                });
+                            },
+                            "Stop"
+                        }
+                    }
+                }
+            }
+
+            // ── History section ───────────────────────────────────────────────
+            section { class: "card",
+                h2 { class: "section-title", "History" }
+                if history.read().is_empty() {
+                    p { class: "info-text", "Finished text and image prints appear here; click one to load it again." }
+                } else {
+                    div { class: "history-list",
+                        for entry in history.read().iter().cloned() {
+                            button {
+                                class: "history-item",
+                                title: "Load this print again",
+                                onclick: {
+                                    let source = entry.source.clone();
+                                    move |_| match &source {
+                                        HistorySource::Text(text) => {
+                                            text_input.set(text.clone());
+                                            last_text_edit.set(None);
+                                        }
+                                        HistorySource::Image { img, crop } => {
+                                            current_image.set(Some(DynamicImage::clone(img)));
+                                            rotation.set(Rotation::None);
+                                            crop_rect.set(*crop);
+                                        }
+                                    }
+                                },
+                                img { src: "data:image/png;base64,{entry.thumbnail}", alt: "{entry.source.label()}" }
+                                span { class: "history-kind", "{entry.source.label()}" }
+                                span { class: "history-meta", "{entry.time} · {entry.bytes} bytes" }
+                            }
+                        }
+                    }
+                }
+            }
+
             // ── Activity log section ──────────────────────────────────────────
             section { class: "card",
-                h2 { class: "section-title", "Activity Log" }
+                div { class: "log-header",
+                    h2 { class: "section-title", "Activity Log" }
+                    select {
+                        class: "log-filter",
+                        title: "Hide entries below this level",
+                        onchange: move |e| {
+                            if let Some(level) = LogLevel::ALL.into_iter().find(|l| l.label() == e.value()) {
+                                log_filter.set(level);
+                            }
+                        },
+                        for level in LogLevel::ALL {
+                            option { value: level.label(), selected: level == *log_filter.read(), "{level.label()}" }
+                        }
+                    }
+                }
+                input {
+                    class: "log-search",
+                    r#type: "search",
+                    placeholder: "Filter log…",
+                    value: "{log_search}",
+                    oninput: move |e| log_search.set(e.value()),
+                }
                 div { class: "log-box",
                     id: "log-scroll",
-                    for entry in log_entries.read().iter() {
-                        p { class: "log-entry", "{entry}" }
+                    {
+                        let query = log_search.read().to_lowercase();
+                        let min_level = *log_filter.read();
+                        let entries = log_entries.read();
+                        let shown: Vec<&(LogLevel, String)> = entries
+                            .iter()
+                            .filter(|(level, entry)| *level >= min_level && entry.to_lowercase().contains(&query))
+                            .collect();
+                        if shown.is_empty() && !entries.is_empty() {
+                            rsx! { p { class: "log-entry log-debug", "No matching entries" } }
+                        } else {
+                            rsx! {
+                                for (level, entry) in shown {
+                                    p { class: "log-entry {level.css_class()}", "{entry}" }
+                                }
+                            }
+                        }
                     }
                 }
             }
         }
 
-        // Auto-scroll log to bottom whenever entries change
-        script {
-            r#"
-            (function() {{
-                var el = document.getElementById('log-scroll');
-                if (el) el.scrollTop = el.scrollHeight;
-            }})();
-            "#
+        // Auto-scroll log to bottom whenever entries change — but not while
+        // searching, so new entries don't yank the view away from a match
+        if log_search.read().is_empty() {
+            script {
+                r#"
+                (function() {{
+                    var el = document.getElementById('log-scroll');
+                    if (el) el.scrollTop = el.scrollHeight;
+                }})();
+                "#
+            }
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// Which print path the 1:1 preview panel is showing.
+#[derive(Clone, Copy, PartialEq)]
+enum PreviewSource {
+    Text,
+    Image,
+}
+
+/// A finished print in the History card.
+#[derive(Clone)]
+struct HistoryEntry {
+    source: HistorySource,
+    /// Top of the first page as a small PNG (base64).
+    thumbnail: String,
+    /// Local time the print finished, HH:MM:SS.
+    time: String,
+    /// Bytes sent over all its pages and copies.
+    bytes: usize,
+}
+
+/// What a history entry loads back into the editor.
+#[derive(Clone)]
+enum HistorySource {
+    Text(String),
+    /// The image as printed (rotation applied) and its crop.
+    Image { img: Arc<DynamicImage>, crop: CropRect },
+}
+
+impl HistorySource {
+    fn label(&self) -> &'static str {
+        match self {
+            HistorySource::Text(_) => "Text",
+            HistorySource::Image { .. } => "Image",
+        }
+    }
+}
+
+/// A history entry (not yet finished) for a text, QR or image print.
+fn history_entry(cmd: &BleCommand) -> Option<HistoryEntry> {
+    let (source, page) = match cmd {
+        BleCommand::PrintText { text, options, .. } => {
+            // Only the top of page 1 shows, so a long text isn't rendered in full again
+            let page = render_text_to_images(thumbnail_text(text), options).ok()?.into_iter().next()?;
+            (HistorySource::Text(text.clone()), page)
+        }
+        BleCommand::PrintQr { text, ec_level, module_px } => {
+            (HistorySource::Text(text.clone()), render_qr(text, *ec_level, *module_px).ok()?)
+        }
+        BleCommand::PrintImage { img, crop, .. } => {
+            let page = DynamicImage::clone(&crop.apply(img.clone()));
+            (HistorySource::Image { img: img.clone(), crop: *crop }, page)
+        }
+        _ => return None,
+    };
+    let top = page.crop_imm(0, 0, page.width(), page.height().min(page.width()));
+    Some(HistoryEntry { source, thumbnail: thumbnail_png_b64(&top), time: String::new(), bytes: 0 })
+}
+
+/// The start of `text`, cut to THUMBNAIL_TEXT_LINES lines and THUMBNAIL_TEXT_CHARS characters.
+fn thumbnail_text(text: &str) -> &str {
+    let end = text.char_indices().nth(THUMBNAIL_TEXT_CHARS).map_or(text.len(), |(i, _)| i);
+    let text = &text[..end];
+    text.match_indices('\n').nth(THUMBNAIL_TEXT_LINES).map_or(text, |(i, _)| &text[..i])
+}
+
+/// Result of the last "Save as image", shown only next to the job it was for.
+#[component]
+fn SavedImageNotice(saved: Option<(PreviewSource, Result<PathBuf, String>)>, source: PreviewSource) -> Element {
+    match saved {
+        Some((from, Ok(path))) if from == source => rsx! { p { class: "info-text", "Saved to {path.display()}" } },
+        Some((from, Err(e))) if from == source => rsx! { p { class: "error-text", "{e}" } },
+        _ => rsx! {},
+    }
+}
+
+/// Render a print command through the same pipeline the BLE task uses,
+/// returning each page as the full-resolution 1-bit bitmap that is sent.
+fn render_command_bitmaps(cmd: BleCommand) -> Result<Vec<GrayImage>, String> {
+    let (images, options) = match cmd {
+        BleCommand::PrintText { text, options, .. } => (render_text_to_images(&text, &options)?, RasterOptions::default()),
+        BleCommand::PrintQr { text, ec_level, module_px } => {
+            (vec![render_qr(&text, ec_level, module_px)?], RasterOptions::default())
+        }
+        BleCommand::PrintImage { img, options, crop, segment_height, .. } => {
+            let img = crop.apply(img);
+            let segments = match segment_height {
+                Some(max_height) => crate::escpos::split_into_segments(&img, &options, max_height),
+                None => vec![Arc::unwrap_or_clone(img)],
+            };
+            (segments, options)
+        }
+        _ => return Ok(Vec::new()),
+    };
+    Ok(images.iter().map(|img| crate::escpos::raster_preview(img, &options).0).collect())
+}
+
+/// Each page of a print command as a PNG (base64) for the 1:1 preview,
+/// optionally with the layout ruler drawn over it.
+fn render_command_preview(cmd: BleCommand, ruler: bool) -> Result<Vec<String>, String> {
+    render_command_bitmaps(cmd)?
+        .into_iter()
+        .map(|raster| {
+            let page = if ruler { DynamicImage::ImageRgb8(draw_ruler(&raster)) } else { DynamicImage::ImageLuma8(raster) };
+            png_b64(&page).ok_or_else(|| "Failed to encode preview".to_string())
+        })
+        .collect()
+}
+
+/// Ask where to save, then write all pages of a print command as one PNG
+/// of exactly the bits that would be sent. Returns None if cancelled.
+async fn save_command_bitmap(cmd: BleCommand) -> Option<Result<PathBuf, String>> {
+    let file = rfd::AsyncFileDialog::new()
+        .add_filter("PNG image", &["png"])
+        .set_file_name("ctp500-bitmap.png")
+        .save_file()
+        .await?;
+    let path = file.path().to_path_buf();
+    let result = render_command_bitmaps(cmd).and_then(|pages| {
+        if pages.is_empty() {
+            return Err("Nothing to save".to_string());
+        }
+        stack_pages(&pages).save(&path).map_err(|e| format!("Failed to save bitmap: {}", e))
+    });
+    Some(result.map(|()| path))
+}
+
+/// Ask where to save, then write a print command's rendered pages as a
+/// `.ctpjob` file. Returns None if cancelled.
+async fn export_command_job(cmd: BleCommand) -> Option<Result<PathBuf, String>> {
+    let file = rfd::AsyncFileDialog::new()
+        .add_filter("CTP500 print job", &[jobfile::EXTENSION])
+        .set_file_name(format!("ctp500-job.{}", jobfile::EXTENSION))
+        .save_file()
+        .await?;
+    let path = file.path().to_path_buf();
+    let name = match &cmd {
+        BleCommand::PrintText { text, .. } => text.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim().chars().take(40).collect(),
+        _ => "Image".to_string(),
+    };
+    let result = render_command_bitmaps(cmd).and_then(|pages| {
+        if pages.is_empty() {
+            return Err("Nothing to export".to_string());
         }
+        JobFile::from_bitmaps(&name, &pages).save(&path)
+    });
+    Some(result.map(|()| path))
+}
+
+/// Encode an image as a base64 PNG for an <img> data URL.
+fn png_b64(img: &DynamicImage) -> Option<String> {
+    use base64::Engine;
+
+    let mut buf = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png).ok()?;
+    Some(base64::engine::general_purpose::STANDARD.encode(&buf))
+}
+
+/// Preview of a loaded image as it will print, plus where the image sits
+/// horizontally on the paper (as fractions of PRINTER_WIDTH).
+#[derive(Clone, PartialEq)]
+struct ImagePreview {
+    b64: String,
+    x_frac: f32,
+    w_frac: f32,
+    /// Shown flipped left to right, so crop coordinates are too.
+    mirrored: bool,
+}
+
+impl ImagePreview {
+    /// Run the same fit/threshold pipeline as printing and encode it as PNG.
+    fn render(img: &DynamicImage, options: &RasterOptions) -> Option<Self> {
+        let (raster, (x, w)) = crate::escpos::raster_preview(img, options);
+        let paper = raster.width() as f32;
+        Some(Self {
+            b64: png_b64(&DynamicImage::ImageLuma8(raster))?,
+            x_frac: x as f32 / paper,
+            w_frac: w as f32 / paper,
+            mirrored: options.mirror,
+        })
+    }
+}
+
+/// Text on the system clipboard, or a message saying why there is none.
+fn clipboard_text() -> Result<String, String> {
+    let text = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()).map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => "The clipboard doesn't contain text".to_string(),
+        e => format!("Couldn't read the clipboard: {}", e),
+    })?;
+    if text.trim().is_empty() {
+        return Err("The clipboard is empty".to_string());
     }
+    Ok(text)
+}
+
+/// Small PNG thumbnail (base64) for the batch list.
+fn thumbnail_png_b64(img: &DynamicImage) -> String {
+    png_b64(&img.thumbnail(64, 64)).unwrap_or_default()
+}
+
+/// Convert a mouse position on the preview element into normalized image
+/// coordinates, discounting the paper margins around the image and undoing
+/// any mirroring.
+fn image_point(
+    p: dioxus::html::geometry::ElementPoint,
+    (w, h): (f64, f64),
+    preview: Memo<Option<ImagePreview>>,
+) -> (f32, f32) {
+    let (span_x, span_w, mirrored) = preview.read().as_ref().map_or((0.0, 1.0, false), |p| (p.x_frac, p.w_frac, p.mirrored));
+    let x = ((p.x / w) as f32 - span_x) / span_w;
+    (if mirrored { 1.0 - x } else { x }, (p.y / h) as f32)
+}
+
+/// Build @font-face rules so the textarea can preview user-added fonts.
+fn custom_font_faces(fonts: &[FontChoice]) -> String {
+    fonts
+        .iter()
+        .map(|fc| {
+            format!(
+                "@font-face {{ font-family: \"{}\"; src: url(\"file://{}\"); }}\n",
+                fc.css_family,
+                fc.path.replace(' ', "%20").replace('"', "%22"),
+            )
+        })
+        .collect()
 }
 
 // ── Embedded CSS ──────────────────────────────────────────────────────────────
@@ -458,13 +3009,39 @@ const STYLES: &str = r#"
     font-weight: normal; font-style: normal;
 }
 
+/* Theme colors; DARK_THEME overrides them. Printed output (previews,
+   thumbnails) keeps white paper in both themes. */
+:root {
+    --bg: #f0f0f0;
+    --surface: #ffffff;
+    --input-bg: #ffffff;
+    --well: #fafafa;
+    --paper: #ffffff;
+    --fill: #e5e5ea;
+    --border: #d1d1d6;
+    --text: #1a1a1a;
+    --text-strong: #333;
+    --text-muted: #555;
+    --text-faint: #aaa;
+    --accent: #0071e3;
+    --accent-hover: #0064cc;
+    --accent-tint: #e8f0fc;
+    --ok-bg: #e6f4e6;
+    --ok-text: #2a7a2a;
+    --warn-bg: #fff4e5;
+    --warn-text: #8a4b00;
+    --error: #cc0000;
+    --error-bg: #fdecea;
+    --veil: rgba(255, 255, 255, 0.6);
+}
+
 *, *::before, *::after { box-sizing: border-box; margin: 0; padding: 0; }
 
 body {
     font-family: -apple-system, BlinkMacSystemFont, "Helvetica Neue", sans-serif;
     font-size: 14px;
-    background: #f0f0f0;
-    color: #1a1a1a;
+    background: var(--bg);
+    color: var(--text);
     min-height: 100vh;
 }
 
@@ -476,9 +3053,10 @@ body {
     flex-direction: column;
     gap: 10px;
 }
+.container:focus { outline: none; }
 
 .card {
-    background: #ffffff;
+    background: var(--surface);
     border-radius: 10px;
     padding: 14px;
     box-shadow: 0 1px 4px rgba(0,0,0,0.10);
@@ -490,7 +3068,7 @@ body {
 .section-title {
     font-size: 13px;
     font-weight: 600;
-    color: #555;
+    color: var(--text-muted);
     text-transform: uppercase;
     letter-spacing: 0.04em;
     margin-bottom: 2px;
@@ -509,21 +3087,67 @@ body {
     transition: opacity 0.15s, background 0.15s;
 }
 .btn:disabled { opacity: 0.45; cursor: not-allowed; }
-.btn-primary  { background: #0071e3; color: #fff; }
-.btn-primary:hover:not(:disabled)  { background: #0064cc; }
-.btn-secondary { background: #e5e5ea; color: #1a1a1a; }
-.btn-secondary:hover:not(:disabled) { background: #d1d1d6; }
-.btn-outline  { background: transparent; color: #0071e3;
-                border: 1.5px solid #0071e3; }
-.btn-outline:hover:not(:disabled)  { background: #e8f0fc; }
+.btn-primary  { background: var(--accent); color: #fff; }
+.btn-primary:hover:not(:disabled)  { background: var(--accent-hover); }
+.btn-secondary { background: var(--fill); color: var(--text); }
+.btn-secondary:hover:not(:disabled) { background: var(--border); }
+.btn-outline  { background: transparent; color: var(--accent);
+                border: 1.5px solid var(--accent); }
+.btn-outline:hover:not(:disabled)  { background: var(--accent-tint); }
+
+.btn-small { width: auto; padding: 5px 10px; font-size: 12px; }
 
 .btn-row { display: flex; gap: 8px; }
 .btn-row .btn { flex: 1; }
 
+/* Checkbox options */
+.checkbox-row {
+    display: flex;
+    align-items: center;
+    gap: 6px;
+    font-size: 12px;
+    color: var(--text-muted);
+    cursor: pointer;
+}
+.checkbox-row input { accent-color: var(--accent); cursor: pointer; }
+
 /* Status */
 .status-text { font-size: 13px; font-weight: 500; }
 .battery-text { font-size: 13px; }
-.error-text { font-size: 12px; color: #cc0000; }
+.link-badge {
+    align-self: flex-start;
+    font-size: 11px;
+    padding: 1px 6px;
+    border-radius: 8px;
+    background: var(--ok-bg);
+    color: var(--ok-text);
+}
+.link-badge.link-slow { background: var(--error-bg); color: var(--error); }
+.info-text { font-size: 12px; color: var(--text-muted); }
+.advanced-panel { display: flex; flex-direction: column; gap: 6px; font-size: 12px; color: var(--text-muted); }
+.advanced-panel summary { cursor: pointer; }
+.tall-warning, .columns-warning {
+    display: flex;
+    align-items: center;
+    justify-content: space-between;
+    gap: 8px;
+    padding: 6px 10px;
+    border-radius: 6px;
+    background: var(--warn-bg);
+    color: var(--warn-text);
+    font-size: 12px;
+}
+.text-estimate { font-size: 12px; color: var(--text-muted); margin-top: -4px; }
+.error-text { font-size: 12px; color: var(--error); }
+.fault-banner {
+    background: #cc0000;
+    color: #fff;
+    font-size: 14px;
+    font-weight: 600;
+    padding: 10px 14px;
+    border-radius: 8px;
+}
+.low-battery-banner { background: #cc7700; }
 
 /* Font / size controls */
 .control-row {
@@ -533,24 +3157,48 @@ body {
 }
 .control-label {
     font-size: 12px;
-    color: #555;
+    color: var(--text-muted);
     white-space: nowrap;
     min-width: 140px;
 }
 .control-select {
     flex: 1;
     padding: 5px 8px;
-    border: 1.5px solid #d1d1d6;
+    border: 1.5px solid var(--border);
+    border-radius: 6px;
+    font-size: 13px;
+    background: var(--input-bg);
+    color: var(--text);
+    cursor: pointer;
+}
+.control-text {
+    flex: 1;
+    min-width: 0;
+    padding: 5px 8px;
+    border: 1.5px solid var(--border);
+    border-radius: 6px;
+    font-size: 13px;
+}
+.control-number {
+    width: 64px;
+    padding: 4px 6px;
+    border: 1.5px solid var(--border);
     border-radius: 6px;
     font-size: 13px;
-    background: #fff;
-    color: #1a1a1a;
+}
+.radio-group { display: flex; gap: 12px; }
+.radio-option {
+    display: flex;
+    align-items: center;
+    gap: 4px;
+    font-size: 13px;
     cursor: pointer;
 }
+.radio-option input { accent-color: var(--accent); cursor: pointer; }
 .control-slider {
     flex: 1;
     cursor: pointer;
-    accent-color: #0071e3;
+    accent-color: var(--accent);
 }
 
 /* Text input — width and font are set dynamically via inline style */
@@ -563,7 +3211,7 @@ body {
        so the textarea exactly mirrors what will be rendered on the 384px printer. */
     box-sizing: content-box;
     padding: 8px 10px;
-    border: 1.5px solid #d1d1d6;
+    border: 1.5px solid var(--border);
     border-radius: 7px;
     line-height: 1.45;
     resize: none;
@@ -571,33 +3219,133 @@ body {
     transition: border-color 0.15s;
     display: block;
 }
-.text-input:focus { border-color: #0071e3; }
+.text-input:focus { border-color: var(--accent); }
 
 /* Image preview */
 .image-preview {
     width: 100%;
     height: 110px;
-    border: 1.5px solid #d1d1d6;
+    border: 1.5px solid var(--border);
     border-radius: 7px;
     display: flex;
     align-items: center;
     justify-content: center;
     overflow: hidden;
-    background: #fafafa;
+    background: var(--well);
+}
+.preview-img { max-width: 100%; max-height: 108px; object-fit: contain; display: block; pointer-events: none; }
+.crop-area { position: relative; cursor: crosshair; user-select: none; }
+.crop-overlay {
+    position: absolute;
+    border: 1.5px dashed var(--accent);
+    background: rgba(0, 113, 227, 0.15);
+    pointer-events: none;
+}
+.preview-placeholder { color: var(--text-faint); font-size: 13px; }
+
+/* 1:1 preview panel */
+.modal-backdrop {
+    position: fixed; inset: 0;
+    background: rgba(0, 0, 0, 0.35);
+    display: flex; align-items: center; justify-content: center;
+    z-index: 10;
+}
+.modal {
+    background: var(--surface);
+    border-radius: 12px;
+    padding: 16px;
+    display: flex; flex-direction: column; gap: 12px;
+    max-height: 90vh;
+}
+.modal-header { display: flex; align-items: center; justify-content: space-between; gap: 12px; }
+.full-preview-scroll {
+    overflow-y: auto;
+    border: 1.5px solid var(--border);
+    background: var(--paper);
+    display: flex; flex-direction: column;
+}
+.full-preview-img { width: 384px; image-rendering: pixelated; display: block; }
+
+/* Busy overlay while scanning/connecting */
+.busy-overlay {
+    position: fixed; inset: 0;
+    background: var(--veil);
+    display: flex; align-items: center; justify-content: center;
+    z-index: 20;
+    cursor: progress;
+}
+.busy-box {
+    background: var(--surface);
+    border-radius: 12px;
+    padding: 20px 28px;
+    box-shadow: 0 4px 20px rgba(0, 0, 0, 0.15);
+    display: flex; flex-direction: column; align-items: center; gap: 8px;
+    font-size: 14px;
+}
+.spinner {
+    width: 28px; height: 28px;
+    border: 3px solid var(--border);
+    border-top-color: var(--accent);
+    border-radius: 50%;
+    animation: spin 0.8s linear infinite;
+}
+@keyframes spin { to { transform: rotate(360deg); } }
+
+/* Device list */
+.device-list { display: flex; flex-direction: column; gap: 6px; }
+.device-row { display: flex; align-items: center; gap: 10px; font-size: 13px; }
+.device-name { flex: 1; }
+.device-rssi { color: var(--text-muted); font-size: 12px; font-variant-numeric: tabular-nums; }
+
+/* Batch list */
+.subsection-title { font-size: 13px; font-weight: 600; color: var(--text-strong); }
+.batch-list { display: flex; flex-wrap: wrap; gap: 8px; }
+.batch-item {
+    position: relative;
+    width: 64px; height: 64px;
+    border: 1.5px solid var(--border);
+    border-radius: 6px;
+    display: flex; align-items: center; justify-content: center;
+    cursor: grab;
+    background: var(--paper);
 }
-.preview-img { max-width: 100%; max-height: 108px; object-fit: contain; }
-.preview-placeholder { color: #aaa; font-size: 13px; }
+.batch-item img { max-width: 60px; max-height: 60px; pointer-events: none; }
+.batch-remove {
+    position: absolute; top: -6px; right: -6px;
+    width: 18px; height: 18px;
+    border: none; border-radius: 50%;
+    background: #cc0000; color: #fff;
+    font-size: 12px; line-height: 18px;
+    cursor: pointer;
+}
+
+/* History */
+.history-list { display: flex; flex-direction: column; gap: 6px; max-height: 260px; overflow-y: auto; }
+.history-item {
+    display: flex; align-items: center; gap: 10px;
+    padding: 4px 8px;
+    border: 1.5px solid var(--border);
+    border-radius: 6px;
+    background: var(--input-bg);
+    color: var(--text);
+    cursor: pointer;
+    text-align: left;
+}
+.history-item:hover { border-color: var(--accent); }
+.history-item img { width: 40px; height: 40px; object-fit: contain; image-rendering: pixelated; }
+.history-kind { font-size: 13px; font-weight: 600; }
+.history-meta { font-size: 12px; color: var(--text-muted); font-variant-numeric: tabular-nums; }
 
 /* Progress */
 .progress-wrap { display: flex; flex-direction: column; gap: 4px; }
-.progress-label { font-size: 12px; color: #555; }
+.progress-label { font-size: 12px; color: var(--text-muted); }
 .progress-bar-bg {
     width: 100%; height: 6px;
-    background: #e5e5ea; border-radius: 3px; overflow: hidden;
+    background: var(--fill); border-radius: 3px; overflow: hidden;
 }
 .progress-bar-fill {
     height: 100%;
-    background: #0071e3;
+    background: var(--accent);
     border-radius: 3px;
     transition: width 0.2s;
 }
@@ -620,4 +3368,47 @@ body {
     white-space: pre-wrap;
     word-break: break-all;
 }
+.log-debug { color: #808080; }
+.log-warn  { color: #e5c07b; }
+.log-error { color: #f48771; }
+.log-header {
+    display: flex;
+    align-items: center;
+    justify-content: space-between;
+}
+.log-filter {
+    font-size: 11px;
+}
+.log-search {
+    font-size: 12px;
+    padding: 4px 8px;
+    margin-bottom: 6px;
+}
+"#;
+
+/// Dark palette for the variables declared at the top of STYLES.
+const DARK_THEME: &str = r#"
+:root {
+    color-scheme: dark;
+    --bg: #1c1c1e;
+    --surface: #2c2c2e;
+    --input-bg: #1c1c1e;
+    --well: #1c1c1e;
+    --fill: #3a3a3c;
+    --border: #48484a;
+    --text: #f2f2f7;
+    --text-strong: #d1d1d6;
+    --text-muted: #a1a1a6;
+    --text-faint: #6e6e73;
+    --accent: #0a84ff;
+    --accent-hover: #339bff;
+    --accent-tint: #1c3552;
+    --ok-bg: #1f3a24;
+    --ok-text: #7bd88f;
+    --warn-bg: #3d2a10;
+    --warn-text: #ffb340;
+    --error: #ff6961;
+    --error-bg: #43201e;
+    --veil: rgba(0, 0, 0, 0.5);
+}
 "#;
@@ -0,0 +1,241 @@
+use ab_glyph::PxScale;
+use image::{DynamicImage, Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut, text_size};
+use imageproc::rect::Rect;
+
+use crate::types::{load_font_cached, PRINTER_WIDTH};
+
+/// Blank modules required on each side of the symbol.
+const QUIET_ZONE: u32 = 10;
+/// Height of the bars in pixels.
+const BAR_HEIGHT: u32 = 80;
+/// Caption font size in pixels.
+const CAPTION_SIZE: f32 = 22.0;
+
+/// 1D barcode symbologies offered in the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Symbology {
+    #[default]
+    Code128,
+    Ean13,
+}
+
+impl Symbology {
+    pub const ALL: [Symbology; 2] = [Symbology::Code128, Symbology::Ean13];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Symbology::Code128 => "Code 128",
+            Symbology::Ean13 => "EAN-13",
+        }
+    }
+}
+
+/// Render `data` as a barcode centered on a PRINTER_WIDTH-wide canvas, with
+/// the human-readable text drawn underneath in the given font.
+pub fn render_barcode(data: &str, symbology: Symbology, font_path: &str) -> Result<DynamicImage, String> {
+    let (modules, caption) = match symbology {
+        Symbology::Code128 => (encode_code128(data)?, data.to_string()),
+        Symbology::Ean13 => {
+            let digits = ean13_digits(data)?;
+            let caption = digits.iter().map(|d| char::from(b'0' + d)).collect();
+            (encode_ean13(&digits), caption)
+        }
+    };
+
+    let total_modules = modules.len() as u32 + 2 * QUIET_ZONE;
+    let module_px = PRINTER_WIDTH / total_modules;
+    if module_px == 0 {
+        return Err(format!("Barcode is too long to fit the paper ({} modules)", total_modules));
+    }
+
+    let font = load_font_cached(font_path, 0)?;
+    let scale = PxScale::from(CAPTION_SIZE);
+    let (caption_w, caption_h) = text_size(scale, &*font, &caption);
+
+    let height = BAR_HEIGHT + 6 + caption_h + 10;
+    let mut img = RgbImage::from_pixel(PRINTER_WIDTH, height, Rgb([255u8, 255, 255]));
+
+    let x_offset = (PRINTER_WIDTH - modules.len() as u32 * module_px) / 2;
+    for (i, &bar) in modules.iter().enumerate() {
+        if bar {
+            let x = x_offset + i as u32 * module_px;
+            draw_filled_rect_mut(&mut img, Rect::at(x as i32, 0).of_size(module_px, BAR_HEIGHT), Rgb([0u8, 0, 0]));
+        }
+    }
+
+    let caption_x = (PRINTER_WIDTH.saturating_sub(caption_w) / 2) as i32;
+    draw_text_mut(&mut img, Rgb([0u8, 0, 0]), caption_x, (BAR_HEIGHT + 6) as i32, scale, &*font, &caption);
+
+    Ok(DynamicImage::ImageRgb8(img))
+}
+
+// ── Code 128 ──────────────────────────────────────────────────────────────────
+
+/// Bar/space widths for Code 128 symbol values 0..=105 (each sums to 11 modules).
+const CODE128_PATTERNS: [&str; 106] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212", "221213",
+    "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221", "223211", "221132",
+    "221231", "213212", "223112", "312131", "311222", "321122", "321221", "312212", "322112", "322211",
+    "212123", "212321", "232121", "111323", "131123", "131321", "112313", "132113", "132311", "211313",
+    "231113", "231311", "112133", "112331", "132131", "113123", "113321", "133121", "313121", "211331",
+    "231131", "213113", "213311", "213131", "311123", "311321", "331121", "312113", "312311", "332111",
+    "314111", "221411", "431111", "111224", "111422", "121124", "121421", "141122", "141221", "112214",
+    "112412", "122114", "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111",
+    "111242", "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311", "113141",
+    "114131", "311141", "411131", "211412", "211214", "211232",
+];
+const CODE128_START_B: usize = 104;
+const CODE128_STOP: &str = "2331112";
+
+/// Encode printable ASCII with Code 128 code set B. Returns one bool per module (true = bar).
+fn encode_code128(data: &str) -> Result<Vec<bool>, String> {
+    if data.is_empty() {
+        return Err("Nothing to encode".into());
+    }
+    let mut values = vec![CODE128_START_B];
+    for c in data.chars() {
+        if !(' '..='~').contains(&c) {
+            return Err(format!("Code 128 only supports printable ASCII (found {:?})", c));
+        }
+        values.push(c as usize - 32);
+    }
+    let checksum = values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| if i == 0 { v } else { v * i })
+        .sum::<usize>()
+        % 103;
+    values.push(checksum);
+
+    let mut modules = Vec::new();
+    for v in values {
+        push_widths(&mut modules, CODE128_PATTERNS[v]);
+    }
+    push_widths(&mut modules, CODE128_STOP);
+    Ok(modules)
+}
+
+/// Append alternating bar/space runs described by a width string (bar first).
+fn push_widths(modules: &mut Vec<bool>, widths: &str) {
+    for (i, w) in widths.bytes().enumerate() {
+        let bar = i % 2 == 0;
+        modules.extend(std::iter::repeat_n(bar, (w - b'0') as usize));
+    }
+}
+
+// ── EAN-13 ────────────────────────────────────────────────────────────────────
+
+/// L-code (odd parity) patterns for digits 0-9; R-codes are their complement
+/// and G-codes the reversed R-codes.
+const EAN_L_CODES: [u8; 10] = [
+    0b0001101, 0b0011001, 0b0010011, 0b0111101, 0b0100011,
+    0b0110001, 0b0101111, 0b0111011, 0b0110111, 0b0001011,
+];
+/// Which of the six left-hand digits use G-codes, keyed by the first digit
+/// (bit 5 = second digit … bit 0 = seventh digit).
+const EAN_PARITY: [u8; 10] = [
+    0b000000, 0b001011, 0b001101, 0b001110, 0b010011,
+    0b011001, 0b011100, 0b010101, 0b010110, 0b011010,
+];
+
+/// Compute the EAN-13 check digit for the first twelve digits.
+fn ean13_check_digit(digits: &[u8]) -> u8 {
+    let sum: u32 = digits[..12]
+        .iter()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 0 { d as u32 } else { d as u32 * 3 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// Parse 12 digits (check digit appended) or 13 digits (check digit verified).
+pub fn ean13_digits(data: &str) -> Result<Vec<u8>, String> {
+    let data = data.trim();
+    if !data.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("EAN-13 accepts digits only".into());
+    }
+    let mut digits: Vec<u8> = data.bytes().map(|b| b - b'0').collect();
+    match digits.len() {
+        12 => {
+            let check = ean13_check_digit(&digits);
+            digits.push(check);
+            Ok(digits)
+        }
+        13 => {
+            let expected = ean13_check_digit(&digits);
+            if digits[12] != expected {
+                return Err(format!("Invalid EAN-13 check digit: got {}, expected {}", digits[12], expected));
+            }
+            Ok(digits)
+        }
+        n => Err(format!("EAN-13 needs 12 or 13 digits, got {}", n)),
+    }
+}
+
+/// Encode 13 validated digits. Returns 95 modules (true = bar).
+fn encode_ean13(digits: &[u8]) -> Vec<bool> {
+    fn push_bits(modules: &mut Vec<bool>, bits: u8, len: u32) {
+        for i in (0..len).rev() {
+            modules.push(bits >> i & 1 == 1);
+        }
+    }
+
+    let mut modules = Vec::with_capacity(95);
+    let parity = EAN_PARITY[digits[0] as usize];
+
+    push_bits(&mut modules, 0b101, 3); // start guard
+    for (i, &d) in digits[1..7].iter().enumerate() {
+        let l = EAN_L_CODES[d as usize];
+        let use_g = parity >> (5 - i) & 1 == 1;
+        // G-code = R-code (complement of L) read right to left
+        let code = if use_g { (!l & 0x7f).reverse_bits() >> 1 } else { l };
+        push_bits(&mut modules, code, 7);
+    }
+    push_bits(&mut modules, 0b01010, 5); // centre guard
+    for &d in &digits[7..13] {
+        push_bits(&mut modules, !EAN_L_CODES[d as usize] & 0x7f, 7);
+    }
+    push_bits(&mut modules, 0b101, 3); // end guard
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code128_patterns_are_eleven_modules() {
+        for p in CODE128_PATTERNS {
+            assert_eq!(p.bytes().map(|b| (b - b'0') as u32).sum::<u32>(), 11, "{}", p);
+        }
+        // "A": start(11) + 1 data(11) + checksum(11) + stop(13)
+        assert_eq!(encode_code128("A").unwrap().len(), 46);
+        assert!(encode_code128("naïve").is_err());
+    }
+
+    #[test]
+    fn test_ean13_check_digit() {
+        assert_eq!(ean13_digits("400638133393").unwrap()[12], 1);
+        assert!(ean13_digits("4006381333931").is_ok());
+        assert!(ean13_digits("4006381333932").is_err());
+        assert!(ean13_digits("12345").is_err());
+        assert!(ean13_digits("40063813339a1").is_err());
+    }
+
+    #[test]
+    fn test_ean13_module_layout() {
+        let digits = ean13_digits("4006381333931").unwrap();
+        let modules = encode_ean13(&digits);
+        assert_eq!(modules.len(), 95);
+        let to_str = |m: &[bool]| m.iter().map(|&b| if b { '1' } else { '0' }).collect::<String>();
+        assert_eq!(to_str(&modules[..3]), "101");
+        assert_eq!(to_str(&modules[45..50]), "01010");
+        assert_eq!(to_str(&modules[92..]), "101");
+        // Second digit (0) with first digit 4 → parity L: L-code 0001101
+        assert_eq!(to_str(&modules[3..10]), "0001101");
+        // Last digit (1) → R-code 1100110
+        assert_eq!(to_str(&modules[85..92]), "1100110");
+    }
+}
@@ -1,190 +1,1427 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use futures::StreamExt;
-use tokio::sync::mpsc::{Receiver, Sender};
-use btleplug::api::{Central, Manager as _, Peripheral as _, ScanFilter, WriteType};
-use btleplug::platform::{Manager, Peripheral};
-use btleplug::api::Characteristic;
+use regex::Regex;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use btleplug::api::{Central, CentralEvent, Manager as _, Peripheral as _, ScanFilter, WriteType};
+use btleplug::platform::{Adapter, Manager, Peripheral, PeripheralId};
 
 use crate::types::{
-    AppEvent, BleCommand, WRITE_CHAR_UUID, NOTIFY_CHAR_UUID,
-    printer_name_regex, parse_battery, CHUNK_SIZE,
+    AppEvent, BleCommand, ConnectedPrinter, DiscoveredDevice, LogLevel, PrintTarget, PrinterStatus, SERVICE_UUID, WRITE_CHAR_UUID, NOTIFY_CHAR_UUID,
+    address_matches, compile_printer_name_regex, printer_name_regex, parse_battery_mv, parse_printer_info, parse_printer_status, is_known_status, CHUNK_SIZE,
 };
-use crate::printer::print_image;
-use crate::text_render::render_text_to_image;
+use crate::barcode::render_barcode;
+use crate::error::PrinterError;
+use crate::escpos::{raster_size, split_into_segments};
+use crate::printer::{
+    feed_paper, print_job, reset_printer, send_raw, simulate_job, DuplicateGuard, PrintJob, PrintTiming, PrinterLink, DEFAULT_FEED_LINES,
+};
+use crate::qr::render_qr;
+use crate::test_page::render_test_page;
+use crate::text_render::render_text_to_images;
+
+type BleResult<T> = Result<T, PrinterError>;
+
+/// Longest a scan runs. For the first half only devices advertising
+/// SERVICE_UUID are reported; if none turned up, the rest scans everything.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to keep scanning after the first match, so nearby printers can
+/// be compared by signal strength.
+const DISCOVERY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Longest we wait for connect + service discovery before giving up.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Status request (battery, firmware); harmless, so also used as a keep-alive.
+const STATUS_REQUEST: [u8; 3] = [0x1e, 0x47, 0x03];
+
+/// Delay before the first reconnect attempt; doubled after each failure.
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound for the reconnect backoff delay.
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
 
 struct BleState {
-    peripheral: Peripheral,
-    write_char: Characteristic,
+    link: PrinterLink,
+    /// Advertised name (or address), for logs and the target picker.
+    name: String,
+    /// Most recent status string reported by the printer (firmware, battery).
+    status: Arc<Mutex<String>>,
+    /// Watches adapter events for an unexpected disconnect of the printer.
+    watcher: JoinHandle<()>,
+}
+
+/// Queue key for jobs run through the simulator instead of a printer.
+const SIMULATOR: &str = "simulator";
+
+/// A job rendered to raster bytes, and the line feeds to send after it.
+type QueuedJob = (PrintJob, u8);
+
+/// Print jobs waiting for one printer (or the simulator), and the one it's running.
+#[derive(Default)]
+struct PrintQueue {
+    jobs: VecDeque<QueuedJob>,
+    active: Option<JoinHandle<()>>,
+    /// Set by `CancelPrint` to stop the active job between chunks.
+    cancel: Arc<AtomicBool>,
 }
 
 /// Main BLE task that runs on a dedicated Tokio runtime.
 /// Loops on cmd_rx, dispatching BLE operations, sending events back via evt_tx.
+/// Several printers can be connected at once, keyed by peripheral id; each has
+/// its own queue, and prints run on their own tasks so that `CancelPrint` can be
+/// received mid-transfer and printers work in parallel.
 pub async fn ble_task(mut cmd_rx: Receiver<BleCommand>, evt_tx: Sender<AppEvent>) {
-    let mut state: Option<BleState> = None;
+    let mut printers: HashMap<String, BleState> = HashMap::new();
+    let mut queues: HashMap<String, PrintQueue> = HashMap::new();
+    // Which connected printer(s) new jobs go to.
+    let mut target = PrintTarget::All;
+
+    // Unexpected disconnects reported by the watchers, and connections re-established
+    // by background reconnect tasks (one per dropped printer).
+    let (lost_tx, mut lost_rx) = mpsc::channel::<PeripheralId>(4);
+    let (reconnected_tx, mut reconnected_rx) = mpsc::channel::<BleState>(1);
+    let mut auto_reconnect = false;
+    let mut reconnect_tasks: HashMap<String, JoinHandle<()>> = HashMap::new();
+    // The printer whose link died most recently; with auto-reconnect on, a
+    // print sent while nothing is connected first tries to get it back.
+    let mut last_dropped: Option<PeripheralId> = None;
+    // Pattern used to recognise printers while scanning; user-configurable.
+    let name_pattern = Arc::new(Mutex::new(printer_name_regex().clone()));
+    // Line feeds sent after each job before the end marker.
+    let mut feed_lines = DEFAULT_FEED_LINES;
+    // Which of the system's Bluetooth adapters to scan and connect with.
+    let mut adapter_index = 0usize;
+    // Printers found by the last scan when more than one matched, awaiting a choice.
+    let mut candidates: Option<(Adapter, Vec<Peripheral>)> = None;
+    // Ping the printers this often while idle so they don't auto-sleep (None = off).
+    let mut keep_alive: Option<Duration> = None;
+    let mut next_keep_alive = tokio::time::Instant::now();
+    // Write print jobs to files instead of sending them to a printer.
+    let mut simulate = false;
+    // Every job of the last print (pages and copies), as queued, for Reprint.
+    let mut last_print: Vec<QueuedJob> = Vec::new();
+    // The last print submitted, to catch a double-clicked Print button.
+    let mut duplicates = DuplicateGuard::default();
+    // Send image data with write-without-response (see PrinterLink::without_response).
+    let mut without_response = false;
+    // Pauses between the steps of each print.
+    let mut timing = PrintTiming::default();
+    // Commands that arrived while a scan was running, handled once it ends.
+    let mut deferred: VecDeque<BleCommand> = VecDeque::new();
+
+    loop {
+        // Start the next job on every idle queue whose printer is connected.
+        let ready: Vec<String> = queues
+            .iter()
+            .filter(|(_, q)| q.active.is_none() && !q.jobs.is_empty())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in ready {
+            if key != SIMULATOR {
+                // Check the link is still up before starting a job, so a silent drop
+                // is reported clearly instead of as a write error halfway through.
+                let dropped = match printers.get(&key) {
+                    // Only a definite "not connected" counts; a failed query lets the print try
+                    Some(s) => !s.link.peripheral.is_connected().await.unwrap_or(true),
+                    // Still reconnecting: the jobs wait
+                    None => continue,
+                };
+                if dropped {
+                    let s = printers.remove(&key).unwrap();
+                    s.watcher.abort();
+                    last_dropped = Some(s.link.peripheral.id());
+                    evt_tx.send(AppEvent::ConnectionLost).await.ok();
+                    let msg = if auto_reconnect {
+                        format!("{} dropped its connection before printing; reconnecting", s.name)
+                    } else {
+                        format!("{} dropped its connection before printing", s.name)
+                    };
+                    evt_tx.send(AppEvent::Error(msg)).await.ok();
+                    report_printers(&printers, &evt_tx).await;
+                    if let Some(task) = after_connection_lost(
+                        s.link.peripheral.id(),
+                        auto_reconnect,
+                        adapter_index,
+                        &mut queues,
+                        &evt_tx,
+                        &lost_tx,
+                        &reconnected_tx,
+                    )
+                    .await
+                    {
+                        reconnect_tasks.insert(key, task);
+                    }
+                    continue;
+                }
+            }
+            let queue = queues.get_mut(&key).unwrap();
+            let (job, feed_lines) = queue.jobs.pop_front().unwrap();
+            queue.active = Some(match printers.get(&key) {
+                Some(s) => {
+                    let link = PrinterLink { without_response, timing, ..s.link.clone() };
+                    spawn_print(link, job, feed_lines, &evt_tx, &queue.cancel)
+                }
+                None => spawn_simulation(job, feed_lines, &evt_tx, &queue.cancel),
+            });
+            evt_tx.send(AppEvent::QueueLength(waiting_jobs(&queues))).await.ok();
+        }
+
+        let printing = queues.values().any(|q| q.active.is_some());
+        let cmd = if let Some(cmd) = deferred.pop_front() {
+            cmd
+        } else {
+            tokio::select! {
+                cmd = cmd_rx.recv() => match cmd {
+                    Some(cmd) => cmd,
+                    None => break,
+                },
+
+                key = next_finished(&mut queues), if printing => {
+                    if let Some(queue) = queues.get_mut(&key) {
+                        queue.active = None;
+                    }
+                    if let Some(interval) = keep_alive {
+                        next_keep_alive = tokio::time::Instant::now() + interval;
+                    }
+                    continue;
+                }
+
+                // Only fires between prints, so it never interleaves with a transfer
+                _ = tokio::time::sleep_until(next_keep_alive),
+                    if keep_alive.is_some() && !printers.is_empty() && !printing =>
+                {
+                    next_keep_alive = tokio::time::Instant::now() + keep_alive.unwrap();
+                    for s in printers.values() {
+                        if let Err(e) = s.link.peripheral.write(&s.link.write_char, &STATUS_REQUEST, WriteType::WithResponse).await {
+                            evt_tx.send(AppEvent::Log(LogLevel::Warn, format!("Keep-alive failed: {}", e))).await.ok();
+                        }
+                    }
+                    continue;
+                }
+
+                Some(id) = lost_rx.recv() => {
+                    let key = id.to_string();
+                    if let Some(s) = printers.remove(&key) {
+                        last_dropped = Some(id.clone());
+                        let printing = queues.get(&key).is_some_and(|q| q.active.is_some());
+                        let reason = disconnect_reason(printing, keep_alive.is_some());
+                        evt_tx.send(AppEvent::Log(LogLevel::Warn, format!("{} disconnected — {}", s.name, reason))).await.ok();
+                        evt_tx.send(AppEvent::ConnectionLost).await.ok();
+                        report_printers(&printers, &evt_tx).await;
+                        if let Some(task) = after_connection_lost(
+                            id,
+                            auto_reconnect,
+                            adapter_index,
+                            &mut queues,
+                            &evt_tx,
+                            &lost_tx,
+                            &reconnected_tx,
+                        )
+                        .await
+                        {
+                            reconnect_tasks.insert(key, task);
+                        }
+                    }
+                    continue;
+                }
+
+                Some(new_state) = reconnected_rx.recv() => {
+                    let key = new_state.link.peripheral.id().to_string();
+                    reconnect_tasks.remove(&key);
+                    last_dropped = None;
+                    add_printer(&mut printers, new_state, &name_pattern, &evt_tx).await;
+                    report_printers(&printers, &evt_tx).await;
+                    continue;
+                }
+            }
+        };
+
+        // Don't wait for the reconnect backoff when the user wants to print now
+        if cmd.is_print() && auto_reconnect && !simulate && printers.is_empty() {
+            if let Some(id) = last_dropped.take() {
+                let key = id.to_string();
+                if let Some(task) = reconnect_tasks.remove(&key) {
+                    task.abort();
+                }
+                evt_tx.send(AppEvent::Log(LogLevel::Info, "Printer not connected; reconnecting before printing...".into())).await.ok();
+                // Finding and connecting can take a while; keep reading commands
+                // so Cancel and Disconnect take effect at once
+                let mut disconnecting = false;
+                let result = {
+                    let attempt = reconnect(&id, adapter_index, &evt_tx, &lost_tx);
+                    tokio::pin!(attempt);
+                    loop {
+                        tokio::select! {
+                            result = &mut attempt => break Some(result),
+                            Some(next) = cmd_rx.recv() => match next {
+                                BleCommand::CancelPrint | BleCommand::StopScan => break None,
+                                BleCommand::Disconnect => {
+                                    disconnecting = true;
+                                    break None;
+                                }
+                                other => deferred.push_back(other),
+                            },
+                        }
+                    }
+                };
+                let cancelled = result.is_none();
+                match result {
+                    Some(Ok(new_state)) => {
+                        add_printer(&mut printers, new_state, &name_pattern, &evt_tx).await;
+                        report_printers(&printers, &evt_tx).await;
+                    }
+                    // Its own handler, run next, cancels reconnecting and clears up
+                    None if disconnecting => deferred.push_front(BleCommand::Disconnect),
+                    result => {
+                        match result {
+                            Some(Err(e)) => evt_tx.send(AppEvent::Log(LogLevel::Warn, format!("Reconnect failed: {}", e))).await.ok(),
+                            _ => evt_tx.send(AppEvent::Log(LogLevel::Info, "Print cancelled while reconnecting".into())).await.ok(),
+                        };
+                        // Keep trying in the background as before
+                        let task = tokio::spawn(reconnect_with_backoff(
+                            id.clone(),
+                            adapter_index,
+                            evt_tx.clone(),
+                            lost_tx.clone(),
+                            reconnected_tx.clone(),
+                        ));
+                        reconnect_tasks.insert(key, task);
+                        last_dropped = Some(id);
+                    }
+                }
+                // The print that triggered the reconnect is dropped along with it
+                if cancelled {
+                    evt_tx.send(AppEvent::PrintCancelled).await.ok();
+                    continue;
+                }
+            }
+        }
 
-    while let Some(cmd) = cmd_rx.recv().await {
+        // The jobs a print command queues, kept for Reprint once it's handled
+        let mut print = Vec::new();
         match cmd {
             BleCommand::ScanAndConnect => {
                 evt_tx.send(AppEvent::ScanStarted).await.ok();
-                evt_tx.send(AppEvent::Log("Scanning for compatible printers (10s)...".into())).await.ok();
-                match scan_and_connect(&evt_tx).await {
-                    Ok(Some(new_state)) => {
-                        state = Some(new_state);
+                evt_tx.send(AppEvent::Log(LogLevel::Info, "Scanning for compatible printers (10s)...".into())).await.ok();
+                candidates = None;
+                let skip: Vec<String> = printers.keys().chain(reconnect_tasks.keys()).cloned().collect();
+                // Keep reading commands during the scan so StopScan ends it promptly
+                let (stop_tx, stop_rx) = watch::channel(false);
+                let scan = scan_and_connect(adapter_index, &name_pattern, &skip, stop_rx, &evt_tx, &lost_tx);
+                tokio::pin!(scan);
+                let result = loop {
+                    tokio::select! {
+                        result = &mut scan => break result,
+                        Some(cmd) = cmd_rx.recv() => match cmd {
+                            BleCommand::StopScan => {
+                                stop_tx.send(true).ok();
+                            }
+                            other => deferred.push_back(other),
+                        },
+                    }
+                };
+                match result {
+                    Ok(ScanResult::Connected(new_state)) => {
+                        add_printer(&mut printers, new_state, &name_pattern, &evt_tx).await;
+                    }
+                    Ok(ScanResult::Choose(adapter, found)) => {
+                        let msg = format!("Found {} printers — choose one to connect", found.len());
+                        evt_tx.send(AppEvent::Log(LogLevel::Info, msg)).await.ok();
+                        candidates = Some((adapter, found));
+                    }
+                    Ok(ScanResult::NotFound) => {
+                        evt_tx.send(AppEvent::Log(LogLevel::Info, "No compatible printer found nearby".into())).await.ok();
+                    }
+                    Ok(ScanResult::Stopped) => {
+                        evt_tx.send(AppEvent::Log(LogLevel::Info, "Scan stopped".into())).await.ok();
+                    }
+                    Err(e) => {
+                        evt_tx.send(AppEvent::Error(format!("Scan error: {}", e))).await.ok();
+                    }
+                }
+                report_printers(&printers, &evt_tx).await;
+            }
+
+            // Only meaningful while a scan or connection attempt is running, which handles it itself
+            BleCommand::StopScan => {}
+
+            BleCommand::ConnectDevice(id) => {
+                let chosen = candidates.as_ref().and_then(|(adapter, found)| {
+                    let peripheral = found.iter().find(|p| p.id().to_string() == id)?;
+                    Some((adapter.clone(), peripheral.clone()))
+                });
+                let Some((adapter, peripheral)) = chosen else {
+                    evt_tx.send(AppEvent::Error("That printer is no longer available; scan again".into())).await.ok();
+                    continue;
+                };
+                if printers.contains_key(&id) {
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, "Already connected to that printer".into())).await.ok();
+                    continue;
+                }
+                evt_tx.send(AppEvent::ConnectStarted).await.ok();
+                let attempt = connect_peripheral(adapter, peripheral.clone(), &evt_tx, &lost_tx);
+                match until_stopped(attempt, &mut cmd_rx, &mut deferred).await {
+                    Some(Ok(new_state)) => {
+                        add_printer(&mut printers, new_state, &name_pattern, &evt_tx).await;
+                        // The rest of the scan's finds stay available to connect as well
+                        if let Some((_, found)) = candidates.as_mut() {
+                            found.retain(|p| p.id().to_string() != id);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        evt_tx.send(AppEvent::Error(format!("Connection error: {}", e))).await.ok();
+                    }
+                    None => connection_stopped(Some(&peripheral), &evt_tx).await,
+                }
+                report_printers(&printers, &evt_tx).await;
+            }
+
+            BleCommand::ConnectLast(id) => {
+                if printers.contains_key(&id) {
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, "Already connected to the last used printer".into())).await.ok();
+                    continue;
+                }
+                if let Some(task) = reconnect_tasks.remove(&id) {
+                    task.abort();
+                }
+                evt_tx.send(AppEvent::ConnectStarted).await.ok();
+                evt_tx.send(AppEvent::Log(LogLevel::Info, "Looking for the last used printer...".into())).await.ok();
+                let result = match until_stopped(find_peripheral(&id, adapter_index), &mut cmd_rx, &mut deferred).await {
+                    Some(Ok((adapter, peripheral))) => {
+                        let attempt = connect_peripheral(adapter, peripheral.clone(), &evt_tx, &lost_tx);
+                        until_stopped(attempt, &mut cmd_rx, &mut deferred).await.ok_or(Some(peripheral))
                     }
-                    Ok(None) => {
-                        evt_tx.send(AppEvent::Log("No compatible printer found nearby".into())).await.ok();
-                        evt_tx.send(AppEvent::Disconnected).await.ok();
+                    Some(Err(e)) => Ok(Err(e)),
+                    None => {
+                        // Stopped mid-search: end the scan it started
+                        if let Ok(adapter) = get_adapter(adapter_index).await {
+                            adapter.stop_scan().await.ok();
+                        }
+                        Err(None)
+                    }
+                };
+                match result {
+                    Ok(Ok(new_state)) => {
+                        add_printer(&mut printers, new_state, &name_pattern, &evt_tx).await;
+                        candidates = None;
+                    }
+                    Ok(Err(PrinterError::NotFound)) => {
+                        evt_tx.send(AppEvent::Error("Last used printer not found nearby; try Scan & Connect".into())).await.ok();
+                    }
+                    Ok(Err(e)) => {
+                        evt_tx.send(AppEvent::Error(format!("Connection error: {}", e))).await.ok();
+                    }
+                    Err(peripheral) => connection_stopped(peripheral.as_ref(), &evt_tx).await,
+                }
+                report_printers(&printers, &evt_tx).await;
+            }
+
+            BleCommand::ConnectByAddress(address) => {
+                let (adapter, peripheral) = match peripheral_by_address(&address, adapter_index).await {
+                    Ok(found) => found,
+                    Err(PrinterError::NotFound) => {
+                        let msg = format!("No known device with address {}; scan once so the system learns it", address.trim());
+                        evt_tx.send(AppEvent::Error(msg)).await.ok();
+                        continue;
                     }
                     Err(e) => {
-                        evt_tx.send(AppEvent::Log(format!("Scan error: {}", e))).await.ok();
-                        evt_tx.send(AppEvent::Disconnected).await.ok();
+                        evt_tx.send(AppEvent::Error(format!("Connection error: {}", e))).await.ok();
+                        continue;
+                    }
+                };
+                let id = peripheral.id().to_string();
+                if printers.contains_key(&id) {
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, "Already connected to that printer".into())).await.ok();
+                    continue;
+                }
+                if let Some(task) = reconnect_tasks.remove(&id) {
+                    task.abort();
+                }
+                evt_tx.send(AppEvent::ConnectStarted).await.ok();
+                let attempt = connect_peripheral(adapter, peripheral.clone(), &evt_tx, &lost_tx);
+                match until_stopped(attempt, &mut cmd_rx, &mut deferred).await {
+                    Some(Ok(new_state)) => {
+                        add_printer(&mut printers, new_state, &name_pattern, &evt_tx).await;
+                        candidates = None;
+                    }
+                    Some(Err(e)) => {
+                        evt_tx.send(AppEvent::Error(format!("Connection error: {}", e))).await.ok();
                     }
+                    None => connection_stopped(Some(&peripheral), &evt_tx).await,
                 }
+                report_printers(&printers, &evt_tx).await;
             }
 
             BleCommand::Disconnect => {
-                if let Some(ref s) = state {
-                    disconnect_peripheral(&s.peripheral, &evt_tx).await;
+                last_dropped = None;
+                if !reconnect_tasks.is_empty() {
+                    reconnect_tasks.drain().for_each(|(_, task)| task.abort());
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, "Reconnect cancelled".into())).await.ok();
+                }
+                clear_queues(&mut queues, None, &evt_tx).await;
+                for (_, s) in printers.drain() {
+                    s.watcher.abort();
+                    disconnect_peripheral(&s.link.peripheral, &evt_tx).await;
                 }
-                state = None;
-                evt_tx.send(AppEvent::Disconnected).await.ok();
+                report_printers(&printers, &evt_tx).await;
             }
 
-            BleCommand::PrintImage(img) => {
-                if let Some(ref s) = state {
-                    print_image(&s.peripheral, &s.write_char, img, &evt_tx).await;
-                } else {
-                    evt_tx.send(AppEvent::Log("Print aborted: not connected".into())).await.ok();
+            BleCommand::DisconnectDevice(id) => {
+                if last_dropped.as_ref().is_some_and(|d| d.to_string() == id) {
+                    last_dropped = None;
+                }
+                if let Some(task) = reconnect_tasks.remove(&id) {
+                    task.abort();
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, "Reconnect cancelled".into())).await.ok();
+                }
+                clear_queues(&mut queues, Some(&id), &evt_tx).await;
+                if let Some(s) = printers.remove(&id) {
+                    s.watcher.abort();
+                    disconnect_peripheral(&s.link.peripheral, &evt_tx).await;
+                }
+                report_printers(&printers, &evt_tx).await;
+            }
+
+            BleCommand::SetPrintTarget(new_target) => {
+                target = new_target;
+            }
+
+            BleCommand::ListAdapters => {
+                match list_adapters().await {
+                    Ok(names) => {
+                        evt_tx.send(AppEvent::Adapters(names)).await.ok();
+                    }
+                    Err(e) => {
+                        evt_tx.send(AppEvent::Log(LogLevel::Warn, format!("Could not list Bluetooth adapters: {}", e))).await.ok();
+                    }
+                }
+            }
+
+            BleCommand::SetAdapter(index) => {
+                adapter_index = index;
+            }
+
+            BleCommand::SetAutoReconnect(enabled) => {
+                auto_reconnect = enabled;
+                if !enabled && !reconnect_tasks.is_empty() {
+                    reconnect_tasks.drain().for_each(|(_, task)| task.abort());
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, "Reconnect cancelled".into())).await.ok();
+                }
+            }
+
+            BleCommand::SetKeepAlive(secs) => {
+                keep_alive = secs.map(|s| Duration::from_secs(s.max(1) as u64));
+                if let Some(interval) = keep_alive {
+                    next_keep_alive = tokio::time::Instant::now() + interval;
+                }
+            }
+
+            BleCommand::SetWriteWithoutResponse(enabled) => {
+                without_response = enabled;
+            }
+
+            BleCommand::SetPrintTiming(t) => {
+                timing = t;
+            }
+
+            BleCommand::SetSimulate(enabled) => {
+                simulate = enabled;
+                if enabled {
+                    let dir = crate::printer::simulation_dir();
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Simulation on: prints go to {}", dir.display()))).await.ok();
+                }
+            }
+
+            BleCommand::SetFeedLines(lines) => {
+                feed_lines = lines;
+            }
+
+            BleCommand::SetPrinterNamePattern(pattern) => {
+                let re = match compile_printer_name_regex(&pattern) {
+                    Ok(re) => re,
+                    Err(e) => {
+                        let msg = format!("Invalid printer name pattern, using default: {}", e);
+                        evt_tx.send(AppEvent::Error(msg)).await.ok();
+                        printer_name_regex().clone()
+                    }
+                };
+                evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Printer name pattern: {}", re.as_str()))).await.ok();
+                *name_pattern.lock().unwrap() = re;
+            }
+
+            BleCommand::PrintImage { img, options, crop, segment_height, copies } => {
+                let img = crop.apply(img);
+                let targets = print_targets(simulate, &target, &printers);
+                let jobs = match segment_height {
+                    Some(max_height) => {
+                        let segments = split_into_segments(&img, &options, max_height);
+                        if segments.len() > 1 {
+                            let msg = format!("Image split into {} segments", segments.len());
+                            evt_tx.send(AppEvent::Log(LogLevel::Info, msg)).await.ok();
+                        }
+                        segments.into_iter().map(|img| PrintJob::BatchImage { img, options }).collect()
+                    }
+                    None => vec![PrintJob::Image { img, options }],
+                };
+                if is_duplicate(&mut duplicates, &jobs, &targets, copies, &queues, &evt_tx).await {
+                    continue;
+                }
+                enqueue_copies(&mut queues, &targets, jobs, copies, feed_lines, &mut print, &evt_tx).await;
+            }
+
+            BleCommand::PrintBatch { images, options } => {
+                let targets = print_targets(simulate, &target, &printers);
+                let jobs: Vec<_> = images.into_iter().map(|img| PrintJob::BatchImage { img, options }).collect();
+                if is_duplicate(&mut duplicates, &jobs, &targets, 1, &queues, &evt_tx).await {
+                    continue;
+                }
+                for job in jobs {
+                    enqueue(&mut queues, &targets, job, feed_lines, &mut print, &evt_tx).await;
                 }
             }
 
-            BleCommand::PrintText { text, font_path, font_size } => {
-                match render_text_to_image(&text, &font_path, font_size) {
+            BleCommand::PrintText { text, options, copies } => {
+                match render_text_to_images(&text, &options) {
+                    Ok(pages) => {
+                        if pages.len() > 1 {
+                            evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Text split into {} pages", pages.len()))).await.ok();
+                        }
+                        let targets = print_targets(simulate, &target, &printers);
+                        let jobs: Vec<_> = pages.iter().map(PrintJob::rendered).collect();
+                        if is_duplicate(&mut duplicates, &jobs, &targets, copies, &queues, &evt_tx).await {
+                            continue;
+                        }
+                        enqueue_copies(&mut queues, &targets, jobs, copies, feed_lines, &mut print, &evt_tx).await;
+                    }
+                    Err(reason) => {
+                        let e = PrinterError::RenderFailed { what: "Text", reason };
+                        evt_tx.send(AppEvent::Error(e.to_string())).await.ok();
+                    }
+                }
+            }
+
+            BleCommand::PrintQr { text, ec_level, module_px } => {
+                match render_qr(&text, ec_level, module_px) {
                     Ok(img) => {
-                        if let Some(ref s) = state {
-                            print_image(&s.peripheral, &s.write_char, img, &evt_tx).await;
-                        } else {
-                            evt_tx.send(AppEvent::Log("Print aborted: not connected".into())).await.ok();
+                        let jobs = [PrintJob::rendered(&img)];
+                        let targets = print_targets(simulate, &target, &printers);
+                        if is_duplicate(&mut duplicates, &jobs, &targets, 1, &queues, &evt_tx).await {
+                            continue;
                         }
+                        let [job] = jobs;
+                        enqueue(&mut queues, &targets, job, feed_lines, &mut print, &evt_tx).await;
                     }
-                    Err(e) => {
-                        evt_tx.send(AppEvent::Error(format!("Text render error: {}", e))).await.ok();
+                    Err(reason) => {
+                        let e = PrinterError::RenderFailed { what: "QR code", reason };
+                        evt_tx.send(AppEvent::Error(e.to_string())).await.ok();
+                    }
+                }
+            }
+
+            BleCommand::PrintBarcode { data, symbology, font_path } => {
+                match render_barcode(&data, symbology, &font_path) {
+                    Ok(img) => {
+                        let jobs = [PrintJob::rendered(&img)];
+                        let targets = print_targets(simulate, &target, &printers);
+                        if is_duplicate(&mut duplicates, &jobs, &targets, 1, &queues, &evt_tx).await {
+                            continue;
+                        }
+                        let [job] = jobs;
+                        enqueue(&mut queues, &targets, job, feed_lines, &mut print, &evt_tx).await;
+                    }
+                    Err(reason) => {
+                        let e = PrinterError::RenderFailed { what: "Barcode", reason };
+                        evt_tx.send(AppEvent::Error(e.to_string())).await.ok();
+                    }
+                }
+            }
+
+            BleCommand::PrintJobFile(job) => {
+                evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Printing job file \"{}\"", job.name))).await.ok();
+                let targets = print_targets(simulate, &target, &printers);
+                let jobs: Vec<_> = job
+                    .pages
+                    .into_iter()
+                    .map(|data| {
+                        let (width, height) = raster_size(&data).unwrap_or_default();
+                        PrintJob::Raster { data, width, height }
+                    })
+                    .collect();
+                if is_duplicate(&mut duplicates, &jobs, &targets, 1, &queues, &evt_tx).await {
+                    continue;
+                }
+                for job in jobs {
+                    enqueue(&mut queues, &targets, job, feed_lines, &mut print, &evt_tx).await;
+                }
+            }
+
+            BleCommand::PrintTestPage => {
+                // Each printer's page shows its own status
+                let targets = print_targets(simulate, &target, &printers);
+                if targets.is_empty() {
+                    evt_tx.send(AppEvent::Log(LogLevel::Warn, "Print aborted: not connected".into())).await.ok();
+                }
+                for key in targets {
+                    let status = printers.get(&key).map(|s| s.status.lock().unwrap().clone()).unwrap_or_default();
+                    let job = PrintJob::Image { img: Arc::new(render_test_page(&status)), options: Default::default() };
+                    // Not kept for Reprint, which would send this printer's status to every target
+                    enqueue(&mut queues, &[key], job, feed_lines, &mut Vec::new(), &evt_tx).await;
+                }
+            }
+
+            BleCommand::CancelPrint => {
+                for queue in queues.values() {
+                    if queue.active.is_some() {
+                        queue.cancel.store(true, Ordering::SeqCst);
+                    }
+                }
+                clear_queues(&mut queues, None, &evt_tx).await;
+            }
+
+            BleCommand::Reprint => {
+                let targets = print_targets(simulate, &target, &printers);
+                if last_print.is_empty() {
+                    evt_tx.send(AppEvent::Error("Nothing to reprint yet".into())).await.ok();
+                } else if targets.is_empty() {
+                    evt_tx.send(AppEvent::Log(LogLevel::Warn, "Print aborted: not connected".into())).await.ok();
+                } else {
+                    evt_tx.send(AppEvent::Log(LogLevel::Info, "Reprinting last job".into())).await.ok();
+                    for job in &last_print {
+                        queue_job(&mut queues, &targets, job, &evt_tx).await;
                     }
                 }
             }
+
+            BleCommand::ResetPrinter => {
+                let Some(targets) = idle_targets(&target, &printers, &queues, "resetting the printer", &evt_tx).await else {
+                    continue;
+                };
+                for s in targets {
+                    match reset_printer(&s.link, &evt_tx).await {
+                        Ok(()) => {
+                            evt_tx.send(AppEvent::Log(LogLevel::Info, "Sent: initialize printer (ESC @)".into())).await.ok();
+                        }
+                        Err(e) => {
+                            evt_tx.send(AppEvent::Error(format!("Reset failed: {}", e))).await.ok();
+                        }
+                    }
+                }
+            }
+            BleCommand::SendRaw(bytes) => {
+                let Some(targets) = idle_targets(&target, &printers, &queues, "sending raw commands", &evt_tx).await else {
+                    continue;
+                };
+                for s in targets {
+                    match send_raw(&s.link, &bytes, &evt_tx).await {
+                        Ok(()) => {
+                            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                            evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Sent raw: {}", hex.join(" ")))).await.ok();
+                        }
+                        Err(e) => {
+                            evt_tx.send(AppEvent::Error(format!("Raw send failed: {}", e))).await.ok();
+                        }
+                    }
+                }
+            }
+            BleCommand::Feed(lines) => {
+                feed(&target, &printers, &queues, lines, false, &evt_tx).await;
+            }
+            BleCommand::FeedDots(dots) => {
+                feed(&target, &printers, &queues, dots, true, &evt_tx).await;
+            }
+        }
+        if !print.is_empty() {
+            last_print = print;
+            evt_tx.send(AppEvent::ReprintAvailable).await.ok();
+        }
+    }
+}
+
+/// Run a direct connection attempt while still reading commands, so the busy
+/// overlay's Stop works. StopScan or CancelPrint drops the attempt, as does
+/// Disconnect, which is then handled next; anything else waits until the
+/// attempt ends. Returns None if it was stopped.
+async fn until_stopped<T>(
+    attempt: impl std::future::Future<Output = T>,
+    cmd_rx: &mut Receiver<BleCommand>,
+    deferred: &mut VecDeque<BleCommand>,
+) -> Option<T> {
+    tokio::pin!(attempt);
+    loop {
+        tokio::select! {
+            result = &mut attempt => return Some(result),
+            Some(cmd) = cmd_rx.recv() => match cmd {
+                BleCommand::StopScan | BleCommand::CancelPrint => return None,
+                BleCommand::Disconnect => {
+                    deferred.push_front(BleCommand::Disconnect);
+                    return None;
+                }
+                other => deferred.push_back(other),
+            },
+        }
+    }
+}
+
+/// Clean up after `until_stopped` dropped a connection attempt: a connect
+/// may still be pending on `peripheral`.
+async fn connection_stopped(peripheral: Option<&Peripheral>, evt_tx: &Sender<AppEvent>) {
+    if let Some(peripheral) = peripheral {
+        peripheral.disconnect().await.ok();
+    }
+    evt_tx.send(AppEvent::Log(LogLevel::Info, "Connection attempt stopped".into())).await.ok();
+}
+
+/// Queue keys for a new job: the targeted printers, or the simulator.
+fn print_targets(simulate: bool, target: &PrintTarget, printers: &HashMap<String, BleState>) -> Vec<String> {
+    if simulate {
+        return vec![SIMULATOR.to_string()];
+    }
+    target.resolve(printers.keys())
+}
+
+/// The targeted printers for a direct command (reset, raw bytes), or None
+/// after reporting why it can't be sent: nothing connected, or a print is
+/// using one of the links.
+async fn idle_targets<'a>(
+    target: &PrintTarget,
+    printers: &'a HashMap<String, BleState>,
+    queues: &HashMap<String, PrintQueue>,
+    action: &str,
+    evt_tx: &Sender<AppEvent>,
+) -> Option<Vec<&'a BleState>> {
+    let keys = target.resolve(printers.keys());
+    if keys.is_empty() {
+        evt_tx.send(AppEvent::Error("Not connected".into())).await.ok();
+        return None;
+    }
+    if keys.iter().any(|key| queues.get(key).is_some_and(|q| q.active.is_some())) {
+        evt_tx.send(AppEvent::Error(format!("Cancel the current print before {}", action))).await.ok();
+        return None;
+    }
+    Some(keys.iter().map(|key| &printers[key]).collect())
+}
+
+/// Jog the paper forward on the targeted printers, unless a print is using the link.
+async fn feed(
+    target: &PrintTarget,
+    printers: &HashMap<String, BleState>,
+    queues: &HashMap<String, PrintQueue>,
+    amount: u8,
+    dots: bool,
+    evt_tx: &Sender<AppEvent>,
+) {
+    let keys = target.resolve(printers.keys());
+    if keys.is_empty() {
+        evt_tx.send(AppEvent::Error("Not connected".into())).await.ok();
+        return;
+    }
+    for key in keys {
+        if queues.get(&key).is_some_and(|q| q.active.is_some()) {
+            evt_tx.send(AppEvent::Log(LogLevel::Warn, "Paper feed ignored while printing".into())).await.ok();
+        } else if let Err(e) = feed_paper(&printers[&key].link, amount, dots, evt_tx).await {
+            evt_tx.send(AppEvent::Error(format!("Feed failed: {}", e))).await.ok();
+        }
+    }
+}
+
+/// Render a job and add a copy to the queue of each target, or reject it if
+/// no printer is connected or the image is too small to print. The rendered
+/// job and its feed are also added to `print`, the record kept for Reprint.
+async fn enqueue(
+    queues: &mut HashMap<String, PrintQueue>,
+    targets: &[String],
+    job: PrintJob,
+    feed_lines: u8,
+    print: &mut Vec<QueuedJob>,
+    evt_tx: &Sender<AppEvent>,
+) {
+    if targets.is_empty() {
+        evt_tx.send(AppEvent::Log(LogLevel::Warn, "Print aborted: not connected".into())).await.ok();
+        return;
+    }
+    if let Err(e) = job.validate() {
+        evt_tx.send(AppEvent::Error(e)).await.ok();
+        return;
+    }
+    // A batch image's minimum feed is folded in before rendering drops it
+    let feed_lines = feed_lines.max(job.min_feed_lines());
+    let job = (job.into_rendered(), feed_lines);
+    queue_job(queues, targets, &job, evt_tx).await;
+    print.push(job);
+}
+
+/// Add a copy of an already rendered job to the queue of each target.
+async fn queue_job(
+    queues: &mut HashMap<String, PrintQueue>,
+    targets: &[String],
+    job: &QueuedJob,
+    evt_tx: &Sender<AppEvent>,
+) {
+    for key in targets {
+        let queue = queues.entry(key.clone()).or_default();
+        queue.jobs.push_back(job.clone());
+        if queue.active.is_some() {
+            evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Print job queued ({} waiting)", queue.jobs.len()))).await.ok();
+        }
+    }
+    evt_tx.send(AppEvent::QueueLength(waiting_jobs(queues))).await.ok();
+}
+
+/// Whether to drop this print as a repeat of the one just submitted, logging
+/// it if so. A print of several copies was asked for on purpose and always
+/// goes through.
+async fn is_duplicate(
+    guard: &mut DuplicateGuard,
+    jobs: &[PrintJob],
+    targets: &[String],
+    copies: u8,
+    queues: &HashMap<String, PrintQueue>,
+    evt_tx: &Sender<AppEvent>,
+) -> bool {
+    if copies > 1 || targets.is_empty() {
+        return false;
+    }
+    let Some(age) = guard.check(jobs, targets, std::time::Instant::now()) else {
+        return false;
+    };
+    let msg = format!("Skipped a duplicate print (identical to one sent {:.1}s ago)", age.as_secs_f32());
+    evt_tx.send(AppEvent::Log(LogLevel::Warn, msg)).await.ok();
+    let busy = queues.values().any(|q| q.active.is_some() || !q.jobs.is_empty());
+    evt_tx.send(AppEvent::DuplicateSkipped { busy }).await.ok();
+    true
+}
+
+/// Queue `copies` copies of a print made up of `jobs`, one whole copy after
+/// another. With more than one copy, each copy's first job is marked so the
+/// UI can show which copy is printing.
+async fn enqueue_copies(
+    queues: &mut HashMap<String, PrintQueue>,
+    targets: &[String],
+    jobs: Vec<PrintJob>,
+    copies: u8,
+    feed_lines: u8,
+    print: &mut Vec<QueuedJob>,
+    evt_tx: &Sender<AppEvent>,
+) {
+    let copies = copies.max(1);
+    for copy in 1..=copies {
+        for (i, job) in jobs.iter().enumerate() {
+            let job = if copies > 1 && i == 0 {
+                PrintJob::Copy { job: Box::new(job.clone()), copy, copies }
+            } else {
+                job.clone()
+            };
+            enqueue(queues, targets, job, feed_lines, print, evt_tx).await;
+        }
+    }
+}
+
+/// Jobs waiting behind the running ones, across all printers.
+fn waiting_jobs(queues: &HashMap<String, PrintQueue>) -> usize {
+    queues.values().map(|q| q.jobs.len()).sum()
+}
+
+/// Wait for any running print to end and return its queue's key.
+/// Only call while at least one queue has an active job.
+async fn next_finished(queues: &mut HashMap<String, PrintQueue>) -> String {
+    let running = queues.iter_mut().filter_map(|(key, queue)| {
+        let handle = queue.active.as_mut()?;
+        Some(Box::pin(async move {
+            handle.await.ok();
+            key.clone()
+        }))
+    });
+    futures::future::select_all(running).await.0
+}
+
+/// Add a newly connected printer, warning (but keeping it) if its name no
+/// longer matches the printer pattern: connecting by id skips the scan's
+/// name check, so some other device exposing the same characteristics
+/// could have answered, and prints to it would be wasted writes.
+async fn add_printer(
+    printers: &mut HashMap<String, BleState>,
+    state: BleState,
+    name_pattern: &Mutex<Regex>,
+    evt_tx: &Sender<AppEvent>,
+) {
+    let matches = name_pattern.lock().unwrap().is_match(&state.name);
+    if !matches {
+        let msg = format!("Connected to \"{}\", whose name doesn't match the printer pattern; is it the right device?", state.name);
+        evt_tx.send(AppEvent::Log(LogLevel::Warn, msg)).await.ok();
+    }
+    printers.insert(state.link.peripheral.id().to_string(), state);
+}
+
+/// Tell the UI which printers are connected now. Also ends a connect attempt
+/// in the UI; with none left this is reported as `Disconnected` as well.
+async fn report_printers(printers: &HashMap<String, BleState>, evt_tx: &Sender<AppEvent>) {
+    if printers.is_empty() {
+        evt_tx.send(AppEvent::Disconnected).await.ok();
+    }
+    let mut list: Vec<ConnectedPrinter> = printers
+        .iter()
+        .map(|(id, s)| ConnectedPrinter { id: id.clone(), name: s.name.clone() })
+        .collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    evt_tx.send(AppEvent::ConnectedPrinters(list)).await.ok();
+}
+
+/// Best guess at why a printer dropped off, for the log. BLE doesn't say, so
+/// go by what it was doing: the CTP500 auto-sleeps when idle unless kept awake.
+fn disconnect_reason(printing: bool, keep_alive: bool) -> &'static str {
+    if printing {
+        "mid-print, so likely out of range or out of power"
+    } else if keep_alive {
+        "likely out of range or switched off"
+    } else {
+        "likely out of range or asleep (\"Keep the printer awake\" prevents sleeping)"
+    }
+}
+
+/// After a printer dropped off unexpectedly: start reconnecting in the
+/// background (its queued jobs wait for it) or, without auto-reconnect,
+/// discard its queue.
+async fn after_connection_lost(
+    id: PeripheralId,
+    auto_reconnect: bool,
+    adapter_index: usize,
+    queues: &mut HashMap<String, PrintQueue>,
+    evt_tx: &Sender<AppEvent>,
+    lost_tx: &Sender<PeripheralId>,
+    reconnected_tx: &Sender<BleState>,
+) -> Option<JoinHandle<()>> {
+    if !auto_reconnect {
+        clear_queues(queues, Some(&id.to_string()), evt_tx).await;
+        return None;
+    }
+    Some(tokio::spawn(reconnect_with_backoff(
+        id,
+        adapter_index,
+        evt_tx.clone(),
+        lost_tx.clone(),
+        reconnected_tx.clone(),
+    )))
+}
+
+/// Drop all jobs that haven't started yet, on every printer or just `only`.
+async fn clear_queues(queues: &mut HashMap<String, PrintQueue>, only: Option<&str>, evt_tx: &Sender<AppEvent>) {
+    let mut discarded = 0;
+    for (key, queue) in queues.iter_mut() {
+        if only.is_none_or(|only| only == key) {
+            discarded += queue.jobs.len();
+            queue.jobs.clear();
+        }
+    }
+    if discarded == 0 {
+        return;
+    }
+    evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Discarded {} queued print job(s)", discarded))).await.ok();
+    evt_tx.send(AppEvent::QueueLength(waiting_jobs(queues))).await.ok();
+}
+
+/// Run a print job on a separate task, clearing any stale cancel request first.
+fn spawn_print(
+    link: PrinterLink,
+    job: PrintJob,
+    feed_lines: u8,
+    evt_tx: &Sender<AppEvent>,
+    cancel: &Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    cancel.store(false, Ordering::SeqCst);
+    let evt_tx = evt_tx.clone();
+    let cancel = cancel.clone();
+    tokio::spawn(async move {
+        let job = announce_copy(job, &evt_tx).await;
+        print_job(&link, job, feed_lines, &evt_tx, &cancel).await;
+    })
+}
+
+/// Run a job through the simulator (file output) on a separate task.
+fn spawn_simulation(job: PrintJob, feed_lines: u8, evt_tx: &Sender<AppEvent>, cancel: &Arc<AtomicBool>) -> JoinHandle<()> {
+    cancel.store(false, Ordering::SeqCst);
+    let evt_tx = evt_tx.clone();
+    let cancel = cancel.clone();
+    tokio::spawn(async move {
+        let job = announce_copy(job, &evt_tx).await;
+        simulate_job(job, feed_lines, &evt_tx, &cancel).await;
+    })
+}
+
+/// Report which copy is starting if `job` begins one, and return the job to print.
+async fn announce_copy(job: PrintJob, evt_tx: &Sender<AppEvent>) -> PrintJob {
+    match job {
+        PrintJob::Copy { job, copy, copies } => {
+            evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Printing copy {} of {}", copy, copies))).await.ok();
+            evt_tx.send(AppEvent::CopyProgress { copy, copies }).await.ok();
+            *job
         }
+        job => job,
     }
 }
 
-/// Scan for a compatible printer and connect to the first found.
-/// Port of Python's `PrinterConnect._scan_and_connect()`.
-async fn scan_and_connect(evt_tx: &Sender<AppEvent>) -> Result<Option<BleState>, Box<dyn std::error::Error>> {
+/// All Bluetooth adapters on the system, in btleplug's enumeration order.
+async fn adapters() -> BleResult<Vec<Adapter>> {
     let manager = Manager::new().await?;
     // Let CoreBluetooth initialize before scanning
     tokio::time::sleep(Duration::from_millis(200)).await;
 
-    let adapters = manager.adapters().await?;
-    let adapter = adapters.into_iter().next().ok_or("No Bluetooth adapter found")?;
+    Ok(manager.adapters().await?)
+}
 
-    adapter.start_scan(ScanFilter::default()).await?;
+/// Return the adapter at `index` (0 = the first, the usual built-in one).
+async fn get_adapter(index: usize) -> BleResult<Adapter> {
+    adapters().await?.into_iter().nth(index).ok_or(PrinterError::NoAdapter)
+}
+
+/// Human-readable names of the system's Bluetooth adapters, for the picker.
+async fn list_adapters() -> BleResult<Vec<String>> {
+    let mut names = Vec::new();
+    for (i, adapter) in adapters().await?.iter().enumerate() {
+        let info = adapter.adapter_info().await.unwrap_or_default();
+        names.push(if info.is_empty() { format!("Adapter {}", i + 1) } else { info });
+    }
+    Ok(names)
+}
+
+/// Scan for compatible printers, reporting each with its RSSI, and connect
+/// if exactly one is found. Printers in `skip` (already connected or being
+/// reconnected) are ignored. Setting `stop` ends the scan early without
+/// connecting. Port of Python's `PrinterConnect._scan_and_connect()`.
+async fn scan_and_connect(
+    adapter_index: usize,
+    name_pattern: &Arc<Mutex<Regex>>,
+    skip: &[String],
+    mut stop: watch::Receiver<bool>,
+    evt_tx: &Sender<AppEvent>,
+    lost_tx: &Sender<PeripheralId>,
+) -> BleResult<ScanResult> {
+    let adapter = get_adapter(adapter_index).await?;
+    let name_re = name_pattern.lock().unwrap().clone();
+
+    adapter.start_scan(printer_scan_filter()).await?;
 
     let mut event_stream = adapter.events().await?;
-    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    let started = tokio::time::Instant::now();
+    let mut deadline = started + SCAN_TIMEOUT;
+    // When to drop the service filter; None once it has been decided
+    let mut unfilter_at = Some(started + SCAN_TIMEOUT / 2);
 
-    let mut found_peripheral: Option<Peripheral> = None;
+    let mut found: Vec<Peripheral> = Vec::new();
+    let mut stopped = false;
 
     loop {
-        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        let now = tokio::time::Instant::now();
+        if unfilter_at.is_some_and(|at| now >= at) {
+            unfilter_at = None;
+            if found.is_empty() {
+                // Some firmware doesn't advertise the service; look at everything
+                evt_tx.send(AppEvent::Log(LogLevel::Debug, "No printer advertised its service; scanning all devices".into())).await.ok();
+                adapter.stop_scan().await.ok();
+                adapter.start_scan(ScanFilter::default()).await?;
+            }
+        }
+        let remaining = deadline.saturating_duration_since(now);
         if remaining.is_zero() {
             break;
         }
+        let wait = unfilter_at.map_or(remaining, |at| remaining.min(at - now));
 
-        match tokio::time::timeout(remaining, event_stream.next()).await {
-            Ok(Some(btleplug::api::CentralEvent::DeviceDiscovered(id))) => {
+        let event = tokio::select! {
+            event = tokio::time::timeout(wait, event_stream.next()) => event,
+            _ = stop.changed() => {
+                stopped = true;
+                break;
+            }
+        };
+        match event {
+            Ok(Some(CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id))) => {
                 let peripheral = adapter.peripheral(&id).await?;
                 if let Ok(Some(props)) = peripheral.properties().await {
                     if let Some(name) = &props.local_name {
-                        if printer_name_regex().is_match(name) {
-                            evt_tx.send(AppEvent::Log(format!("Found: {}", name))).await.ok();
-                            found_peripheral = Some(peripheral);
-                            break;
+                        if name_re.is_match(name) && !skip.contains(&id.to_string()) {
+                            let device = DiscoveredDevice {
+                                id: id.to_string(),
+                                name: name.clone(),
+                                rssi: props.rssi,
+                            };
+                            if !found.iter().any(|p| p.id() == id) {
+                                let rssi = props.rssi.map_or("?".into(), |r| r.to_string());
+                                evt_tx.send(AppEvent::Log(LogLevel::Debug, format!("Found: {} ({} dBm)", name, rssi))).await.ok();
+                                found.push(peripheral);
+                                // Give other nearby printers a moment to show up
+                                deadline = deadline.min(tokio::time::Instant::now() + DISCOVERY_WINDOW);
+                            }
+                            evt_tx.send(AppEvent::DeviceDiscovered(device)).await.ok();
                         }
                     }
                 }
             }
             Ok(Some(_)) => {} // Ignore other events
-            Ok(None) | Err(_) => break, // Stream ended or timeout
+            Ok(None) => break, // Stream ended
+            Err(_) => {}       // Deadline or filter switch; checked at the top
         }
     }
 
     adapter.stop_scan().await.ok();
+    if stopped {
+        return Ok(ScanResult::Stopped);
+    }
 
-    let peripheral = match found_peripheral {
-        Some(p) => p,
-        None => return Ok(None),
-    };
+    match found.len() {
+        0 => Ok(ScanResult::NotFound),
+        1 => {
+            let peripheral = found.remove(0);
+            connect_peripheral(adapter, peripheral, evt_tx, lost_tx).await.map(ScanResult::Connected)
+        }
+        _ => Ok(ScanResult::Choose(adapter, found)),
+    }
+}
 
-    // Connect
-    let address = if let Ok(Some(props)) = peripheral.properties().await {
-        props.address.to_string()
-    } else {
-        "unknown".to_string()
+/// Scan filter for devices advertising the printer's service. An
+/// unparsable SERVICE_UUID leaves it empty, which matches everything.
+fn printer_scan_filter() -> ScanFilter {
+    ScanFilter { services: SERVICE_UUID.parse().into_iter().collect() }
+}
+
+/// Outcome of `scan_and_connect`.
+enum ScanResult {
+    NotFound,
+    /// The user stopped the scan.
+    Stopped,
+    /// Exactly one printer matched and is now connected.
+    Connected(BleState),
+    /// Several printers matched; the user picks one via `BleCommand::ConnectDevice`.
+    Choose(Adapter, Vec<Peripheral>),
+}
+
+/// Connect to a known peripheral, discover its characteristics, subscribe to
+/// status notifications, and start watching for unexpected disconnects.
+async fn connect_peripheral(
+    adapter: Adapter,
+    peripheral: Peripheral,
+    evt_tx: &Sender<AppEvent>,
+    lost_tx: &Sender<PeripheralId>,
+) -> BleResult<BleState> {
+    let (address, name) = match peripheral.properties().await {
+        Ok(Some(props)) => (props.address.to_string(), props.local_name),
+        _ => ("unknown".to_string(), None),
     };
-    evt_tx.send(AppEvent::Log(format!("Connecting to {}...", address))).await.ok();
+    evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Connecting to {}...", address))).await.ok();
 
-    peripheral.connect().await?;
-    peripheral.discover_services().await?;
+    // A half-asleep printer can leave connect/discovery hanging forever
+    let connected = tokio::time::timeout(CONNECT_TIMEOUT, async {
+        peripheral.connect().await?;
+        peripheral.discover_services().await
+    })
+    .await;
+    match connected {
+        Ok(result) => result.map_err(|e| PrinterError::ConnectFailed(e.to_string()))?,
+        Err(_) => {
+            peripheral.disconnect().await.ok();
+            let secs = CONNECT_TIMEOUT.as_secs();
+            let reason = format!("timed out connecting to {} after {}s; is the printer awake?", address, secs);
+            return Err(PrinterError::ConnectFailed(reason));
+        }
+    }
 
     let characteristics = peripheral.characteristics();
 
     let write_char = characteristics.iter()
         .find(|c| c.uuid.to_string().eq_ignore_ascii_case(WRITE_CHAR_UUID))
-        .ok_or("Write characteristic not found")?
+        .ok_or(PrinterError::CharMissing("Write"))?
         .clone();
 
     let notify_char = characteristics.iter()
         .find(|c| c.uuid.to_string().eq_ignore_ascii_case(NOTIFY_CHAR_UUID))
-        .ok_or("Notify characteristic not found")?
+        .ok_or(PrinterError::CharMissing("Notify"))?
         .clone();
 
     // Subscribe to notifications
-    peripheral.subscribe(&notify_char).await?;
+    peripheral.subscribe(&notify_char).await.map_err(|e| PrinterError::ConnectFailed(e.to_string()))?;
+
+    // The advertised name can change once connected; use the current one
+    let name = match peripheral.properties().await {
+        Ok(Some(props)) => props.local_name.or(name),
+        _ => name,
+    };
 
-    evt_tx.send(AppEvent::Log(format!("Connected (chunk size: {} bytes)", CHUNK_SIZE))).await.ok();
     evt_tx.send(AppEvent::Connected).await.ok();
+    let name = name.unwrap_or_else(|| address.clone());
+    evt_tx.send(AppEvent::PrinterIdentity { id: peripheral.id().to_string(), name: name.clone() }).await.ok();
 
     // Request printer status (battery etc.) — same as Python's \x1e\x47\x03
-    peripheral.write(&write_char, &[0x1e, 0x47, 0x03], WriteType::WithResponse).await.ok();
+    peripheral.write(&write_char, &STATUS_REQUEST, WriteType::WithResponse).await.ok();
 
     // Spawn a task to drain notifications
     let evt_tx_clone = evt_tx.clone();
     let peripheral_clone = peripheral.clone();
+    let status = Arc::new(Mutex::new(String::new()));
+    let status_clone = status.clone();
     tokio::spawn(async move {
+        let mut printer_status = PrinterStatus::Ready;
         if let Ok(mut stream) = peripheral_clone.notifications().await {
             while let Some(data) = stream.next().await {
                 let text = String::from_utf8_lossy(&data.value)
                     .trim()
                     .trim_end_matches(',')
                     .to_string();
-                evt_tx_clone.send(AppEvent::Log(format!("Printer status: {}", text))).await.ok();
+                *status_clone.lock().unwrap() = text.clone();
+                evt_tx_clone.send(AppEvent::Log(LogLevel::Debug, format!("Printer status: {}", text))).await.ok();
+                // Fault detection is guesswork until real fault payloads are seen
+                if !is_known_status(&data.value) {
+                    let msg = format!("Unrecognized printer status, please report: {:02x?}", data.value);
+                    evt_tx_clone.send(AppEvent::Log(LogLevel::Debug, msg)).await.ok();
+                }
+
+                if let Some(mv) = parse_battery_mv(&data.value) {
+                    evt_tx_clone.send(AppEvent::BatteryVoltage(mv)).await.ok();
+                }
+                if let Some(info) = parse_printer_info(&data.value) {
+                    evt_tx_clone.send(AppEvent::PrinterInfo(info)).await.ok();
+                }
+                // Only report transitions, so the UI banner clears once the fault does
+                let new_status = parse_printer_status(&data.value);
+                if new_status != printer_status {
+                    printer_status = new_status;
+                    evt_tx_clone.send(AppEvent::PrinterFault(new_status)).await.ok();
+                }
+            }
+        }
+    });
 
-                if let Some(pct) = parse_battery(&data.value) {
-                    evt_tx_clone.send(AppEvent::BatteryLevel(pct)).await.ok();
+    // Spawn a task that reports when this peripheral drops off unexpectedly.
+    // The adapter is moved in so its event stream outlives this function.
+    let mut adapter_events = adapter.events().await?;
+    let id = peripheral.id();
+    let lost_tx = lost_tx.clone();
+    let watcher = tokio::spawn(async move {
+        let _adapter = adapter;
+        while let Some(event) = adapter_events.next().await {
+            if let CentralEvent::DeviceDisconnected(gone) = event {
+                if gone == id {
+                    lost_tx.send(gone).await.ok();
+                    break;
                 }
             }
         }
     });
 
-    Ok(Some(BleState { peripheral, write_char }))
+    // btleplug 0.11 reports neither the negotiated MTU nor a max write length,
+    // so every connection uses the conservative CHUNK_SIZE
+    let link = PrinterLink { peripheral, write_char, chunk_size: CHUNK_SIZE, without_response: false, timing: PrintTiming::default() };
+    Ok(BleState { link, name, status, watcher })
+}
+
+/// Re-establish a connection to `id` after an unexpected disconnect, retrying
+/// with exponential backoff until it succeeds. The task is aborted by `ble_task`
+/// if the user disconnects or starts a new scan in the meantime.
+async fn reconnect_with_backoff(
+    id: PeripheralId,
+    adapter_index: usize,
+    evt_tx: Sender<AppEvent>,
+    lost_tx: Sender<PeripheralId>,
+    reconnected_tx: Sender<BleState>,
+) {
+    let mut delay = RECONNECT_INITIAL_DELAY;
+    let mut attempt = 1u32;
+    loop {
+        let msg = format!("Reconnecting in {}s (attempt {})...", delay.as_secs(), attempt);
+        evt_tx.send(AppEvent::Log(LogLevel::Info, msg)).await.ok();
+        tokio::time::sleep(delay).await;
+
+        match reconnect(&id, adapter_index, &evt_tx, &lost_tx).await {
+            Ok(new_state) => {
+                reconnected_tx.send(new_state).await.ok();
+                return;
+            }
+            Err(e) => {
+                evt_tx.send(AppEvent::Log(LogLevel::Warn, format!("Reconnect attempt {} failed: {}", attempt, e))).await.ok();
+            }
+        }
+
+        delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+        attempt += 1;
+    }
+}
+
+/// Single reconnect attempt: briefly scan until `id` is advertising again, then connect.
+async fn reconnect(
+    id: &PeripheralId,
+    adapter_index: usize,
+    evt_tx: &Sender<AppEvent>,
+    lost_tx: &Sender<PeripheralId>,
+) -> BleResult<BleState> {
+    let (adapter, peripheral) = find_peripheral(&id.to_string(), adapter_index).await?;
+    connect_peripheral(adapter, peripheral, evt_tx, lost_tx).await
+}
+
+/// Look up a known printer by its peripheral id string without a full scan:
+/// use the adapter's cached peripherals if it already knows the device,
+/// otherwise scan for up to 5s until it advertises.
+async fn find_peripheral(id: &str, adapter_index: usize) -> BleResult<(Adapter, Peripheral)> {
+    let adapter = get_adapter(adapter_index).await?;
+    adapter.start_scan(ScanFilter::default()).await?;
+
+    let known = adapter.peripherals().await?.into_iter().find(|p| p.id().to_string() == id);
+    if let Some(peripheral) = known {
+        adapter.stop_scan().await.ok();
+        return Ok((adapter, peripheral));
+    }
+
+    let mut event_stream = adapter.events().await?;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    let mut seen = None;
+    loop {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        match tokio::time::timeout(remaining, event_stream.next()).await {
+            Ok(Some(CentralEvent::DeviceDiscovered(found)))
+            | Ok(Some(CentralEvent::DeviceUpdated(found))) if found.to_string() == id => {
+                seen = Some(found);
+                break;
+            }
+            Ok(Some(_)) => {}
+            Ok(None) | Err(_) => break,
+        }
+    }
+
+    adapter.stop_scan().await.ok();
+    let Some(found) = seen else {
+        return Err(PrinterError::NotFound);
+    };
+    let peripheral = adapter.peripheral(&found).await?;
+    Ok((adapter, peripheral))
+}
+
+/// Find a device the adapter already knows by a user-typed MAC address or
+/// peripheral id and get its handle with `Adapter::peripheral`, without scanning.
+async fn peripheral_by_address(address: &str, adapter_index: usize) -> BleResult<(Adapter, Peripheral)> {
+    let adapter = get_adapter(adapter_index).await?;
+    let id = adapter
+        .peripherals()
+        .await?
+        .into_iter()
+        .find(|p| address_matches(address, &p.id().to_string(), &p.address().to_string()))
+        .map(|p| p.id())
+        .ok_or(PrinterError::NotFound)?;
+    let peripheral = adapter.peripheral(&id).await?;
+    Ok((adapter, peripheral))
 }
 
 /// Disconnect from the peripheral cleanly.
 /// Port of Python's `PrinterConnect._disconnect()`.
 async fn disconnect_peripheral(peripheral: &Peripheral, evt_tx: &Sender<AppEvent>) {
-    evt_tx.send(AppEvent::Log("Disconnecting...".into())).await.ok();
+    evt_tx.send(AppEvent::Log(LogLevel::Info, "Disconnecting...".into())).await.ok();
     if let Err(e) = peripheral.disconnect().await {
-        evt_tx.send(AppEvent::Log(format!("Disconnect error: {}", e))).await.ok();
+        evt_tx.send(AppEvent::Log(LogLevel::Warn, format!("Disconnect error: {}", e))).await.ok();
     } else {
-        evt_tx.send(AppEvent::Log("Disconnected".into())).await.ok();
+        evt_tx.send(AppEvent::Log(LogLevel::Info, "Disconnected".into())).await.ok();
     }
 }
@@ -0,0 +1,295 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use clap::{Args, Parser, Subcommand};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use crate::ble::ble_task;
+use crate::config::{self, Settings};
+use crate::escpos::RasterOptions;
+use crate::fetch::open_image;
+use crate::types::{Alignment, AppEvent, BleCommand, CropRect, LogLevel};
+
+/// How long to wait for the BLE task to confirm a disconnect before exiting.
+const DISCONNECT_WAIT: Duration = Duration::from_secs(5);
+
+/// Bluetooth LE client for the CTP500 thermal printer. Without a subcommand
+/// the GUI opens; with one, the job is printed headlessly and the process exits.
+#[derive(Debug, Parser)]
+#[command(name = "ctp500", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Render text with the saved font settings and print it.
+    PrintText {
+        /// Text to print; "-" reads it from stdin.
+        text: String,
+        /// Font file (.ttf/.ttc/.otf) instead of the saved font.
+        #[arg(long)]
+        font: Option<String>,
+        /// Font size in pixels.
+        #[arg(long)]
+        size: Option<f32>,
+        /// Line alignment: left, center or right.
+        #[arg(long, value_parser = parse_alignment)]
+        align: Option<Alignment>,
+        #[command(flatten)]
+        job: JobArgs,
+    },
+    /// Print an image file (PNG, JPEG, BMP, WebP or GIF), scaled to the paper width.
+    PrintImage {
+        path: PathBuf,
+        #[command(flatten)]
+        job: JobArgs,
+    },
+}
+
+/// Options shared by every print subcommand.
+#[derive(Debug, Args)]
+pub struct JobArgs {
+    /// Line feeds after the print (defaults to the saved setting).
+    #[arg(long)]
+    pub feed: Option<u8>,
+    /// Write the job to ~/ctp500-sim instead of printing.
+    #[arg(long)]
+    pub simulate: bool,
+    /// Print every event as a JSON line on stdout instead of log text on stderr.
+    #[arg(long)]
+    pub json: bool,
+}
+
+fn parse_alignment(s: &str) -> Result<Alignment, String> {
+    Alignment::ALL
+        .into_iter()
+        .find(|a| a.label().eq_ignore_ascii_case(s))
+        .ok_or_else(|| format!("expected left, center or right, got \"{}\"", s))
+}
+
+/// Run a print subcommand: connect (unless simulating), print one job,
+/// disconnect. Returns the process exit code.
+pub fn run(command: Command) -> i32 {
+    let settings = config::load();
+    let (job, args) = match build_job(command, &settings) {
+        Ok(job) => job,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return 2;
+        }
+    };
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+    match rt.block_on(print_once(job, &args, &settings)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// Turn a subcommand into the BLE command that prints it.
+fn build_job(command: Command, settings: &Settings) -> Result<(BleCommand, JobArgs), String> {
+    match command {
+        Command::PrintText { text, font, size, align, job } => {
+            let text = if text == "-" {
+                let mut buf = String::new();
+                std::io::stdin().read_to_string(&mut buf).map_err(|e| format!("Failed to read stdin: {}", e))?;
+                buf
+            } else {
+                text
+            };
+            let mut options = settings.text_options();
+            if let Some(font) = font {
+                options.font_path = font;
+                options.face_index = 0;
+            }
+            if let Some(size) = size {
+                options.font_size = size;
+            }
+            if let Some(align) = align {
+                options.alignment = align;
+            }
+            Ok((BleCommand::PrintText { text, options, copies: 1 }, job))
+        }
+        Command::PrintImage { path, job } => {
+            let (img, note) = open_image(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            if let Some(note) = note {
+                eprintln!("{}", note);
+            }
+            let cmd = BleCommand::PrintImage {
+                img: Arc::new(img),
+                options: RasterOptions { mirror: settings.mirror, ..RasterOptions::default() },
+                crop: CropRect::default(),
+                segment_height: None,
+                copies: 1,
+            };
+            Ok((cmd, job))
+        }
+    }
+}
+
+/// Drive the BLE task through connect → print → disconnect.
+async fn print_once(job: BleCommand, args: &JobArgs, settings: &Settings) -> Result<(), String> {
+    let (cmd_tx, cmd_rx) = mpsc::channel::<BleCommand>(32);
+    let (evt_tx, mut evt_rx) = mpsc::channel::<AppEvent>(256);
+    tokio::spawn(ble_task(cmd_rx, evt_tx));
+
+    for cmd in settings.ble_commands() {
+        send(&cmd_tx, cmd).await?;
+    }
+    if let Some(feed) = args.feed {
+        send(&cmd_tx, BleCommand::SetFeedLines(feed)).await?;
+    }
+    let simulate = args.simulate || settings.simulate;
+    if simulate && !settings.simulate {
+        send(&cmd_tx, BleCommand::SetSimulate(true)).await?;
+    }
+
+    let json = args.json;
+    if !simulate {
+        send(&cmd_tx, BleCommand::ScanAndConnect).await?;
+        wait_for(&mut evt_rx, json, |event| match event {
+            AppEvent::Connected => Some(Ok(())),
+            AppEvent::Disconnected => Some(Err("No printer connected".to_string())),
+            _ => None,
+        })
+        .await?;
+    }
+
+    send(&cmd_tx, job).await?;
+    // Long text prints as several page jobs; disconnecting after the first
+    // would throw the rest away
+    let mut progress = PrintProgress::default();
+    let printed = wait_for(&mut evt_rx, json, |event| progress.update(event)).await;
+
+    if !simulate {
+        send(&cmd_tx, BleCommand::Disconnect).await.ok();
+        let disconnected = wait_for(&mut evt_rx, json, |event| matches!(event, AppEvent::Disconnected).then_some(Ok(())));
+        tokio::time::timeout(DISCONNECT_WAIT, disconnected).await.ok();
+    }
+    printed
+}
+
+/// Follows a print made of one or more queued jobs (pages, copies) until the
+/// last one completes. The BLE task reports how many jobs are waiting each
+/// time one is queued or starts, so a completion while none wait ends the print.
+#[derive(Default)]
+struct PrintProgress {
+    waiting: usize,
+}
+
+impl PrintProgress {
+    fn update(&mut self, event: &AppEvent) -> Option<Result<(), String>> {
+        match event {
+            AppEvent::QueueLength(n) => {
+                self.waiting = *n;
+                None
+            }
+            AppEvent::PrintComplete(_) if self.waiting == 0 => Some(Ok(())),
+            AppEvent::PrintCancelled => Some(Err("Print cancelled".to_string())),
+            AppEvent::Disconnected => Some(Err("Printer disconnected during the print".to_string())),
+            _ => None,
+        }
+    }
+}
+
+async fn send(cmd_tx: &Sender<BleCommand>, cmd: BleCommand) -> Result<(), String> {
+    cmd_tx.send(cmd).await.map_err(|_| "BLE task stopped unexpectedly".to_string())
+}
+
+/// Echo events until `done` returns a result for one of them: log lines to
+/// stderr, or with `json` every event as a JSON line to stdout.
+/// Any `AppEvent::Error` ends the wait with that error.
+async fn wait_for(
+    evt_rx: &mut Receiver<AppEvent>,
+    json: bool,
+    mut done: impl FnMut(&AppEvent) -> Option<Result<(), String>>,
+) -> Result<(), String> {
+    while let Some(event) = evt_rx.recv().await {
+        if json {
+            match serde_json::to_string(&event) {
+                Ok(line) => println!("{}", line),
+                Err(e) => log::warn!("Couldn't serialize {:?}: {}", event, e),
+            }
+        }
+        match &event {
+            AppEvent::Log(level, msg) if !json && *level >= LogLevel::Info => eprintln!("{}", msg),
+            AppEvent::Error(e) => return Err(e.clone()),
+            _ => {}
+        }
+        if let Some(result) = done(&event) {
+            return result;
+        }
+    }
+    Err("BLE task stopped unexpectedly".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_parsing() {
+        Cli::command().debug_assert();
+        assert!(Cli::try_parse_from(["ctp500"]).unwrap().command.is_none());
+
+        let cli = Cli::try_parse_from(["ctp500", "print-text", "hello", "--size", "30", "--align", "center"]).unwrap();
+        let Some(Command::PrintText { text, size, align, job, .. }) = cli.command else { panic!("expected print-text") };
+        assert_eq!((text.as_str(), size, align), ("hello", Some(30.0), Some(Alignment::Center)));
+        assert!(!job.simulate);
+
+        let cli = Cli::try_parse_from(["ctp500", "print-image", "logo.png", "--feed", "0", "--simulate"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::PrintImage { job: JobArgs { feed: Some(0), simulate: true, json: false }, .. })));
+        assert!(Cli::try_parse_from(["ctp500", "print-text", "x", "--align", "middle"]).is_err());
+    }
+
+    #[test]
+    fn test_waits_for_every_page() {
+        let done = crate::types::PrintSummary { bytes: 488, width: 384, height: 10, elapsed_ms: 1200 };
+        // Two pages queued, then each starts and completes in turn
+        let events = [
+            AppEvent::QueueLength(1),
+            AppEvent::QueueLength(2),
+            AppEvent::QueueLength(1),
+            AppEvent::PrintComplete(done),
+            AppEvent::QueueLength(0),
+            AppEvent::PrintComplete(done),
+        ];
+        let mut progress = PrintProgress::default();
+        let results: Vec<_> = events.iter().map(|e| progress.update(e)).collect();
+        assert!(results[..5].iter().all(Option::is_none));
+        assert_eq!(results[5], Some(Ok(())));
+
+        // A single page ends at its one completion
+        let mut progress = PrintProgress::default();
+        assert_eq!(progress.update(&AppEvent::QueueLength(1)), None);
+        assert_eq!(progress.update(&AppEvent::QueueLength(0)), None);
+        assert_eq!(progress.update(&AppEvent::PrintComplete(done)), Some(Ok(())));
+        assert!(progress.update(&AppEvent::PrintCancelled).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_events_serialize_as_json_lines() {
+        let json = |event: AppEvent| serde_json::to_string(&event).unwrap();
+        let summary = crate::types::PrintSummary { bytes: 488, width: 384, height: 10, elapsed_ms: 1200 };
+        assert_eq!(
+            json(AppEvent::PrintComplete(summary)),
+            r#"{"event":"print_complete","data":{"bytes":488,"width":384,"height":10,"elapsed_ms":1200}}"#
+        );
+        assert_eq!(json(AppEvent::PrintCancelled), r#"{"event":"print_cancelled"}"#);
+        assert_eq!(
+            json(AppEvent::PrintProgress { sent: 10, total: 40 }),
+            r#"{"event":"print_progress","data":{"sent":10,"total":40}}"#
+        );
+        assert_eq!(json(AppEvent::Log(LogLevel::Warn, "low".into())), r#"{"event":"log","data":["warn","low"]}"#);
+        assert_eq!(
+            json(AppEvent::PrinterFault(crate::types::PrinterStatus::PaperOut)),
+            r#"{"event":"printer_fault","data":"paper_out"}"#
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::printer::{PrintTiming, DEFAULT_FEED_LINES};
+use crate::text_render::TextOptions;
+use crate::types::{Alignment, BatteryCurve, BleCommand, FontChoice, ListStyle, DEFAULT_LOW_BATTERY_PCT, FONT_CHOICES};
+
+/// User settings restored on launch and saved whenever a control changes.
+/// Missing fields fall back to their defaults, so older files keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Index into the built-in fonts followed by `custom_fonts`.
+    pub font_idx: usize,
+    /// Face within a .ttc collection font.
+    pub font_face: u32,
+    pub font_size_px: u32,
+    pub line_spacing: f32,
+    pub alignment: Alignment,
+    /// Keep indentation and expand tabs instead of re-flowing words.
+    pub preserve_indent: bool,
+    pub tab_width: usize,
+    /// Table mode: one printed line per input line, no word wrap.
+    pub preformatted: bool,
+    /// Text as white on a black bar.
+    pub highlight: bool,
+    /// Blank margins either side of printed text, in pixels.
+    pub left_margin: u32,
+    pub right_margin: u32,
+    /// Print the current date/time above text jobs.
+    pub timestamp_header: bool,
+    /// strftime format for the timestamp header.
+    pub timestamp_format: String,
+    /// Render text as markdown (headings, bold, lists).
+    pub markdown: bool,
+    /// Bullets or numbers in front of each line of plain text.
+    pub list_style: ListStyle,
+    pub auto_reconnect: bool,
+    /// Index of the Bluetooth adapter used for scanning (0 = first).
+    pub adapter_index: usize,
+    /// Custom printer name substring/regex; empty means the built-in pattern.
+    pub printer_name_pattern: String,
+    /// Peripheral id and name of the last printer connected to, for quick reconnect.
+    pub last_printer_id: String,
+    pub last_printer_name: String,
+    /// Line feeds after each print; 0 for labels, more for receipts.
+    pub feed_lines: u8,
+    /// Ping the printer while idle so it doesn't sleep.
+    pub keep_alive: bool,
+    pub keep_alive_secs: u32,
+    /// Faster but riskier write-without-response transfers.
+    pub write_without_response: bool,
+    /// Mirror text and image prints left to right (iron-on transfers).
+    pub mirror: bool,
+    /// Lightest value still treated as ink when trimming text pages.
+    pub white_threshold: u8,
+    /// Baseline adjustment in pixels for each font that needs one, by font path.
+    pub baseline_offsets: BTreeMap<String, i32>,
+    /// Pauses between the steps of a print.
+    pub print_timing: PrintTiming,
+    /// How the reported battery voltage maps to a percentage.
+    pub battery_curve: BatteryCurve,
+    /// Warn below this battery percentage.
+    pub low_battery_pct: u8,
+    /// Refuse new prints while the battery is below `low_battery_pct`.
+    pub block_low_battery: bool,
+    /// Dry-run prints to files instead of the printer.
+    pub simulate: bool,
+    /// Use the dark UI theme.
+    pub dark_mode: bool,
+    /// Paths of fonts added with "Add font…", listed after the built-in ones.
+    pub custom_fonts: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            font_idx: 0,
+            font_face: 0,
+            font_size_px: 28,
+            line_spacing: 1.0,
+            alignment: Alignment::Left,
+            preserve_indent: false,
+            tab_width: TextOptions::DEFAULT_TAB_WIDTH,
+            preformatted: false,
+            highlight: false,
+            left_margin: 0,
+            right_margin: 0,
+            timestamp_header: false,
+            timestamp_format: TextOptions::DEFAULT_TIMESTAMP_FORMAT.to_string(),
+            markdown: false,
+            list_style: ListStyle::None,
+            auto_reconnect: false,
+            adapter_index: 0,
+            printer_name_pattern: String::new(),
+            last_printer_id: String::new(),
+            last_printer_name: String::new(),
+            feed_lines: DEFAULT_FEED_LINES,
+            keep_alive: false,
+            keep_alive_secs: 60,
+            write_without_response: false,
+            mirror: false,
+            white_threshold: TextOptions::DEFAULT_WHITE_THRESHOLD,
+            baseline_offsets: BTreeMap::new(),
+            print_timing: PrintTiming::default(),
+            battery_curve: BatteryCurve::default(),
+            low_battery_pct: DEFAULT_LOW_BATTERY_PCT,
+            block_low_battery: false,
+            simulate: false,
+            dark_mode: false,
+            custom_fonts: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Path of the saved font, built-in or added.
+    pub fn font_path(&self) -> &str {
+        match self.font_idx.checked_sub(FONT_CHOICES.len()) {
+            None => &FONT_CHOICES[self.font_idx].path,
+            Some(i) => self.custom_fonts.get(i).map_or(&FONT_CHOICES[0].path, |path| path.as_str()),
+        }
+    }
+
+    /// The built-in fonts followed by the added ones that still load; an
+    /// added font whose file is gone or broken is skipped with a warning.
+    pub fn font_choices(&self) -> Vec<FontChoice> {
+        let mut fonts = FONT_CHOICES.to_vec();
+        for path in &self.custom_fonts {
+            match FontChoice::from_file(Path::new(path), format!("CustomPrinter{}", fonts.len())) {
+                Ok(font) => fonts.push(font),
+                Err(e) => log::warn!("Skipping added font: {}", e),
+            }
+        }
+        fonts
+    }
+
+    /// Text layout options for the saved font and text settings.
+    pub fn text_options(&self) -> TextOptions {
+        let font_path = self.font_path();
+        TextOptions {
+            font_path: font_path.to_string(),
+            face_index: self.font_face,
+            font_size: self.font_size_px as f32,
+            line_spacing: self.line_spacing,
+            alignment: self.alignment,
+            preserve_indent: self.preserve_indent,
+            tab_width: self.tab_width,
+            preformatted: self.preformatted,
+            highlight: self.highlight,
+            timestamp_format: self.timestamp_header.then(|| self.timestamp_format.clone()),
+            markdown: self.markdown,
+            left_margin: self.left_margin,
+            right_margin: self.right_margin,
+            mirror: self.mirror,
+            white_threshold: self.white_threshold,
+            list_style: self.list_style,
+            baseline_offset: self.baseline_offsets.get(font_path).copied().unwrap_or(0),
+        }
+    }
+
+    /// Commands that hand the BLE-side settings to the BLE task on startup.
+    pub fn ble_commands(&self) -> Vec<BleCommand> {
+        let mut cmds = vec![
+            BleCommand::SetAdapter(self.adapter_index),
+            BleCommand::SetAutoReconnect(self.auto_reconnect),
+            BleCommand::SetFeedLines(self.feed_lines),
+            BleCommand::SetWriteWithoutResponse(self.write_without_response),
+            BleCommand::SetPrintTiming(self.print_timing),
+            BleCommand::SetSimulate(self.simulate),
+        ];
+        if self.keep_alive {
+            cmds.push(BleCommand::SetKeepAlive(Some(self.keep_alive_secs)));
+        }
+        if !self.printer_name_pattern.is_empty() {
+            cmds.push(BleCommand::SetPrinterNamePattern(self.printer_name_pattern.clone()));
+        }
+        cmds
+    }
+}
+
+/// `~/.config/ctp500/settings.json`, or None if $HOME is unset.
+pub fn settings_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".config").join("ctp500").join("settings.json"))
+}
+
+/// Load settings from disk. A missing or corrupt file yields the defaults.
+pub fn load() -> Settings {
+    let Some(path) = settings_path() else {
+        return Settings::default();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(json) => parse(&json),
+        Err(_) => Settings::default(),
+    }
+}
+
+/// Parse settings JSON, falling back to defaults (with a warning) if it is malformed.
+pub fn parse(json: &str) -> Settings {
+    let mut settings: Settings = serde_json::from_str(json).unwrap_or_else(|e| {
+        log::warn!("Ignoring corrupt settings file: {}", e);
+        Settings::default()
+    });
+    if settings.font_idx >= FONT_CHOICES.len() + settings.custom_fonts.len() {
+        settings.font_idx = 0;
+        settings.font_face = 0;
+    }
+    settings
+}
+
+/// Write settings to disk, creating the config directory if needed.
+pub fn save(settings: &Settings) -> std::io::Result<()> {
+    let path = settings_path()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "HOME is not set"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    std::fs::write(path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let settings = Settings {
+            font_idx: 2,
+            font_size_px: 20,
+            alignment: Alignment::Center,
+            auto_reconnect: true,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(parse(&json), settings);
+    }
+
+    #[test]
+    fn test_settings_partial_and_corrupt() {
+        let partial = parse(r#"{ "font_size_px": 36 }"#);
+        assert_eq!(partial.font_size_px, 36);
+        assert_eq!(partial.font_idx, 0);
+
+        assert_eq!(parse("{ not json"), Settings::default());
+        assert_eq!(parse(r#"{ "font_idx": 999 }"#).font_idx, 0);
+    }
+
+    #[test]
+    fn test_added_fonts_are_restored() {
+        let added = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/box.otf");
+        let json = format!(r#"{{ "font_idx": {}, "custom_fonts": ["/no/such/font.ttf", "{}"] }}"#, FONT_CHOICES.len() + 1, added);
+        let settings = parse(&json);
+        assert_eq!(settings.font_idx, FONT_CHOICES.len() + 1);
+        assert_eq!(settings.font_path(), added);
+        assert_eq!(settings.text_options().font_path, added);
+
+        // The missing file is dropped, so the fixture moves up one place
+        let fonts = settings.font_choices();
+        assert_eq!(fonts.len(), FONT_CHOICES.len() + 1);
+        assert_eq!(fonts.last().unwrap().path, added);
+    }
+
+    #[test]
+    fn test_baseline_offset_follows_font() {
+        let mut settings = Settings { font_idx: 1, ..Default::default() };
+        settings.baseline_offsets.insert(FONT_CHOICES[1].path.to_string(), -3);
+        assert_eq!(settings.text_options().baseline_offset, -3);
+        settings.font_idx = 0;
+        assert_eq!(settings.text_options().baseline_offset, 0);
+    }
+}
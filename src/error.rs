@@ -0,0 +1,64 @@
+use std::fmt;
+
+/// Ways talking to the printer can fail, so callers can match on the failure
+/// mode (e.g. to decide whether a reconnect is worth retrying) instead of
+/// parsing strings.
+#[derive(Debug)]
+pub enum PrinterError {
+    /// No Bluetooth adapter, or not the selected one, is available.
+    NoAdapter,
+    /// The printer isn't advertising nearby.
+    NotFound,
+    /// Connecting or discovering services failed or timed out.
+    ConnectFailed(String),
+    /// The printer doesn't expose a required GATT characteristic ("Write" / "Notify").
+    CharMissing(&'static str),
+    /// A BLE write to the printer failed (after retries).
+    WriteFailed(btleplug::Error),
+    /// Rendering text/QR/barcode output failed before anything was sent.
+    RenderFailed { what: &'static str, reason: String },
+    /// Any other Bluetooth stack error (scanning, adapter events).
+    Bluetooth(btleplug::Error),
+}
+
+impl fmt::Display for PrinterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrinterError::NoAdapter => write!(f, "No Bluetooth adapter found"),
+            PrinterError::NotFound => write!(f, "Printer not in range"),
+            PrinterError::ConnectFailed(reason) => write!(f, "Connection failed: {}", reason),
+            PrinterError::CharMissing(which) => write!(f, "{} characteristic not found", which),
+            PrinterError::WriteFailed(e) => write!(f, "Write failed: {}", e),
+            PrinterError::RenderFailed { what, reason } => write!(f, "{} render error: {}", what, reason),
+            PrinterError::Bluetooth(e) => write!(f, "Bluetooth error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PrinterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PrinterError::WriteFailed(e) | PrinterError::Bluetooth(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<btleplug::Error> for PrinterError {
+    fn from(e: btleplug::Error) -> Self {
+        PrinterError::Bluetooth(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_messages() {
+        assert_eq!(PrinterError::CharMissing("Write").to_string(), "Write characteristic not found");
+        let e = PrinterError::RenderFailed { what: "QR code", reason: "data too long".into() };
+        assert_eq!(e.to_string(), "QR code render error: data too long");
+        assert!(std::error::Error::source(&PrinterError::NotFound).is_none());
+    }
+}
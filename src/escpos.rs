@@ -1,19 +1,335 @@
-use image::{DynamicImage, GrayImage, ImageBuffer, Luma, imageops};
-use crate::types::PRINTER_WIDTH;
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgb, RgbImage, imageops};
+use crate::types::{DOTS_PER_MM, PRINTER_WIDTH};
+
+/// Dots between the major ticks of the preview ruler.
+pub const RULER_STEP_PX: u32 = 50;
+const RULER_PX_COLOR: Rgb<u8> = Rgb([0, 122, 255]);
+const RULER_MM_COLOR: Rgb<u8> = Rgb([255, 59, 48]);
+/// Blur radius (Gaussian sigma, in pixels) of the unsharp mask behind
+/// `RasterOptions::sharpen`; about the softening a Lanczos downscale leaves.
+pub const SHARPEN_RADIUS: f32 = 1.0;
+
+/// User-selectable options applied while converting an image to a raster.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterOptions {
+    /// Print light pixels as ink instead of dark ones (white-on-black).
+    pub invert: bool,
+    /// Added to every channel, -255..=255 (0 = unchanged).
+    pub brightness: i32,
+    /// Multiplier on each channel's distance from mid-gray (1.0 = unchanged).
+    pub contrast: f32,
+    /// Stretch the luminance range to full black..white before the
+    /// brightness/contrast adjustment (see `auto_contrast`).
+    pub auto_levels: bool,
+    /// Unsharp mask strength applied after scaling to the paper width
+    /// (0 = off, see `unsharp`).
+    pub sharpen: f32,
+    /// How images narrower than the paper are placed or scaled.
+    pub fit: FitMode,
+    /// How gray levels are reduced to ink / no ink.
+    pub dither: DitherMode,
+    /// How color is converted to gray before dithering.
+    pub grayscale: GrayscaleMethod,
+    /// Flip the fitted image left to right (for iron-on transfer paper).
+    pub mirror: bool,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self {
+            invert: false,
+            brightness: 0,
+            contrast: 1.0,
+            auto_levels: false,
+            sharpen: 0.0,
+            fit: FitMode::default(),
+            dither: DitherMode::default(),
+            grayscale: GrayscaleMethod::default(),
+            mirror: false,
+        }
+    }
+}
+
+/// Conversion of a color image to gray. Colored logos can lose detail under
+/// plain luminance (e.g. red comes out nearly black); the other methods keep
+/// differently colored regions apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrayscaleMethod {
+    /// Standard luma weights (what `to_luma8` uses).
+    #[default]
+    Luminance,
+    /// Plain mean of R, G and B.
+    Average,
+    /// Brightest channel, so any saturated color prints light.
+    MaxChannel,
+    Red,
+    Green,
+    Blue,
+}
+
+impl GrayscaleMethod {
+    pub const ALL: [GrayscaleMethod; 6] = [
+        GrayscaleMethod::Luminance,
+        GrayscaleMethod::Average,
+        GrayscaleMethod::MaxChannel,
+        GrayscaleMethod::Red,
+        GrayscaleMethod::Green,
+        GrayscaleMethod::Blue,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GrayscaleMethod::Luminance => "Luminance",
+            GrayscaleMethod::Average => "Average",
+            GrayscaleMethod::MaxChannel => "Brightest channel",
+            GrayscaleMethod::Red => "Red channel",
+            GrayscaleMethod::Green => "Green channel",
+            GrayscaleMethod::Blue => "Blue channel",
+        }
+    }
+
+    /// Convert `img` to gray with this method.
+    pub fn apply(self, img: &DynamicImage) -> GrayImage {
+        let pick: fn(u8, u8, u8) -> u8 = match self {
+            GrayscaleMethod::Luminance => return img.to_luma8(),
+            GrayscaleMethod::Average => |r, g, b| ((r as u16 + g as u16 + b as u16) / 3) as u8,
+            GrayscaleMethod::MaxChannel => |r, g, b| r.max(g).max(b),
+            GrayscaleMethod::Red => |r, _, _| r,
+            GrayscaleMethod::Green => |_, g, _| g,
+            GrayscaleMethod::Blue => |_, _, b| b,
+        };
+        let rgb = img.to_rgb8();
+        ImageBuffer::from_fn(rgb.width(), rgb.height(), |x, y| {
+            let [r, g, b] = rgb.get_pixel(x, y).0;
+            Luma([pick(r, g, b)])
+        })
+    }
+}
+
+/// Reduction of grayscale to the printer's 1-bit output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DitherMode {
+    /// Hard cut at mid-gray; best for text and line art.
+    #[default]
+    Threshold,
+    /// Error diffusion to four neighbours; smooth photo tones.
+    FloydSteinberg,
+    /// Diffuses only 3/4 of the error, giving lighter, higher-contrast output.
+    Atkinson,
+    /// Ordered dithering with a 2×2, 4×4 or 8×8 Bayer matrix; fast, retro pattern.
+    Bayer2,
+    Bayer4,
+    Bayer8,
+}
+
+impl DitherMode {
+    pub const ALL: [DitherMode; 6] = [
+        DitherMode::Threshold,
+        DitherMode::FloydSteinberg,
+        DitherMode::Atkinson,
+        DitherMode::Bayer2,
+        DitherMode::Bayer4,
+        DitherMode::Bayer8,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DitherMode::Threshold => "Threshold",
+            DitherMode::FloydSteinberg => "Floyd–Steinberg",
+            DitherMode::Atkinson => "Atkinson",
+            DitherMode::Bayer2 => "Ordered 2×2",
+            DitherMode::Bayer4 => "Ordered 4×4",
+            DitherMode::Bayer8 => "Ordered 8×8",
+        }
+    }
+}
+
+/// Horizontal fit of an image on the paper. Images wider than PRINTER_WIDTH
+/// are always scaled down; the mode decides what happens to narrower ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// Keep the original size, aligned to the left edge.
+    #[default]
+    FitWidthLeft,
+    /// Keep the original size, centered on the paper.
+    Center,
+    /// Scale up to the full paper width, keeping the aspect ratio.
+    Stretch,
+}
+
+impl FitMode {
+    pub const ALL: [FitMode; 3] = [FitMode::FitWidthLeft, FitMode::Center, FitMode::Stretch];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FitMode::FitWidthLeft => "Original size, left",
+            FitMode::Center => "Original size, centered",
+            FitMode::Stretch => "Stretch to full width",
+        }
+    }
+}
+
+/// Clockwise rotation applied to a loaded image before printing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    /// The next rotation in the 0 → 90 → 180 → 270 → 0 cycle.
+    pub fn next(self) -> Self {
+        match self {
+            Rotation::None => Rotation::Cw90,
+            Rotation::Cw90 => Rotation::Cw180,
+            Rotation::Cw180 => Rotation::Cw270,
+            Rotation::Cw270 => Rotation::None,
+        }
+    }
+
+    pub fn degrees(self) -> u32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Cw90 => 90,
+            Rotation::Cw180 => 180,
+            Rotation::Cw270 => 270,
+        }
+    }
+
+    /// Rotate `img`. Sideways results wider than the paper are scaled down
+    /// later by `image_to_escpos_bytes_with` like any other wide image.
+    pub fn apply(self, img: &DynamicImage) -> DynamicImage {
+        match self {
+            Rotation::None => img.clone(),
+            Rotation::Cw90 => img.rotate90(),
+            Rotation::Cw180 => img.rotate180(),
+            Rotation::Cw270 => img.rotate270(),
+        }
+    }
+}
+
+/// Apply brightness and contrast to every pixel. Contrast pivots around mid-gray
+/// (128) and brightness is added afterwards; results are clamped to 0..=255.
+pub fn adjust(img: &DynamicImage, brightness: i32, contrast: f32) -> DynamicImage {
+    let mut rgb = img.to_rgb8();
+    for pixel in rgb.pixels_mut() {
+        for c in pixel.0.iter_mut() {
+            let v = (*c as f32 - 128.0) * contrast + 128.0 + brightness as f32;
+            *c = v.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(rgb)
+}
+
+/// Histogram stretch: map the 2nd percentile of luminance to black and the
+/// 98th to white, linearly, so washed-out photos use the full gray range.
+/// Images that are (nearly) one flat shade are returned unchanged.
+pub fn auto_contrast(img: &DynamicImage) -> DynamicImage {
+    let mut histogram = [0u64; 256];
+    for p in img.to_luma8().pixels() {
+        histogram[p[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let percentile = |pct: u64| {
+        let target = total * pct / 100;
+        let mut seen = 0;
+        histogram.iter().position(|&n| {
+            seen += n;
+            seen > target
+        })
+        .unwrap_or(255) as f32
+    };
+    let (lo, hi) = (percentile(2), percentile(98));
+    if hi <= lo {
+        return img.clone();
+    }
+    let mut rgb = img.to_rgb8();
+    for pixel in rgb.pixels_mut() {
+        for c in pixel.0.iter_mut() {
+            *c = ((*c as f32 - lo) * 255.0 / (hi - lo)).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(rgb)
+}
+
+/// Unsharp mask: add `amount` times the difference between the image and a
+/// Gaussian blur of it (sigma `radius`), crisping edges that resizing softened.
+pub fn unsharp(img: &DynamicImage, amount: f32, radius: f32) -> DynamicImage {
+    let mut rgb = img.to_rgb8();
+    let blurred = imageops::blur(&rgb, radius);
+    for (pixel, soft) in rgb.pixels_mut().zip(blurred.pixels()) {
+        for (c, &b) in pixel.0.iter_mut().zip(soft.0.iter()) {
+            let v = *c as f32 + amount * (*c as f32 - b as f32);
+            *c = v.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(rgb)
+}
 
 /// Convert a DynamicImage to the ESC/POS raster byte sequence for the CTP500.
 /// This is a direct port of Python's `_image_to_bytes(im)`.
 pub fn image_to_escpos_bytes(img: &DynamicImage) -> Vec<u8> {
-    // 1. Scale down if wider than printer width
-    let img = if img.width() > PRINTER_WIDTH {
+    image_to_escpos_bytes_with(img, &RasterOptions::default())
+}
+
+/// Height in dots of a `width`×`height` image once fitted to the paper, without
+/// doing the resize. Matches the scaling in `fit_to_paper`.
+pub fn printed_height(width: u32, height: u32, options: &RasterOptions) -> u32 {
+    if width > PRINTER_WIDTH || (options.fit == FitMode::Stretch && width < PRINTER_WIDTH && width > 0) {
+        (height as f64 * PRINTER_WIDTH as f64 / width as f64) as u32
+    } else {
+        height
+    }
+}
+
+/// Slice `img` into horizontal bands that each print at most `max_height`
+/// dots tall once fitted, so a long image comes out as tearable pieces.
+/// Returns the image unchanged (as one segment) if it already fits.
+pub fn split_into_segments(img: &DynamicImage, options: &RasterOptions, max_height: u32) -> Vec<DynamicImage> {
+    let (w, h) = (img.width(), img.height());
+    let printed = printed_height(w, h, options);
+    if max_height == 0 || printed <= max_height || h == 0 {
+        return vec![img.clone()];
+    }
+    // Source rows per segment, scaled by the same factor fit_to_paper uses
+    let band = ((max_height as f64 * h as f64 / printed as f64).floor() as u32).max(1);
+    (0..h)
+        .step_by(band as usize)
+        .map(|y| img.crop_imm(0, y, w, band.min(h - y)))
+        .collect()
+}
+
+/// Scale, adjust, and pad `img` onto a PRINTER_WIDTH-wide canvas as configured
+/// by `options` (steps 1–2 of the raster pipeline). Returns the canvas and the
+/// horizontal span `(x, width)` the image occupies on it.
+pub fn fit_to_paper(img: &DynamicImage, options: &RasterOptions) -> (DynamicImage, (u32, u32)) {
+    // 1. Scale down if wider than printer width (or up, when stretching)
+    let img = if img.width() > PRINTER_WIDTH
+        || (options.fit == FitMode::Stretch && img.width() < PRINTER_WIDTH)
+    {
         let new_height = (img.height() as f64 * PRINTER_WIDTH as f64 / img.width() as f64) as u32;
         img.resize(PRINTER_WIDTH, new_height, imageops::FilterType::Lanczos3)
     } else {
         img.clone()
     };
 
+    // 1a. Sharpen what the resize softened
+    let img = if options.sharpen > 0.0 { unsharp(&img, options.sharpen, SHARPEN_RADIUS) } else { img };
+
+    // 1b. Auto levels, then brightness/contrast, before padding so the
+    //     margins stay white
+    let img = if options.auto_levels { auto_contrast(&img) } else { img };
+    let img = if options.brightness != 0 || options.contrast != 1.0 {
+        adjust(&img, options.brightness, options.contrast)
+    } else {
+        img
+    };
+
     // 2. Pad to printer width if narrower
-    let img = if img.width() < PRINTER_WIDTH {
+    let (canvas, (x, w)) = if img.width() < PRINTER_WIDTH {
         let mut padded = DynamicImage::new_rgb8(PRINTER_WIDTH, img.height());
         // Fill with white
         for y in 0..img.height() {
@@ -21,15 +337,168 @@ pub fn image_to_escpos_bytes(img: &DynamicImage) -> Vec<u8> {
                 padded.as_mut_rgb8().unwrap().put_pixel(x, y, image::Rgb([255, 255, 255]));
             }
         }
-        imageops::overlay(&mut padded, &img, 0, 0);
-        padded
+        let x = match options.fit {
+            FitMode::Center => (PRINTER_WIDTH - img.width()) / 2,
+            FitMode::FitWidthLeft | FitMode::Stretch => 0,
+        };
+        imageops::overlay(&mut padded, &img, x as i64, 0);
+        (padded, (x, img.width()))
     } else {
-        img
+        let width = img.width();
+        (img, (0, width))
+    };
+
+    // 3. Mirror last, so it acts on the image as it sits on the paper
+    //    (after any rotation and placement)
+    if options.mirror {
+        let flipped = DynamicImage::ImageRgba8(imageops::flip_horizontal(&canvas));
+        (flipped, (canvas.width() - x - w, w))
+    } else {
+        (canvas, (x, w))
+    }
+}
+
+/// Reduce a fitted canvas to black (0 = ink) and white (255) with the
+/// configured dither mode. Inversion happens first, so light areas dither
+/// as dark ones when printing white-on-black.
+fn to_bitmap(canvas: &DynamicImage, options: &RasterOptions) -> GrayImage {
+    let mut gray = options.grayscale.apply(canvas);
+    if options.invert {
+        for p in gray.pixels_mut() {
+            p[0] = 255 - p[0];
+        }
+    }
+    match options.dither {
+        DitherMode::Threshold => {
+            for p in gray.pixels_mut() {
+                p[0] = if p[0] < 128 { 0 } else { 255 };
+            }
+            gray
+        }
+        DitherMode::FloydSteinberg => diffuse_error(&gray, &[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)], 16),
+        DitherMode::Atkinson => {
+            diffuse_error(&gray, &[(1, 0, 1), (2, 0, 1), (-1, 1, 1), (0, 1, 1), (1, 1, 1), (0, 2, 1)], 8)
+        }
+        DitherMode::Bayer2 => ordered_dither(gray, 2),
+        DitherMode::Bayer4 => ordered_dither(gray, 4),
+        DitherMode::Bayer8 => ordered_dither(gray, 8),
+    }
+}
+
+/// Error-diffusion dither: each pixel's quantization error is spread to the
+/// `(dx, dy, weight)` neighbours, each receiving `weight / divisor` of it.
+fn diffuse_error(gray: &GrayImage, kernel: &[(i32, i32, i32)], divisor: i32) -> GrayImage {
+    let (w, h) = gray.dimensions();
+    let mut levels: Vec<i32> = gray.pixels().map(|p| p[0] as i32).collect();
+    let mut out: GrayImage = ImageBuffer::new(w, h);
+    for y in 0..h as i32 {
+        for x in 0..w as i32 {
+            let old = levels[(y * w as i32 + x) as usize];
+            let new = if old < 128 { 0 } else { 255 };
+            out.put_pixel(x as u32, y as u32, Luma([new as u8]));
+            let err = old - new;
+            for &(dx, dy, weight) in kernel {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx >= 0 && nx < w as i32 && ny < h as i32 {
+                    levels[(ny * w as i32 + nx) as usize] += err * weight / divisor;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The `n`×`n` Bayer index matrix (n a power of two), values 0..n².
+fn bayer_matrix(n: usize) -> Vec<Vec<u32>> {
+    let mut m = vec![vec![0u32]];
+    while m.len() < n {
+        let size = m.len();
+        let mut next = vec![vec![0u32; size * 2]; size * 2];
+        for y in 0..size {
+            for x in 0..size {
+                let v = 4 * m[y][x];
+                next[y][x] = v;
+                next[y][x + size] = v + 2;
+                next[y + size][x] = v + 3;
+                next[y + size][x + size] = v + 1;
+            }
+        }
+        m = next;
+    }
+    m
+}
+
+/// Ordered dither: compare each pixel against a tiled Bayer threshold map.
+fn ordered_dither(mut gray: GrayImage, n: usize) -> GrayImage {
+    let matrix = bayer_matrix(n);
+    let cells = (n * n) as f32;
+    for (x, y, p) in gray.enumerate_pixels_mut() {
+        let index = matrix[y as usize % n][x as usize % n] as f32;
+        let threshold = (index + 0.5) * 255.0 / cells;
+        p[0] = if (p[0] as f32) < threshold { 0 } else { 255 };
+    }
+    gray
+}
+
+/// Render exactly what will be printed: the fitted canvas dithered to
+/// black (ink) and white. Also returns the image span as in `fit_to_paper`.
+pub fn raster_preview(img: &DynamicImage, options: &RasterOptions) -> (GrayImage, (u32, u32)) {
+    let (canvas, span) = fit_to_paper(img, options);
+    (to_bitmap(&canvas, options), span)
+}
+
+/// Join rendered pages top to bottom into one bitmap, as they come off the
+/// roll (narrower pages are padded with paper white).
+pub fn stack_pages(pages: &[GrayImage]) -> GrayImage {
+    let width = pages.iter().map(|p| p.width()).max().unwrap_or(0);
+    let height = pages.iter().map(|p| p.height()).sum();
+    let mut out = GrayImage::from_pixel(width, height, Luma([255]));
+    let mut y = 0;
+    for page in pages {
+        imageops::replace(&mut out, page, 0, y as i64);
+        y += page.height();
+    }
+    out
+}
+
+/// Overlay a ruler on a rendered page for layout work: blue ticks every
+/// RULER_STEP_PX dots along the top and left edges, and a red millimetre
+/// scale along the top (longer every 5 mm and 10 mm). Display only; the
+/// returned copy is never sent to the printer.
+pub fn draw_ruler(page: &GrayImage) -> RgbImage {
+    let mut out = DynamicImage::ImageLuma8(page.clone()).to_rgb8();
+    let (w, h) = out.dimensions();
+    let mut tick = |x0: u32, y0: u32, dx: u32, dy: u32, color: Rgb<u8>| {
+        for x in x0..(x0 + dx).min(w) {
+            for y in y0..(y0 + dy).min(h) {
+                out.put_pixel(x, y, color);
+            }
+        }
     };
+    for mm in 0.. {
+        let x = (mm as f32 * DOTS_PER_MM).round() as u32;
+        if x >= w {
+            break;
+        }
+        let len = if mm % 10 == 0 { 10 } else if mm % 5 == 0 { 6 } else { 3 };
+        tick(x, 0, 1, len, RULER_MM_COLOR);
+    }
+    for x in (0..w).step_by(RULER_STEP_PX as usize) {
+        tick(x, 0, 2, 16, RULER_PX_COLOR);
+    }
+    for y in (0..h).step_by(RULER_STEP_PX as usize) {
+        tick(0, y, 16, 2, RULER_PX_COLOR);
+    }
+    out
+}
+
+/// Same as `image_to_escpos_bytes`, honouring the given raster options.
+pub fn image_to_escpos_bytes_with(img: &DynamicImage, options: &RasterOptions) -> Vec<u8> {
+    let (img, _) = fit_to_paper(img, options);
 
-    // 3. Convert to grayscale and threshold to 1-bit logical
-    //    pixel >= 128 → white (255), < 128 → black (0)
-    let gray = img.to_luma8();
+    // 3. Convert to grayscale and dither/threshold to 1-bit logical
+    //    black (0) = ink, white (255) = paper
+    let gray = to_bitmap(&img, options);
     let (w, h) = gray.dimensions();
 
     // 4. Pad width to multiple of 8
@@ -55,8 +524,7 @@ pub fn image_to_escpos_bytes(img: &DynamicImage) -> Vec<u8> {
             for bit in 0..8u32 {
                 let x = byte_idx as u32 * 8 + bit;
                 let pixel = padded_gray.get_pixel(x, y)[0];
-                // Invert: dark pixels (< 128) become 1, light pixels become 0
-                let ink = if pixel < 128 { 1u8 } else { 0u8 };
+                let ink = if pixel == 0 { 1u8 } else { 0u8 };
                 byte |= ink << (7 - bit);
             }
             pixel_data.push(byte);
@@ -76,6 +544,38 @@ pub fn image_to_escpos_bytes(img: &DynamicImage) -> Vec<u8> {
     out
 }
 
+/// Width and height in dots from a `GS v 0` raster header.
+pub fn raster_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let header = bytes.get(..8)?;
+    if header[..4] != [0x1d, 0x76, 0x30, 0x00] {
+        return None;
+    }
+    let width_bytes = u16::from_le_bytes([header[4], header[5]]) as u32;
+    let height = u16::from_le_bytes([header[6], header[7]]) as u32;
+    Some((width_bytes * 8, height))
+}
+
+/// Decode a `GS v 0` raster command (as produced by `image_to_escpos_bytes_with`)
+/// back into a black-on-white bitmap. Returns None if the header is missing or
+/// the data is shorter than the header claims.
+pub fn raster_to_image(bytes: &[u8]) -> Option<GrayImage> {
+    let (width, height) = raster_size(bytes)?;
+    let width_bytes = width / 8;
+    let data = bytes.get(8..8 + (width_bytes * height) as usize)?;
+
+    let mut img: GrayImage = ImageBuffer::from_pixel(width_bytes * 8, height, Luma([255u8]));
+    for (i, byte) in data.iter().enumerate() {
+        let y = i as u32 / width_bytes;
+        let x0 = (i as u32 % width_bytes) * 8;
+        for bit in 0..8 {
+            if byte & (0x80 >> bit) != 0 {
+                img.put_pixel(x0 + bit, y, Luma([0u8]));
+            }
+        }
+    }
+    Some(img)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,4 +626,258 @@ mod tests {
         let pixel_bytes = &bytes[8..];
         assert!(pixel_bytes.iter().all(|&b| b == 0xFF));
     }
+
+    #[test]
+    fn test_escpos_invert_option() {
+        // With invert set, a fully white image is all ink
+        let img = DynamicImage::ImageRgb8(image::ImageBuffer::from_pixel(
+            384, 1, image::Rgb([255u8, 255, 255]),
+        ));
+        let options = RasterOptions { invert: true, ..Default::default() };
+        let bytes = image_to_escpos_bytes_with(&img, &options);
+        assert!(bytes[8..].iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn test_fit_mode_placement() {
+        // 16px-wide black image: two ink bytes at the left, or in the middle when centered
+        let img = DynamicImage::new_rgb8(16, 1);
+        let row = |fit| {
+            let options = RasterOptions { fit, ..Default::default() };
+            image_to_escpos_bytes_with(&img, &options)[8..].to_vec()
+        };
+        let left = row(FitMode::FitWidthLeft);
+        assert_eq!(&left[..2], &[0xff, 0xff]);
+        assert!(left[2..].iter().all(|&b| b == 0));
+
+        let centered = row(FitMode::Center);
+        let mid = (PRINTER_WIDTH / 16) as usize;
+        assert_eq!(&centered[mid - 1..mid + 1], &[0xff, 0xff]);
+        assert_eq!(centered.iter().filter(|&&b| b != 0).count(), 2);
+
+        // Stretched: whole row is ink, and the height scales with the width
+        let stretched = row(FitMode::Stretch);
+        assert!(stretched.iter().all(|&b| b == 0xff));
+        assert_eq!(stretched.len(), (PRINTER_WIDTH / 8 * 24) as usize);
+    }
+
+    #[test]
+    fn test_printed_height_matches_fit() {
+        for (w, h) in [(1000, 6000), (200, 50), (384, 10)] {
+            for fit in FitMode::ALL {
+                let options = RasterOptions { fit, ..Default::default() };
+                let (fitted, _) = fit_to_paper(&DynamicImage::new_rgb8(w, h), &options);
+                assert_eq!(printed_height(w, h, &options), fitted.height(), "{}x{} {:?}", w, h, fit);
+            }
+        }
+    }
+
+    /// Fraction of ink pixels when dithering a flat gray square.
+    fn ink_coverage(level: u8, dither: DitherMode) -> f32 {
+        let img = DynamicImage::ImageLuma8(ImageBuffer::from_pixel(64, 64, Luma([level])));
+        let bitmap = to_bitmap(&img, &RasterOptions { dither, ..Default::default() });
+        bitmap.pixels().filter(|p| p[0] == 0).count() as f32 / (64.0 * 64.0)
+    }
+
+    #[test]
+    fn test_dither_modes_deterministic() {
+        let gradient = DynamicImage::ImageLuma8(ImageBuffer::from_fn(64, 16, |x, _| Luma([(x * 4) as u8])));
+        for dither in DitherMode::ALL {
+            let options = RasterOptions { dither, ..Default::default() };
+            let first = image_to_escpos_bytes_with(&gradient, &options);
+            assert_eq!(first, image_to_escpos_bytes_with(&gradient, &options), "{:?}", dither);
+            // Preview and printed bits agree for every mode
+            let (preview, _) = raster_preview(&gradient, &options);
+            assert_eq!(raster_to_image(&first).unwrap(), preview, "{:?}", dither);
+        }
+    }
+
+    #[test]
+    fn test_dither_tone_coverage() {
+        // Mid-gray: threshold prints nothing, dithers cover about half
+        assert_eq!(ink_coverage(128, DitherMode::Threshold), 0.0);
+        for dither in [DitherMode::FloydSteinberg, DitherMode::Bayer2, DitherMode::Bayer4, DitherMode::Bayer8] {
+            let c = ink_coverage(128, dither);
+            assert!((0.4..=0.6).contains(&c), "{:?}: {}", dither, c);
+        }
+        // Atkinson drops part of the error, so light grays print lighter
+        assert!(ink_coverage(200, DitherMode::Atkinson) < ink_coverage(200, DitherMode::FloydSteinberg));
+        assert_eq!(bayer_matrix(2), vec![vec![0, 2], vec![3, 1]]);
+        let mut values: Vec<u32> = bayer_matrix(8).concat();
+        values.sort_unstable();
+        assert_eq!(values, (0..64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_grayscale_methods_differ_on_red() {
+        let red = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(2, 2, image::Rgb([255, 0, 0])));
+        let luma = GrayscaleMethod::Luminance.apply(&red).get_pixel(0, 0)[0];
+        let max = GrayscaleMethod::MaxChannel.apply(&red).get_pixel(0, 0)[0];
+        assert!(luma < 128, "luminance {}", luma);
+        assert_eq!(max, 255);
+        assert_eq!(GrayscaleMethod::Green.apply(&red).get_pixel(0, 0)[0], 0);
+        assert_eq!(GrayscaleMethod::Average.apply(&red).get_pixel(0, 0)[0], 85);
+    }
+
+    #[test]
+    fn test_split_into_segments() {
+        let options = RasterOptions::default();
+        // 1000x6000 prints 384x2304: three segments of at most 1000 dots
+        let segments = split_into_segments(&DynamicImage::new_rgb8(1000, 6000), &options, 1000);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments.iter().map(|s| s.height()).sum::<u32>(), 6000);
+        assert!(segments.iter().all(|s| printed_height(s.width(), s.height(), &options) <= 1000));
+
+        let short = DynamicImage::new_rgb8(384, 500);
+        assert_eq!(split_into_segments(&short, &options, 1000).len(), 1);
+        assert_eq!(split_into_segments(&short, &options, 0).len(), 1);
+    }
+
+    #[test]
+    fn test_raster_preview_matches_printed_bits() {
+        let mut img = DynamicImage::new_rgb8(100, 4);
+        img.as_mut_rgb8().unwrap().put_pixel(10, 1, image::Rgb([255, 255, 255]));
+        let options = RasterOptions { fit: FitMode::Center, ..Default::default() };
+        let (preview, span) = raster_preview(&img, &options);
+        assert_eq!(span, (142, 100));
+        assert_eq!(preview.width(), PRINTER_WIDTH);
+
+        let bytes = image_to_escpos_bytes_with(&img, &options);
+        let row_bytes = (PRINTER_WIDTH / 8) as usize;
+        for y in 0..4 {
+            for x in 0..PRINTER_WIDTH {
+                let byte = bytes[8 + y as usize * row_bytes + (x / 8) as usize];
+                let printed = byte & (0x80 >> (x % 8)) != 0;
+                assert_eq!(preview.get_pixel(x, y)[0] == 0, printed, "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mirror_flips_placed_image() {
+        // A black block at the left of a narrow image, left-aligned on the paper
+        let mut img = image::RgbImage::from_pixel(100, 4, image::Rgb([255, 255, 255]));
+        for y in 0..4 {
+            img.put_pixel(0, y, image::Rgb([0, 0, 0]));
+        }
+        let img = DynamicImage::ImageRgb8(img);
+        let options = RasterOptions { fit: FitMode::FitWidthLeft, mirror: true, ..Default::default() };
+        let (bitmap, span) = raster_preview(&img, &options);
+        assert_eq!(span, (PRINTER_WIDTH - 100, 100));
+        assert_eq!(bitmap.get_pixel(PRINTER_WIDTH - 1, 0)[0], 0);
+        assert_eq!(bitmap.get_pixel(0, 0)[0], 255);
+    }
+
+    #[test]
+    fn test_stack_pages() {
+        let black = GrayImage::from_pixel(PRINTER_WIDTH, 10, Luma([0]));
+        let narrow = GrayImage::from_pixel(100, 5, Luma([0]));
+        let stacked = stack_pages(&[black, narrow]);
+        assert_eq!(stacked.dimensions(), (PRINTER_WIDTH, 15));
+        assert_eq!(stacked.get_pixel(200, 9)[0], 0);
+        assert_eq!(stacked.get_pixel(50, 12)[0], 0);
+        assert_eq!(stacked.get_pixel(200, 12)[0], 255);
+        assert_eq!(stack_pages(&[]).dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn test_auto_contrast_widens_range() {
+        // A dull gradient between 100 and 150
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(100, 10, |x, _| Luma([100 + x as u8 / 2])));
+        let range = |img: &DynamicImage| {
+            let gray = img.to_luma8();
+            let (min, max) = gray.pixels().fold((255, 0), |(lo, hi), p| (p[0].min(lo), p[0].max(hi)));
+            max - min
+        };
+        let stretched = auto_contrast(&img);
+        assert_eq!(range(&img), 49);
+        assert!(range(&stretched) >= 250, "range {}", range(&stretched));
+
+        // A flat image has nothing to stretch
+        let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(10, 10, Luma([128])));
+        assert_eq!(auto_contrast(&flat).to_luma8().get_pixel(5, 5)[0], 128);
+    }
+
+    #[test]
+    fn test_unsharp_steepens_edges() {
+        // A soft edge from dark gray to light gray, like a downscaled outline
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(40, 8, |x, _| {
+            Luma([(64 + (x.saturating_sub(15) * 128 / 10).min(128)) as u8])
+        }));
+        // Total horizontal gradient magnitude along a row
+        let gradient = |img: &DynamicImage| -> u32 {
+            let gray = img.to_luma8();
+            (1..gray.width()).map(|x| gray.get_pixel(x, 4)[0].abs_diff(gray.get_pixel(x - 1, 4)[0]) as u32).sum()
+        };
+        let sharpened = unsharp(&img, 1.5, SHARPEN_RADIUS);
+        assert!(gradient(&sharpened) > gradient(&img), "{} vs {}", gradient(&sharpened), gradient(&img));
+        assert_eq!((sharpened.width(), sharpened.height()), (img.width(), img.height()));
+
+        // Flat areas are left alone
+        let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(10, 10, Luma([90])));
+        assert_eq!(unsharp(&flat, 2.0, SHARPEN_RADIUS).to_luma8().get_pixel(5, 5)[0], 90);
+    }
+
+    #[test]
+    fn test_ruler_overlay() {
+        let page = GrayImage::from_pixel(PRINTER_WIDTH, 120, Luma([255]));
+        let ruled = draw_ruler(&page);
+        assert_eq!(ruled.dimensions(), page.dimensions());
+        assert_eq!(*ruled.get_pixel(100, 10), RULER_PX_COLOR);
+        assert_eq!(*ruled.get_pixel(4, 100), RULER_PX_COLOR);
+        // 10 mm = 80 dots gets a long mm tick; 1 mm only a short one
+        assert_eq!(*ruled.get_pixel(80, 9), RULER_MM_COLOR);
+        assert_eq!(*ruled.get_pixel(8, 2), RULER_MM_COLOR);
+        assert_eq!(*ruled.get_pixel(8, 5), Rgb([255, 255, 255]));
+        assert_eq!(*ruled.get_pixel(200, 60), Rgb([255, 255, 255]));
+    }
+
+    #[test]
+    fn test_raster_round_trip() {
+        let mut img = DynamicImage::new_rgb8(20, 3);
+        for x in 0..20 {
+            img.as_mut_rgb8().unwrap().put_pixel(x, 1, image::Rgb([255, 255, 255]));
+        }
+        let (expected, _) = raster_preview(&img, &RasterOptions::default());
+        let decoded = raster_to_image(&image_to_escpos_bytes(&img)).unwrap();
+        assert_eq!(decoded, expected);
+
+        assert!(raster_to_image(&[0x1b, 0x40]).is_none());
+        assert!(raster_to_image(&[0x1d, 0x76, 0x30, 0x00, 48, 0, 10, 0, 0xff]).is_none());
+    }
+
+    #[test]
+    fn test_rotation_cycle_swaps_dimensions() {
+        let img = DynamicImage::new_rgb8(600, 100);
+        let mut rot = Rotation::default();
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            seen.push(rot.degrees());
+            rot = rot.next();
+        }
+        assert_eq!(seen, vec![0, 90, 180, 270]);
+        assert_eq!(rot, Rotation::None);
+
+        let sideways = Rotation::Cw90.apply(&img);
+        assert_eq!((sideways.width(), sideways.height()), (100, 600));
+        let upside_down = Rotation::Cw180.apply(&img);
+        assert_eq!((upside_down.width(), upside_down.height()), (600, 100));
+    }
+
+    #[test]
+    fn test_adjust_brightness_and_contrast() {
+        let img = DynamicImage::ImageRgb8(image::ImageBuffer::from_pixel(
+            2, 2, image::Rgb([100u8, 128, 200]),
+        ));
+
+        let same = adjust(&img, 0, 1.0).to_rgb8();
+        assert_eq!(same.get_pixel(0, 0).0, [100, 128, 200]);
+
+        let brighter = adjust(&img, 50, 1.0).to_rgb8();
+        assert_eq!(brighter.get_pixel(0, 0).0, [150, 178, 250]);
+
+        // Contrast stretches away from 128 and clamps
+        let contrasty = adjust(&img, 0, 2.0).to_rgb8();
+        assert_eq!(contrasty.get_pixel(0, 0).0, [72, 128, 255]);
+    }
 }
@@ -0,0 +1,129 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::time::Duration;
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageFormat};
+
+/// Extensions offered by the image file pickers.
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "webp", "gif"];
+
+/// Give up on slow servers rather than leaving the UI waiting forever.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+/// Refuse downloads larger than this; nothing that big prints usefully.
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Parse and check a user-entered image URL; only http(s) is allowed.
+pub fn parse_image_url(input: &str) -> Result<reqwest::Url, String> {
+    let url = reqwest::Url::parse(input.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        other => Err(format!("Unsupported URL scheme \"{}\" (use http or https)", other)),
+    }
+}
+
+/// Reject responses whose Content-Type says they aren't an image (e.g. an
+/// HTML page). A missing header is allowed; decoding will catch it.
+fn check_content_type(content_type: Option<&str>) -> Result<(), String> {
+    match content_type {
+        Some(ct) if !ct.trim().to_ascii_lowercase().starts_with("image/") => {
+            let mime = ct.split(';').next().unwrap_or(ct).trim();
+            Err(format!("URL points to {}, not an image", mime))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Download an image over HTTP(S), following redirects, and decode it.
+pub async fn fetch_image(input: &str) -> Result<DynamicImage, String> {
+    let url = parse_image_url(input)?;
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("HTTP client error: {}", e))?;
+    let response = client.get(url).send().await.map_err(|e| {
+        if e.is_timeout() {
+            format!("Timed out after {}s fetching image", FETCH_TIMEOUT.as_secs())
+        } else if e.is_redirect() {
+            "Too many redirects".to_string()
+        } else {
+            format!("Network error: {}", e)
+        }
+    })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Server returned {}", status));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    check_content_type(content_type.as_deref())?;
+    if response.content_length().is_some_and(|len| len as usize > MAX_IMAGE_BYTES) {
+        return Err(format!("Image is larger than {} MB", MAX_IMAGE_BYTES / 1024 / 1024));
+    }
+
+    let bytes = response.bytes().await.map_err(|e| format!("Download failed: {}", e))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(format!("Image is larger than {} MB", MAX_IMAGE_BYTES / 1024 / 1024));
+    }
+    image::load_from_memory(&bytes).map_err(|e| format!("Couldn't decode image: {}", e))
+}
+
+/// Open an image file. Animated GIFs load as their first frame, and the
+/// second value is then a note saying so for the log.
+pub fn open_image(path: &Path) -> Result<(DynamicImage, Option<String>), String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let img = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+    let note = is_animated_gif(&bytes).then(|| {
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        format!("{} is animated; using its first frame", name)
+    });
+    Ok((img, note))
+}
+
+/// Whether `bytes` is a GIF with more than one frame.
+fn is_animated_gif(bytes: &[u8]) -> bool {
+    if image::guess_format(bytes).ok() != Some(ImageFormat::Gif) {
+        return false;
+    }
+    GifDecoder::new(Cursor::new(bytes)).is_ok_and(|decoder| decoder.into_frames().take(2).count() > 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_and_content_type_checks() {
+        assert!(parse_image_url(" https://example.com/logo.png ").is_ok());
+        assert!(parse_image_url("ftp://example.com/logo.png").unwrap_err().contains("ftp"));
+        assert!(parse_image_url("not a url").is_err());
+
+        assert!(check_content_type(Some("image/png")).is_ok());
+        assert!(check_content_type(None).is_ok());
+        let err = check_content_type(Some("text/html; charset=utf-8")).unwrap_err();
+        assert_eq!(err, "URL points to text/html, not an image");
+    }
+
+    #[test]
+    fn test_animated_gif_detection() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Frame, RgbaImage};
+
+        let gif = |frames: usize| {
+            let mut buf = Vec::new();
+            let mut encoder = GifEncoder::new(&mut buf);
+            for _ in 0..frames {
+                encoder.encode_frame(Frame::new(RgbaImage::new(4, 4))).unwrap();
+            }
+            drop(encoder);
+            buf
+        };
+        assert!(is_animated_gif(&gif(3)));
+        assert!(!is_animated_gif(&gif(1)));
+        assert!(!is_animated_gif(b"not an image"));
+        assert_eq!(image::load_from_memory(&gif(3)).unwrap().width(), 4);
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::Path;
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+
+use crate::escpos::{image_to_escpos_bytes, raster_to_image};
+
+/// File extension for exported jobs.
+pub const EXTENSION: &str = "ctpjob";
+
+/// First bytes of every job file.
+const MAGIC: &[u8; 6] = b"CTPJOB";
+/// Layout version written after the magic; bump it when the layout changes.
+pub const VERSION: u16 = 1;
+
+/// A print job rendered to the exact raster bytes sent to the printer, so it
+/// prints identically on another machine whatever fonts or settings it has.
+///
+/// On disk: MAGIC, VERSION (u16 LE), the length of a JSON header (u32 LE),
+/// the header, then every page's `GS v 0` raster command back to back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobFile {
+    /// Short description for the log, e.g. the first line of the text.
+    pub name: String,
+    /// When it was exported (RFC 3339).
+    pub created: String,
+    /// One `GS v 0` raster command per page, printed in order.
+    pub pages: Vec<Vec<u8>>,
+}
+
+/// The JSON part of the file; page data follows it.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    name: String,
+    created: String,
+    /// Byte length of each page's raster data.
+    pages: Vec<usize>,
+}
+
+impl JobFile {
+    /// Pack rendered 1-bit pages (as from the 1:1 preview) into a job.
+    pub fn from_bitmaps(name: &str, bitmaps: &[GrayImage]) -> Self {
+        Self {
+            name: name.to_string(),
+            created: chrono::Local::now().to_rfc3339(),
+            pages: bitmaps.iter().map(|page| image_to_escpos_bytes(&DynamicImage::ImageLuma8(page.clone()))).collect(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = Header { name: self.name.clone(), created: self.created.clone(), pages: self.pages.iter().map(Vec::len).collect() };
+        let header = serde_json::to_vec(&header).expect("job header serializes");
+        let mut out = Vec::with_capacity(12 + header.len() + self.pages.iter().map(Vec::len).sum::<usize>());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        out.extend_from_slice(&header);
+        for page in &self.pages {
+            out.extend_from_slice(page);
+        }
+        out
+    }
+
+    /// Parse a job file, checking that every page is a complete raster command.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let rest = bytes.strip_prefix(MAGIC.as_slice()).ok_or("Not a .ctpjob file")?;
+        let (version, rest) = split_u16(rest).ok_or("File is truncated")?;
+        if version > VERSION {
+            return Err(format!("Job file format {} is newer than this app supports ({})", version, VERSION));
+        }
+        let (header_len, rest) = split_u32(rest).ok_or("File is truncated")?;
+        let (header, mut data) = rest.split_at_checked(header_len as usize).ok_or("File is truncated")?;
+        let header: Header = serde_json::from_slice(header).map_err(|e| format!("Invalid job header: {}", e))?;
+
+        let mut pages = Vec::with_capacity(header.pages.len());
+        for (i, len) in header.pages.into_iter().enumerate() {
+            let (page, rest) = data.split_at_checked(len).ok_or("File is truncated")?;
+            if raster_to_image(page).is_none() {
+                return Err(format!("Page {} isn't valid raster data", i + 1));
+            }
+            pages.push(page.to_vec());
+            data = rest;
+        }
+        if pages.is_empty() {
+            return Err("Job has no pages".to_string());
+        }
+        Ok(Self { name: header.name, created: header.created, pages })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("Failed to save job: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn split_u16(bytes: &[u8]) -> Option<(u16, &[u8])> {
+    let (head, rest) = bytes.split_first_chunk::<2>()?;
+    Some((u16::from_le_bytes(*head), rest))
+}
+
+fn split_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let (head, rest) = bytes.split_first_chunk::<4>()?;
+    Some((u32::from_le_bytes(*head), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn sample() -> JobFile {
+        let page = |h| GrayImage::from_fn(384, h, |x, y| Luma([if (x + y) % 3 == 0 { 0 } else { 255 }]));
+        JobFile::from_bitmaps("Shelf label", &[page(20), page(7)])
+    }
+
+    #[test]
+    fn test_job_file_round_trip() {
+        let job = sample();
+        let bytes = job.to_bytes();
+        assert!(bytes.starts_with(b"CTPJOB\x01\x00"));
+        assert_eq!(JobFile::from_bytes(&bytes).unwrap(), job);
+
+        // The pages are exactly what the printer would be sent
+        let bitmap = raster_to_image(&job.pages[1]).unwrap();
+        assert_eq!(bitmap.dimensions(), (384, 7));
+        assert_eq!(bitmap.get_pixel(3, 0)[0], 0);
+        assert_eq!(bitmap.get_pixel(1, 0)[0], 255);
+    }
+
+    #[test]
+    fn test_job_file_rejects_bad_input() {
+        let bytes = sample().to_bytes();
+        assert_eq!(JobFile::from_bytes(b"PNG...").unwrap_err(), "Not a .ctpjob file");
+        assert_eq!(JobFile::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(), "File is truncated");
+
+        let mut newer = bytes.clone();
+        newer[6] = 2;
+        assert!(JobFile::from_bytes(&newer).unwrap_err().contains("newer"));
+
+        let empty = JobFile { name: String::new(), created: String::new(), pages: Vec::new() };
+        assert_eq!(JobFile::from_bytes(&empty.to_bytes()).unwrap_err(), "Job has no pages");
+        let garbage = JobFile { pages: vec![vec![1, 2, 3]], ..empty };
+        assert_eq!(JobFile::from_bytes(&garbage.to_bytes()).unwrap_err(), "Page 1 isn't valid raster data");
+    }
+}
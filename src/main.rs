@@ -1,11 +1,20 @@
 mod app;
+mod barcode;
 mod ble;
+mod cli;
+mod config;
+mod error;
 mod escpos;
+mod fetch;
+mod jobfile;
 mod printer;
+mod qr;
+mod test_page;
 mod text_render;
 mod types;
 
 use std::sync::Arc;
+use clap::Parser;
 use dioxus::prelude::*;
 use dioxus_desktop::{Config, WindowBuilder};
 use tokio::sync::Mutex;
@@ -15,9 +24,21 @@ use app::{App, AppState};
 fn main() {
     env_logger::init();
 
+    // Subcommands print headlessly and exit without opening the window.
+    if let Some(command) = cli::Cli::parse().command {
+        std::process::exit(cli::run(command));
+    }
+
     let (cmd_tx, cmd_rx) = tokio::sync::mpsc::channel::<types::BleCommand>(32);
     let (evt_tx, evt_rx) = tokio::sync::mpsc::channel::<types::AppEvent>(256);
 
+    // Restore last-used settings and hand the BLE-side ones to the BLE task.
+    let settings = config::load();
+    cmd_tx.try_send(types::BleCommand::ListAdapters).ok();
+    for cmd in settings.ble_commands() {
+        cmd_tx.try_send(cmd).ok();
+    }
+
     // Spawn a dedicated OS thread owning the Tokio runtime for BLE operations.
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
@@ -25,7 +46,7 @@ fn main() {
     });
 
     // Wrap channels in Arc<Mutex> so they can be shared into the Dioxus context.
-    let state = Arc::new(Mutex::new(AppState { cmd_tx, evt_rx }));
+    let state = Arc::new(Mutex::new(AppState { cmd_tx, evt_rx: Some(evt_rx) }));
 
     let window = WindowBuilder::new()
         .with_title("CTP500 Printer Control")
@@ -35,5 +56,6 @@ fn main() {
     LaunchBuilder::desktop()
         .with_cfg(Config::new().with_window(window))
         .with_context(state)
+        .with_context(settings)
         .launch(App);
 }
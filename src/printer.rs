@@ -1,81 +1,719 @@
-use std::time::Duration;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
 use image::DynamicImage;
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 use btleplug::api::{Peripheral as _, WriteType};
 use btleplug::platform::Peripheral;
 use btleplug::api::Characteristic;
 
-use crate::escpos::image_to_escpos_bytes;
-use crate::types::{AppEvent, CHUNK_SIZE};
+use crate::error::PrinterError;
+use crate::escpos::{image_to_escpos_bytes, image_to_escpos_bytes_with, raster_size, raster_to_image, RasterOptions};
+use crate::types::{AppEvent, LogLevel, PrintSummary, CHUNK_SIZE};
 
-/// Full print sequence: initialize → start → image data → end.
+/// Resets the printer (ESC @).
+const INIT_SEQUENCE: [u8; 2] = [0x1b, 0x40];
+/// Starts a print job.
+const START_SEQUENCE: [u8; 4] = [0x1d, 0x49, 0xf0, 0x19];
+
+/// Final byte of every print job, sent after the feed line feeds.
+const END_MARKER: u8 = 0x9a;
+
+/// Line feeds sent before the end marker unless the user picks otherwise.
+pub const DEFAULT_FEED_LINES: u8 = 3;
+
+/// Smallest width/height (dots) accepted for printing; anything smaller is
+/// almost certainly a bad decode and makes a malformed raster header.
+const MIN_IMAGE_DIM: u32 = 2;
+
+/// Minimum feed after each image of a batch, so the prints tear apart.
+pub const BATCH_FEED_LINES: u8 = 4;
+
+/// Terminates a print job: `feed_lines` line feeds, then the end marker.
+fn end_sequence(feed_lines: u8) -> Vec<u8> {
+    let mut seq = vec![0x0a; feed_lines as usize];
+    seq.push(END_MARKER);
+    seq
+}
+
+/// Attempts per chunk before a write error aborts the print.
+const WRITE_ATTEMPTS: u32 = 3;
+/// Pause between attempts at the same chunk.
+const WRITE_RETRY_DELAY: Duration = Duration::from_millis(50);
+/// With write-without-response, every this-many chunks is still written with
+/// response so the printer can drain its buffer.
+const FLUSH_EVERY_CHUNKS: usize = 16;
+
+/// Pauses between the steps of the print sequence. The defaults match the
+/// original Python app; slower firmware may need longer ones.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrintTiming {
+    /// Wait after the initialize sequence (ESC @).
+    pub init_delay_ms: u64,
+    /// Wait after the start sequence.
+    pub start_delay_ms: u64,
+    /// Assumed print speed; the wait after the image data is its size
+    /// divided by this.
+    pub bytes_per_sec: u64,
+    /// Shortest wait after the image data, however small the image.
+    pub min_data_delay_ms: u64,
+    /// Wait after the end sequence before reporting the print complete.
+    pub end_delay_ms: u64,
+    /// Pause between BLE chunk writes, for printers whose buffer overruns
+    /// on long prints (0 = none; 2–5 ms usually suffices).
+    pub chunk_delay_ms: u64,
+}
+
+impl Default for PrintTiming {
+    fn default() -> Self {
+        Self {
+            init_delay_ms: 500,
+            start_delay_ms: 500,
+            bytes_per_sec: 5000,
+            min_data_delay_ms: 500,
+            end_delay_ms: 1000,
+            chunk_delay_ms: 0,
+        }
+    }
+}
+
+impl PrintTiming {
+    /// Time to let the printer work through `len` bytes of image data.
+    fn data_delay(&self, len: usize) -> Duration {
+        let ms = (len as f64 / self.bytes_per_sec.max(1) as f64) * 1000.0;
+        Duration::from_millis((ms as u64).max(self.min_data_delay_ms))
+    }
+
+    /// Pause between chunk writes, if any.
+    fn chunk_delay(&self) -> Option<Duration> {
+        (self.chunk_delay_ms > 0).then(|| Duration::from_millis(self.chunk_delay_ms))
+    }
+}
+
+/// Handles for writing to a connected printer.
+#[derive(Clone)]
+pub struct PrinterLink {
+    pub peripheral: Peripheral,
+    pub write_char: Characteristic,
+    /// Payload bytes per BLE write for this connection.
+    pub chunk_size: usize,
+    /// Send image data with write-without-response (faster, but can overrun
+    /// the printer's buffer on some firmware).
+    pub without_response: bool,
+    pub timing: PrintTiming,
+}
+
+/// A unit of work in the BLE task's print queue.
+#[derive(Clone)]
+pub enum PrintJob {
+    /// A decoded image, converted to ESC/POS raster bytes when the job is queued.
+    Image { img: Arc<DynamicImage>, options: RasterOptions },
+    /// One image of a multi-image batch; fed at least BATCH_FEED_LINES afterwards.
+    BatchImage { img: DynamicImage, options: RasterOptions },
+    /// Already-rendered ESC/POS raster bytes; `width`/`height` are the source
+    /// image dimensions, used for logging.
+    Raster { data: Vec<u8>, width: u32, height: u32 },
+    /// The first job of copy `copy` of `copies`; reports which copy is
+    /// starting, then prints `job`.
+    Copy { job: Box<PrintJob>, copy: u8, copies: u8 },
+}
+
+impl PrintJob {
+    /// Render an image to raster bytes up front (used for text jobs).
+    pub fn rendered(img: &DynamicImage) -> Self {
+        PrintJob::Raster { data: image_to_escpos_bytes(img), width: img.width(), height: img.height() }
+    }
+
+    /// Source image size in pixels.
+    fn dimensions(&self) -> (u32, u32) {
+        match self {
+            PrintJob::Image { img, .. } => (img.width(), img.height()),
+            PrintJob::BatchImage { img, .. } => (img.width(), img.height()),
+            PrintJob::Raster { width, height, .. } => (*width, *height),
+            PrintJob::Copy { job, .. } => job.dimensions(),
+        }
+    }
+
+    /// Reject images too small to print before anything is sent.
+    pub fn validate(&self) -> Result<(), String> {
+        let (w, h) = self.dimensions();
+        if w < MIN_IMAGE_DIM || h < MIN_IMAGE_DIM {
+            return Err(format!("Image is too small to print ({}x{}px, minimum {}x{})", w, h, MIN_IMAGE_DIM, MIN_IMAGE_DIM));
+        }
+        Ok(())
+    }
+
+    /// Line feeds this job needs after it at minimum.
+    pub fn min_feed_lines(&self) -> u8 {
+        match self {
+            PrintJob::BatchImage { .. } => BATCH_FEED_LINES,
+            PrintJob::Image { .. } | PrintJob::Raster { .. } => 0,
+            PrintJob::Copy { job, .. } => job.min_feed_lines(),
+        }
+    }
+
+    /// Render to raster bytes now, so the job can be kept and resent byte for
+    /// byte. A copy marker stays around the rendered job.
+    pub fn into_rendered(self) -> PrintJob {
+        match self {
+            PrintJob::Copy { job, copy, copies } => PrintJob::Copy { job: Box::new(job.into_rendered()), copy, copies },
+            job => {
+                let (data, width, height) = job.into_raster();
+                PrintJob::Raster { data, width, height }
+            }
+        }
+    }
+
+    /// Convert to ESC/POS raster bytes plus the source image size.
+    pub fn into_raster(self) -> (Vec<u8>, u32, u32) {
+        match self {
+            PrintJob::Image { img, options } => (image_to_escpos_bytes_with(&img, &options), img.width(), img.height()),
+            PrintJob::BatchImage { img, options } => {
+                (image_to_escpos_bytes_with(&img, &options), img.width(), img.height())
+            }
+            PrintJob::Raster { data, width, height } => (data, width, height),
+            PrintJob::Copy { job, .. } => job.into_raster(),
+        }
+    }
+
+    /// Hash of everything that decides what this job prints: the pixels or
+    /// raster bytes and the conversion options.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash_into(&mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_into(&self, hasher: &mut DefaultHasher) {
+        match self {
+            PrintJob::Image { img, options } => hash_image(0, img, options, hasher),
+            PrintJob::BatchImage { img, options } => hash_image(1, img, options, hasher),
+            PrintJob::Raster { data, width, height } => (2u8, data, width, height).hash(hasher),
+            PrintJob::Copy { job, copy, copies } => {
+                (3u8, copy, copies).hash(hasher);
+                job.hash_into(hasher);
+            }
+        }
+    }
+}
+
+fn hash_image(kind: u8, img: &DynamicImage, options: &RasterOptions, hasher: &mut DefaultHasher) {
+    (kind, img.width(), img.height(), img.color() as u8, img.as_bytes()).hash(hasher);
+    // RasterOptions holds floats, so hash its printed form
+    format!("{:?}", options).hash(hasher);
+}
+
+/// Remembers the last print submitted so an identical one right after it —
+/// usually a double-clicked Print button — can be skipped. Only fingerprints
+/// of the jobs are kept, not the images themselves.
+#[derive(Default)]
+pub struct DuplicateGuard {
+    last: Option<(Vec<u64>, Vec<String>, Instant)>,
+}
+
+impl DuplicateGuard {
+    /// A repeat of the previous print within this long counts as a duplicate.
+    pub const WINDOW: Duration = Duration::from_secs(3);
+
+    /// Record a print of `jobs` to `targets` at `now`. If it repeats the
+    /// previous one within WINDOW, returns how long ago that was instead and
+    /// keeps the earlier record, so a burst of clicks prints only once.
+    pub fn check(&mut self, jobs: &[PrintJob], targets: &[String], now: Instant) -> Option<Duration> {
+        let fingerprints: Vec<u64> = jobs.iter().map(PrintJob::fingerprint).collect();
+        if let Some((last_jobs, last_targets, at)) = &self.last {
+            let age = now.saturating_duration_since(*at);
+            if age < Self::WINDOW && *last_jobs == fingerprints && last_targets.as_slice() == targets {
+                return Some(age);
+            }
+        }
+        self.last = Some((fingerprints, targets.to_vec(), now));
+        None
+    }
+}
+
+/// Full print sequence for a queued job: initialize → start → image data → end.
 /// Port of Python's `PrinterConnect.print_image()`.
-pub async fn print_image(
-    peripheral: &Peripheral,
-    write_char: &Characteristic,
-    img: DynamicImage,
+///
+/// Setting `cancel` aborts the image data transfer between chunks; the end
+/// sequence is still sent so the printer isn't left mid-raster.
+///
+/// `feed_lines` extra line feeds are sent before the end marker so the paper
+/// can be torn off cleanly.
+pub async fn print_job(
+    link: &PrinterLink,
+    job: PrintJob,
+    feed_lines: u8,
     evt_tx: &Sender<AppEvent>,
+    cancel: &AtomicBool,
 ) {
-    let buf = image_to_escpos_bytes(&img);
-    let img_w = img.width();
-    let img_h = img.height();
+    let feed_lines = feed_lines.max(job.min_feed_lines());
+    let (data, width, height) = job.into_raster();
+    print_raster(link, &data, width, height, feed_lines, evt_tx, cancel).await;
+}
+
+/// Send pre-rendered ESC/POS raster bytes wrapped in the init/start/end sequence.
+async fn print_raster(
+    link: &PrinterLink,
+    buf: &[u8],
+    img_w: u32,
+    img_h: u32,
+    feed_lines: u8,
+    evt_tx: &Sender<AppEvent>,
+    cancel: &AtomicBool,
+) {
+    let end = end_sequence(feed_lines);
+    let started = Instant::now();
 
     // Initialize printer (ESC @)
-    evt_tx.send(AppEvent::Log("Sent: initialize printer (ESC @)".into())).await.ok();
-    if let Err(e) = write_chunked(peripheral, write_char, &[0x1b, 0x40], evt_tx).await {
+    evt_tx.send(AppEvent::Log(LogLevel::Debug, "Sent: initialize printer (ESC @)".into())).await.ok();
+    if let Err(e) = write_chunked(link, &INIT_SEQUENCE, evt_tx, None).await {
         evt_tx.send(AppEvent::Error(format!("Print error: {}", e))).await.ok();
         return;
     }
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    tokio::time::sleep(Duration::from_millis(link.timing.init_delay_ms)).await;
 
     // Start print sequence
-    evt_tx.send(AppEvent::Log("Sent: start print sequence".into())).await.ok();
-    if let Err(e) = write_chunked(peripheral, write_char, &[0x1d, 0x49, 0xf0, 0x19], evt_tx).await {
+    evt_tx.send(AppEvent::Log(LogLevel::Debug, "Sent: start print sequence".into())).await.ok();
+    if let Err(e) = write_chunked(link, &START_SEQUENCE, evt_tx, None).await {
         evt_tx.send(AppEvent::Error(format!("Print error: {}", e))).await.ok();
         return;
     }
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    tokio::time::sleep(Duration::from_millis(link.timing.start_delay_ms)).await;
 
     // Image data
     let log_msg = format!("Sent: image data ({} bytes, {}x{}px)", buf.len(), img_w, img_h);
-    evt_tx.send(AppEvent::Log(log_msg)).await.ok();
-    if let Err(e) = write_chunked(peripheral, write_char, &buf, evt_tx).await {
+    evt_tx.send(AppEvent::Log(LogLevel::Info, log_msg)).await.ok();
+    if let Err(e) = write_chunked(link, buf, evt_tx, Some(cancel)).await {
         evt_tx.send(AppEvent::Error(format!("Print error: {}", e))).await.ok();
         return;
     }
-    let delay_ms = ((buf.len() as f64 / 5000.0) * 1000.0).max(500.0) as u64;
-    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+    if cancel.swap(false, Ordering::SeqCst) {
+        evt_tx.send(AppEvent::Log(LogLevel::Info, "Print cancelled".into())).await.ok();
+        write_chunked(link, &end, evt_tx, None).await.ok();
+        evt_tx.send(AppEvent::PrintCancelled).await.ok();
+        return;
+    }
+    tokio::time::sleep(link.timing.data_delay(buf.len())).await;
 
     // End print sequence
-    evt_tx.send(AppEvent::Log("Sent: end print sequence".into())).await.ok();
-    if let Err(e) = write_chunked(peripheral, write_char, &[0x0a, 0x0a, 0x0a, 0x9a], evt_tx).await {
+    evt_tx.send(AppEvent::Log(LogLevel::Debug, "Sent: end print sequence".into())).await.ok();
+    if let Err(e) = write_chunked(link, &end, evt_tx, None).await {
         evt_tx.send(AppEvent::Error(format!("Print error: {}", e))).await.ok();
         return;
     }
-    tokio::time::sleep(Duration::from_millis(1000)).await;
+    let summary = print_summary(buf, started.elapsed());
+    tokio::time::sleep(Duration::from_millis(link.timing.end_delay_ms)).await;
+
+    evt_tx.send(AppEvent::Log(LogLevel::Info, format!("Print complete: {}", summary))).await.ok();
+    evt_tx.send(AppEvent::PrintComplete(summary)).await.ok();
+}
+
+/// Totals for a finished job from its raster bytes and transfer time.
+fn print_summary(raster: &[u8], elapsed: Duration) -> PrintSummary {
+    let (width, height) = raster_size(raster).unwrap_or_default();
+    PrintSummary { bytes: raster.len(), width, height, elapsed_ms: elapsed.as_millis() as u64 }
+}
+
+/// Bytes that advance the paper by `amount` line feeds, or with `dots` by
+/// `amount` dot-lines (ESC J n, 1/8 mm each) for precise tearing.
+fn feed_sequence(amount: u8, dots: bool) -> Vec<u8> {
+    if dots {
+        vec![0x1b, 0x4a, amount]
+    } else {
+        vec![0x0a; amount as usize]
+    }
+}
+
+/// Advance the paper without printing anything.
+pub async fn feed_paper(link: &PrinterLink, amount: u8, dots: bool, evt_tx: &Sender<AppEvent>) -> Result<(), PrinterError> {
+    write_chunked(link, &feed_sequence(amount, dots), evt_tx, None).await
+}
 
-    evt_tx.send(AppEvent::Log("Print complete".into())).await.ok();
-    evt_tx.send(AppEvent::PrintComplete).await.ok();
+/// Send just the initialize sequence (ESC @) to clear a confused printer.
+pub async fn reset_printer(link: &PrinterLink, evt_tx: &Sender<AppEvent>) -> Result<(), PrinterError> {
+    write_chunked(link, &INIT_SEQUENCE, evt_tx, None).await
 }
 
-/// Write data in CHUNK_SIZE-sized chunks using write-with-response.
+/// Send arbitrary bytes to the printer, for experimenting with commands the
+/// app doesn't expose.
+pub async fn send_raw(link: &PrinterLink, bytes: &[u8], evt_tx: &Sender<AppEvent>) -> Result<(), PrinterError> {
+    write_chunked(link, bytes, evt_tx, None).await
+}
+
+/// Longest raw command accepted from the hex field.
+pub const MAX_RAW_BYTES: usize = 1024;
+
+/// Parse hex bytes typed by the user, e.g. `1b 40`, `1b40` or `0x1B, 0x40`.
+pub fn parse_hex_bytes(input: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for token in input.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty()) {
+        let digits = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+        if digits.is_empty() || digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("\"{}\" is not a whole number of hex bytes", token));
+        }
+        for i in (0..digits.len()).step_by(2) {
+            bytes.push(u8::from_str_radix(&digits[i..i + 2], 16).unwrap());
+        }
+    }
+    if bytes.is_empty() {
+        return Err("Enter some hex bytes, e.g. 1b 40".to_string());
+    }
+    if bytes.len() > MAX_RAW_BYTES {
+        return Err(format!("At most {} bytes can be sent at once", MAX_RAW_BYTES));
+    }
+    Ok(bytes)
+}
+
+/// Directory simulated prints are written to: `~/ctp500-sim`, or the system
+/// temp directory if $HOME is unset.
+pub fn simulation_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join("ctp500-sim"))
+        .unwrap_or_else(|| std::env::temp_dir().join("ctp500-sim"))
+}
+
+/// Dry-run a job without a printer: write the exact byte stream that would be
+/// sent (init, start, raster, end) to a `.bin` file and the raster to a `.png`
+/// in `simulation_dir()`, emitting the same progress/completion events.
+pub async fn simulate_job(job: PrintJob, feed_lines: u8, evt_tx: &Sender<AppEvent>, cancel: &AtomicBool) {
+    let feed_lines = feed_lines.max(job.min_feed_lines());
+    let (raster, img_w, img_h) = job.into_raster();
+    if let Err(e) = write_simulation(&raster, img_w, img_h, feed_lines, evt_tx, cancel).await {
+        evt_tx.send(AppEvent::Error(format!("Simulation error: {}", e))).await.ok();
+    }
+}
+
+async fn write_simulation(
+    raster: &[u8],
+    img_w: u32,
+    img_h: u32,
+    feed_lines: u8,
+    evt_tx: &Sender<AppEvent>,
+    cancel: &AtomicBool,
+) -> std::io::Result<()> {
+    let dir = simulation_dir();
+    std::fs::create_dir_all(&dir)?;
+    let stem = format!("print-{}", chrono::Local::now().format("%Y%m%d-%H%M%S-%3f"));
+    let bin_path = dir.join(format!("{}.bin", stem));
+    let mut file = std::fs::File::create(&bin_path)?;
+    let started = Instant::now();
+
+    let log_msg = format!("Simulating: image data ({} bytes, {}x{}px)", raster.len(), img_w, img_h);
+    evt_tx.send(AppEvent::Log(LogLevel::Info, log_msg)).await.ok();
+    file.write_all(&INIT_SEQUENCE)?;
+    file.write_all(&START_SEQUENCE)?;
+
+    let mut progress = Progress::new(raster.len(), CHUNK_SIZE);
+    for chunk in raster.chunks(CHUNK_SIZE) {
+        if cancel.swap(false, Ordering::SeqCst) {
+            file.write_all(&end_sequence(feed_lines))?;
+            evt_tx.send(AppEvent::Log(LogLevel::Info, "Print cancelled".into())).await.ok();
+            evt_tx.send(AppEvent::PrintCancelled).await.ok();
+            return Ok(());
+        }
+        file.write_all(chunk)?;
+        if let Some(event) = progress.advance(chunk.len()) {
+            evt_tx.send(event).await.ok();
+            tokio::task::yield_now().await;
+        }
+    }
+    file.write_all(&end_sequence(feed_lines))?;
+
+    if let Some(bitmap) = raster_to_image(raster) {
+        let png_path = dir.join(format!("{}.png", stem));
+        bitmap.save(&png_path).map_err(std::io::Error::other)?;
+    }
+    let summary = print_summary(raster, started.elapsed());
+    let log_msg = format!("Simulated print written to {} ({})", bin_path.display(), summary);
+    evt_tx.send(AppEvent::Log(LogLevel::Info, log_msg)).await.ok();
+    evt_tx.send(AppEvent::PrintComplete(summary)).await.ok();
+    Ok(())
+}
+
+/// Byte-accurate transfer progress, reported on whole-percent steps.
+///
+/// Payloads that fit in a single chunk (the control sequences) never report.
+struct Progress {
+    sent: usize,
+    total: usize,
+    last_percent: usize,
+    enabled: bool,
+}
+
+impl Progress {
+    fn new(total: usize, chunk_size: usize) -> Self {
+        Self { sent: 0, total, last_percent: 0, enabled: total > chunk_size }
+    }
+
+    /// Count `len` more bytes as sent. Returns a `PrintProgress` event when
+    /// the percentage has moved on (or the transfer just finished).
+    fn advance(&mut self, len: usize) -> Option<AppEvent> {
+        self.sent = (self.sent + len).min(self.total);
+        if !self.enabled {
+            return None;
+        }
+        let percent = self.sent * 100 / self.total;
+        if percent == self.last_percent && self.sent < self.total {
+            return None;
+        }
+        self.last_percent = percent;
+        Some(AppEvent::PrintProgress { sent: self.sent, total: self.total })
+    }
+}
+
+/// Number of recent write round-trips averaged into the link-quality figure.
+const LATENCY_WINDOW: usize = 20;
+
+/// Rolling average of write-with-response round-trip times, a proxy for BLE
+/// link quality.
+#[derive(Default)]
+struct LatencyWindow {
+    samples: VecDeque<Duration>,
+}
+
+impl LatencyWindow {
+    fn record(&mut self, rtt: Duration) {
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(rtt);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Average of the recorded samples in whole milliseconds.
+    fn average_ms(&self) -> u32 {
+        if self.samples.is_empty() {
+            return 0;
+        }
+        let sum: Duration = self.samples.iter().sum();
+        (sum / self.samples.len() as u32).as_millis() as u32
+    }
+}
+
+/// Write data in `link.chunk_size`-sized chunks using write-with-response.
 /// Port of Python's `PrinterConnect._write_bytes()`.
+///
+/// If `cancel` is given and becomes set, returns early (Ok) before the next chunk.
+/// Each chunk is retried up to WRITE_ATTEMPTS times on transient BLE errors.
 async fn write_chunked(
-    peripheral: &Peripheral,
-    write_char: &Characteristic,
+    link: &PrinterLink,
     data: &[u8],
     evt_tx: &Sender<AppEvent>,
-) -> Result<(), btleplug::Error> {
-    let total = data.len();
-    let total_chunks = data.chunks(CHUNK_SIZE).count();
+    cancel: Option<&AtomicBool>,
+) -> Result<(), PrinterError> {
+    let mut progress = Progress::new(data.len(), link.chunk_size);
+    let mut latency = LatencyWindow::default();
 
-    for (i, chunk) in data.chunks(CHUNK_SIZE).enumerate() {
-        peripheral.write(write_char, chunk, WriteType::WithResponse).await?;
+    for (i, chunk) in data.chunks(link.chunk_size).enumerate() {
+        if cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+            break;
+        }
+        if let Some(pause) = link.timing.chunk_delay().filter(|_| i > 0) {
+            tokio::time::sleep(pause).await;
+        }
+        let started = Instant::now();
+        write_with_retry(link, chunk, i, evt_tx).await?;
+        latency.record(started.elapsed());
 
-        if total_chunks > 10 && i % 10 == 0 {
-            let sent = ((i + 1) * CHUNK_SIZE).min(total);
-            evt_tx.send(AppEvent::PrintProgress { sent, total }).await.ok();
+        if let Some(event) = progress.advance(chunk.len()) {
+            evt_tx.send(event).await.ok();
+            evt_tx.send(AppEvent::LinkQuality(latency.average_ms())).await.ok();
         }
     }
+    // Single-chunk writes don't report progress, so report their latency here.
+    if !latency.is_empty() {
+        evt_tx.send(AppEvent::LinkQuality(latency.average_ms())).await.ok();
+    }
     Ok(())
 }
+
+/// Write type for chunk `index`: always with-response unless fast writes are
+/// on, in which case every FLUSH_EVERY_CHUNKS-th chunk is a with-response flush.
+fn write_type_for(index: usize, without_response: bool) -> WriteType {
+    if without_response && !(index + 1).is_multiple_of(FLUSH_EVERY_CHUNKS) {
+        WriteType::WithoutResponse
+    } else {
+        WriteType::WithResponse
+    }
+}
+
+/// Write one chunk, retrying after a short pause if the write fails.
+async fn write_with_retry(
+    link: &PrinterLink,
+    chunk: &[u8],
+    index: usize,
+    evt_tx: &Sender<AppEvent>,
+) -> Result<(), PrinterError> {
+    let mut attempt = 1;
+    loop {
+        let write_type = write_type_for(index, link.without_response);
+        match link.peripheral.write(&link.write_char, chunk, write_type).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < WRITE_ATTEMPTS => {
+                let msg = format!("Chunk {} write failed ({}), retrying ({}/{})", index, e, attempt, WRITE_ATTEMPTS - 1);
+                evt_tx.send(AppEvent::Log(LogLevel::Warn, msg)).await.ok();
+                tokio::time::sleep(WRITE_RETRY_DELAY).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(PrinterError::WriteFailed(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `total` bytes through a Progress in `chunk`-sized steps, collecting `sent` values.
+    fn progress_reports(total: usize, chunk: usize) -> Vec<usize> {
+        let mut progress = Progress::new(total, chunk);
+        let mut reports = Vec::new();
+        let mut remaining = total;
+        while remaining > 0 {
+            let len = remaining.min(chunk);
+            remaining -= len;
+            if let Some(AppEvent::PrintProgress { sent, .. }) = progress.advance(len) {
+                reports.push(sent);
+            }
+        }
+        reports
+    }
+
+    #[test]
+    fn test_progress_counts_real_bytes() {
+        // Partial final chunk: the last report is exactly the total, never more
+        assert_eq!(progress_reports(250, 100), vec![100, 200, 250]);
+        // Large payloads report at most once per percent, ending at the total
+        let reports = progress_reports(100_000, 100);
+        assert_eq!(reports.len(), 100);
+        assert_eq!(reports.last(), Some(&100_000));
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+        // Single-chunk control sequences stay quiet
+        assert!(progress_reports(4, 100).is_empty());
+    }
+
+    #[test]
+    fn test_latency_window_rolls() {
+        let mut window = LatencyWindow::default();
+        assert_eq!(window.average_ms(), 0);
+        window.record(Duration::from_millis(10));
+        window.record(Duration::from_millis(30));
+        assert_eq!(window.average_ms(), 20);
+        // Old samples fall out once the window is full
+        for _ in 0..LATENCY_WINDOW {
+            window.record(Duration::from_millis(100));
+        }
+        assert_eq!(window.average_ms(), 100);
+    }
+
+    #[test]
+    fn test_write_type_flushes_periodically() {
+        assert!((0..100).all(|i| write_type_for(i, false) == WriteType::WithResponse));
+        let flushes: Vec<usize> = (0..40)
+            .filter(|&i| write_type_for(i, true) == WriteType::WithResponse)
+            .collect();
+        assert_eq!(flushes, vec![FLUSH_EVERY_CHUNKS - 1, 2 * FLUSH_EVERY_CHUNKS - 1]);
+    }
+
+    #[test]
+    fn test_end_sequence_feed_lines() {
+        assert_eq!(end_sequence(0), vec![0x9a]);
+        assert_eq!(end_sequence(DEFAULT_FEED_LINES), vec![0x0a, 0x0a, 0x0a, 0x9a]);
+        assert_eq!(feed_sequence(2, false), vec![0x0a, 0x0a]);
+        assert_eq!(feed_sequence(1, true), vec![0x1b, 0x4a, 0x01]);
+    }
+
+    #[test]
+    fn test_data_delay_scales_with_size() {
+        let timing = PrintTiming::default();
+        assert_eq!(timing.data_delay(100), Duration::from_millis(500));
+        assert_eq!(timing.data_delay(20_000), Duration::from_millis(4000));
+        let fast = PrintTiming { bytes_per_sec: 10_000, min_data_delay_ms: 0, ..timing };
+        assert_eq!(fast.data_delay(20_000), Duration::from_millis(2000));
+        // A zero rate is treated as 1 byte/s rather than dividing by zero
+        let stalled = PrintTiming { bytes_per_sec: 0, ..timing };
+        assert_eq!(stalled.data_delay(2), Duration::from_millis(2000));
+
+        assert_eq!(timing.chunk_delay(), None);
+        let paced = PrintTiming { chunk_delay_ms: 3, ..timing };
+        assert_eq!(paced.chunk_delay(), Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn test_duplicate_guard_window() {
+        let raster = |byte| vec![PrintJob::Raster { data: vec![byte; 48], width: 384, height: 1 }];
+        let printer = vec!["printer-a".to_string()];
+        let start = Instant::now();
+        let mut guard = DuplicateGuard::default();
+
+        assert_eq!(guard.check(&raster(1), &printer, start), None);
+        let soon = start + Duration::from_millis(400);
+        assert_eq!(guard.check(&raster(1), &printer, soon), Some(Duration::from_millis(400)));
+        // Different bytes or a different printer are new prints
+        assert_eq!(guard.check(&raster(2), &printer, soon), None);
+        assert_eq!(guard.check(&raster(2), &["printer-b".to_string()], soon), None);
+        // The same print again once the window has passed goes through
+        let later = soon + DuplicateGuard::WINDOW;
+        assert_eq!(guard.check(&raster(2), &["printer-b".to_string()], later), None);
+
+        let image = |w, invert| {
+            let options = RasterOptions { invert, ..RasterOptions::default() };
+            vec![PrintJob::Image { img: Arc::new(DynamicImage::new_luma8(w, 8)), options }]
+        };
+        assert_eq!(guard.check(&image(8, false), &printer, later), None);
+        assert!(guard.check(&image(8, false), &printer, later).is_some());
+        // Same pixels with different options, or different pixels, print again
+        assert_eq!(guard.check(&image(8, true), &printer, later), None);
+        assert_eq!(guard.check(&image(9, true), &printer, later), None);
+    }
+
+    #[test]
+    fn test_validate_rejects_tiny_images() {
+        let job = |w, h| PrintJob::Image { img: Arc::new(DynamicImage::new_luma8(w, h)), options: RasterOptions::default() };
+        let err = job(1, 1).validate().unwrap_err();
+        assert_eq!(err, "Image is too small to print (1x1px, minimum 2x2)");
+        assert!(job(0, 50).validate().is_err());
+        assert!(job(2, 2).validate().is_ok());
+        assert!(PrintJob::Raster { data: vec![], width: 384, height: 0 }.validate().is_err());
+
+        // A copy marker behaves like the job it wraps
+        let copy = |job| PrintJob::Copy { job: Box::new(job), copy: 1, copies: 2 };
+        assert!(copy(job(1, 1)).validate().is_err());
+        let batch = PrintJob::BatchImage { img: DynamicImage::new_luma8(8, 8), options: RasterOptions::default() };
+        assert_eq!(copy(batch).min_feed_lines(), BATCH_FEED_LINES);
+    }
+
+    #[test]
+    fn test_into_rendered_keeps_copy_marker() {
+        let img = DynamicImage::new_luma8(8, 8);
+        let batch = PrintJob::BatchImage { img: img.clone(), options: RasterOptions::default() };
+        let copy = PrintJob::Copy { job: Box::new(batch), copy: 2, copies: 3 };
+        match copy.into_rendered() {
+            PrintJob::Copy { job, copy: 2, copies: 3 } => match *job {
+                PrintJob::Raster { data, width: 8, height: 8 } => assert_eq!(data, image_to_escpos_bytes(&img)),
+                _ => panic!("copy should wrap raster bytes"),
+            },
+            _ => panic!("copy marker was dropped"),
+        }
+    }
+
+    #[test]
+    fn test_parse_hex_bytes() {
+        assert_eq!(parse_hex_bytes("1b 40"), Ok(vec![0x1b, 0x40]));
+        assert_eq!(parse_hex_bytes(" 1B40\n0x0a, 0X9A "), Ok(vec![0x1b, 0x40, 0x0a, 0x9a]));
+        assert!(parse_hex_bytes("1b 4").unwrap_err().contains("\"4\""));
+        assert!(parse_hex_bytes("zz").is_err());
+        assert!(parse_hex_bytes("0x").is_err());
+        assert!(parse_hex_bytes("   ").is_err());
+        assert!(parse_hex_bytes(&"00".repeat(MAX_RAW_BYTES + 1)).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use image::{DynamicImage, GrayImage, Luma};
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::types::PRINTER_WIDTH;
+
+/// Blank modules around the symbol required by the QR spec.
+const QUIET_ZONE: u32 = 4;
+
+/// QR error-correction level: higher levels survive more damage but hold less data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QrEcLevel {
+    /// ~7% recovery
+    Low,
+    /// ~15% recovery
+    #[default]
+    Medium,
+    /// ~25% recovery
+    Quartile,
+    /// ~30% recovery
+    High,
+}
+
+impl QrEcLevel {
+    pub const ALL: [QrEcLevel; 4] = [QrEcLevel::Low, QrEcLevel::Medium, QrEcLevel::Quartile, QrEcLevel::High];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QrEcLevel::Low => "L (7%)",
+            QrEcLevel::Medium => "M (15%)",
+            QrEcLevel::Quartile => "Q (25%)",
+            QrEcLevel::High => "H (30%)",
+        }
+    }
+
+    fn to_qrcode(self) -> EcLevel {
+        match self {
+            QrEcLevel::Low => EcLevel::L,
+            QrEcLevel::Medium => EcLevel::M,
+            QrEcLevel::Quartile => EcLevel::Q,
+            QrEcLevel::High => EcLevel::H,
+        }
+    }
+}
+
+/// Encode `text` as a QR symbol, failing with a readable message if it exceeds
+/// the capacity of the largest symbol at the chosen error-correction level.
+pub fn encode_qr(text: &str, ec: QrEcLevel) -> Result<QrCode, String> {
+    QrCode::with_error_correction_level(text, ec.to_qrcode()).map_err(|e| match e {
+        qrcode::types::QrError::DataTooLong => format!(
+            "Text is too long for a QR code at error correction {} ({} bytes)",
+            ec.label(),
+            text.len()
+        ),
+        other => format!("QR encoding failed: {}", other),
+    })
+}
+
+/// Render `text` as a QR code centered on a PRINTER_WIDTH-wide canvas.
+/// `module_px` is the requested size of one module; it is reduced if the
+/// symbol would otherwise not fit across the paper.
+pub fn render_qr(text: &str, ec: QrEcLevel, module_px: u32) -> Result<DynamicImage, String> {
+    if text.is_empty() {
+        return Err("Nothing to encode".into());
+    }
+    let code = encode_qr(text, ec)?;
+    let modules = code.width() as u32;
+    let max_module = PRINTER_WIDTH / (modules + 2 * QUIET_ZONE);
+    let module_px = module_px.clamp(1, max_module.max(1));
+
+    let side = (modules + 2 * QUIET_ZONE) * module_px;
+    let x_offset = (PRINTER_WIDTH - side) / 2;
+    let mut img = GrayImage::from_pixel(PRINTER_WIDTH, side, Luma([255u8]));
+
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color != Color::Dark {
+            continue;
+        }
+        let mx = i as u32 % modules;
+        let my = i as u32 / modules;
+        let x0 = x_offset + (mx + QUIET_ZONE) * module_px;
+        let y0 = (my + QUIET_ZONE) * module_px;
+        for y in y0..y0 + module_px {
+            for x in x0..x0 + module_px {
+                img.put_pixel(x, y, Luma([0u8]));
+            }
+        }
+    }
+
+    Ok(DynamicImage::ImageLuma8(img))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_centered_at_printer_width() {
+        let img = render_qr("https://example.com", QrEcLevel::Medium, 6).unwrap().to_luma8();
+        assert_eq!(img.width(), PRINTER_WIDTH);
+
+        // Symmetric quiet zone: first ink column mirrors the last
+        let ink_cols: Vec<u32> = (0..img.width())
+            .filter(|&x| (0..img.height()).any(|y| img.get_pixel(x, y)[0] == 0))
+            .collect();
+        let left = ink_cols[0];
+        let right = PRINTER_WIDTH - 1 - ink_cols[ink_cols.len() - 1];
+        assert!(left.abs_diff(right) <= 1, "left {} right {}", left, right);
+    }
+
+    #[test]
+    fn test_qr_module_size_shrinks_to_fit() {
+        let img = render_qr("hello", QrEcLevel::Low, 100).unwrap();
+        assert_eq!(img.width(), PRINTER_WIDTH);
+        assert!(img.height() <= PRINTER_WIDTH);
+    }
+
+    #[test]
+    fn test_qr_capacity_depends_on_ec_level() {
+        // Version 40 holds 2953 bytes at L but only 1273 at H
+        let text = "x".repeat(2000);
+        assert!(encode_qr(&text, QrEcLevel::Low).is_ok());
+        assert!(encode_qr(&text, QrEcLevel::High).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+use std::sync::Arc;
+use ab_glyph::{FontVec, PxScale};
+use image::{DynamicImage, Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_line_segment_mut, draw_text_mut};
+use imageproc::rect::Rect;
+
+use crate::types::{load_font_cached, FONT_CHOICES, PRINTER_WIDTH};
+
+/// Printer dots per millimetre (203 dpi).
+const DOTS_PER_MM: u32 = 8;
+
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Render the built-in diagnostic page: a millimetre ruler, a grayscale ramp,
+/// a sample line in each installed font, and the printer's last status string.
+/// Text sections are skipped if none of the fonts in FONT_CHOICES can be loaded.
+pub fn render_test_page(status: &str) -> DynamicImage {
+    let fonts: Vec<(&str, Arc<FontVec>)> = FONT_CHOICES
+        .iter()
+        .filter_map(|fc| match load_font_cached(&fc.path, 0) {
+            Ok(font) => Some((fc.label.as_ref(), font)),
+            Err(e) => {
+                // Fonts that aren't installed are expected; a broken one is worth a mention
+                if Path::new(fc.path.as_ref()).exists() {
+                    log::warn!("Test page: {}", e);
+                }
+                None
+            }
+        })
+        .collect();
+
+    let height = 150 + 28 * fonts.len() as u32 + 60;
+    let mut img = RgbImage::from_pixel(PRINTER_WIDTH, height, WHITE);
+    let title_font = fonts.first().map(|(_, f)| f.as_ref());
+
+    let mut y = 0i32;
+    if let Some(font) = title_font {
+        draw_text_mut(&mut img, BLACK, 0, y, PxScale::from(28.0), font, "CTP500 test page");
+    }
+    y += 40;
+
+    // Ruler: a tick every mm, longer every 5mm, labelled every 10mm
+    let baseline = (y + 24) as f32;
+    draw_line_segment_mut(&mut img, (0.0, baseline), ((PRINTER_WIDTH - 1) as f32, baseline), BLACK);
+    for mm in 0..=PRINTER_WIDTH / DOTS_PER_MM {
+        let x = (mm * DOTS_PER_MM).min(PRINTER_WIDTH - 1) as f32;
+        let len = if mm % 10 == 0 { 24.0 } else if mm % 5 == 0 { 14.0 } else { 7.0 };
+        draw_line_segment_mut(&mut img, (x, baseline - len), (x, baseline), BLACK);
+        if mm % 10 == 0 && mm > 0 {
+            if let Some(font) = title_font {
+                let label = format!("{}", mm / 10);
+                draw_text_mut(&mut img, BLACK, x as i32 + 2, y, PxScale::from(12.0), font, &label);
+            }
+        }
+    }
+    y += 36;
+
+    // Grayscale ramp, black on the left to white on the right
+    for x in 0..PRINTER_WIDTH {
+        let level = (x * 255 / (PRINTER_WIDTH - 1)) as u8;
+        draw_filled_rect_mut(&mut img, Rect::at(x as i32, y).of_size(1, 40), Rgb([level, level, level]));
+    }
+    y += 56;
+
+    // One sample line per font
+    for (label, font) in &fonts {
+        let sample = format!("{}: AaBb 0123 #@&", label);
+        draw_text_mut(&mut img, BLACK, 0, y, PxScale::from(20.0), font.as_ref(), &sample);
+        y += 28;
+    }
+
+    // Firmware / battery status as last reported by the printer
+    if let Some(font) = title_font {
+        let status = if status.is_empty() { "status: (none received)" } else { status };
+        draw_text_mut(&mut img, BLACK, 0, y + 8, PxScale::from(14.0), font, status);
+    }
+
+    DynamicImage::ImageRgb8(img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_is_printer_width_with_ink() {
+        let img = render_test_page("HV=V1.0A,SV=V1.01,VOLT=4000mv,DPI=384").to_luma8();
+        assert_eq!(img.width(), PRINTER_WIDTH);
+        assert!(img.pixels().any(|p| p[0] < 128));
+    }
+}
@@ -1,53 +1,397 @@
-use ab_glyph::{Font, PxScale, ScaleFont};
-use image::{DynamicImage, Rgb, RgbImage};
-use imageproc::drawing::draw_text_mut;
-use crate::types::PRINTER_WIDTH;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use ab_glyph::{point, Font, FontVec, GlyphId, PxScale, ScaleFont};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use image::{imageops, DynamicImage, Rgb, RgbImage};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use crate::types::{load_font, load_font_cached, Alignment, ListStyle, DOTS_PER_MM, FALLBACK_FONT_PATHS, PRINTER_WIDTH};
 
-const CANVAS_HEIGHT: u32 = 5000;
+/// Maximum height of one rendered page; longer text is split across pages.
+/// Each page's canvas is only as tall as its lines need.
+const MAX_PAGE_HEIGHT: u32 = 5000;
+/// Blank rows kept below the last line of ink on each page.
+const BOTTOM_PADDING: u32 = 10;
 
-/// Render text to a bitmap image at PRINTER_WIDTH, trimmed of trailing whitespace.
+/// Font and layout settings for rendering text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOptions {
+    /// Absolute path to the .ttf/.ttc/.otf file used by ab_glyph.
+    pub font_path: String,
+    /// Face within a .ttc collection (0 for single-face fonts).
+    pub face_index: u32,
+    /// Pixel size used when rendering.
+    pub font_size: f32,
+    /// Multiplier on the font's natural line height (1.0 = unchanged).
+    pub line_spacing: f32,
+    /// Horizontal placement of each wrapped line.
+    pub alignment: Alignment,
+    /// Keep leading whitespace and runs of spaces, wrapping only at the right
+    /// margin, instead of re-flowing words.
+    pub preserve_indent: bool,
+    /// Tab stop interval in columns when `preserve_indent` or `preformatted` is set.
+    pub tab_width: usize,
+    /// Table mode: each input line prints as exactly one line, tabs expanded
+    /// and spacing kept, with no word wrap. If the widest line is too wide the
+    /// whole block is set smaller so columns stay aligned. Lines are always
+    /// left-aligned; markdown and list styles are ignored.
+    pub preformatted: bool,
+    /// strftime format for a timestamp line printed above the text; None for no header.
+    pub timestamp_format: Option<String>,
+    /// Treat the text as markdown (headings, bold, lists, rules).
+    pub markdown: bool,
+    /// Blank space kept at each side of the paper, in pixels. Pages are still
+    /// PRINTER_WIDTH wide; text wraps within the narrower area.
+    pub left_margin: u32,
+    pub right_margin: u32,
+    /// Flip finished pages left to right (for iron-on transfer paper).
+    pub mirror: bool,
+    /// Print white text on a solid black bar the width of the paper, e.g.
+    /// for label headers.
+    pub highlight: bool,
+    /// Rows whose pixels are all at least this light count as blank when
+    /// trimming the top and bottom of each page (255 = only pure white).
+    pub white_threshold: u8,
+    /// Pixels to move every line down (negative: up) from where the font
+    /// places it, for fonts that sit too high or too low.
+    pub baseline_offset: i32,
+    /// Prefix each non-empty line with a bullet or number, with wrapped
+    /// lines indented to line up under the item's text. Ignored for markdown.
+    pub list_style: ListStyle,
+}
+
+impl TextOptions {
+    pub const DEFAULT_TAB_WIDTH: usize = 4;
+    /// Used when the timestamp format is empty or invalid.
+    pub const DEFAULT_TIMESTAMP_FORMAT: &'static str = "%Y-%m-%d %H:%M";
+    /// Largest margin on either side, so there is always room for text.
+    pub const MAX_MARGIN: u32 = 128;
+    /// Tolerates faint anti-aliasing and off-white specks when trimming.
+    pub const DEFAULT_WHITE_THRESHOLD: u8 = 250;
+    /// Largest baseline adjustment either way.
+    pub const MAX_BASELINE_OFFSET: i32 = 32;
+
+    /// Left edge and width of the area text is wrapped and drawn in.
+    fn text_area(&self) -> (f32, f32) {
+        let left = self.left_margin.min(Self::MAX_MARGIN);
+        let right = self.right_margin.min(Self::MAX_MARGIN);
+        (left as f32, (PRINTER_WIDTH - left - right) as f32)
+    }
+}
+
+/// Whether chrono can render `format` (unknown `%` specifiers are rejected).
+pub fn is_valid_timestamp_format(format: &str) -> bool {
+    !format.trim().is_empty() && StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
+}
+
+/// Format `now` with `format`, falling back to the default for invalid formats.
+fn timestamp_line(format: &str, now: DateTime<Local>) -> String {
+    let format = if is_valid_timestamp_format(format) { format } else { TextOptions::DEFAULT_TIMESTAMP_FORMAT };
+    now.format(format).to_string()
+}
+
+/// `text` with Windows (`\r\n`) and classic Mac (`\r`) line endings turned
+/// into `\n`, so pasted text wraps the same whichever OS it came from.
+fn normalize_line_endings(text: &str) -> Cow<'_, str> {
+    if text.contains('\r') {
+        Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// `text` with the current time prepended as its first line, if a timestamp
+/// header is enabled. Evaluated at render time so queued jobs print when sent.
+fn with_timestamp<'a>(text: &'a str, options: &TextOptions) -> Cow<'a, str> {
+    match &options.timestamp_format {
+        Some(format) => {
+            // A blank line keeps the timestamp its own paragraph in markdown
+            let separator = if options.markdown { "\n\n" } else { "\n" };
+            Cow::Owned(format!("{}{}{}", timestamp_line(format, Local::now()), separator, text))
+        }
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// Render text to bitmap pages at PRINTER_WIDTH, each trimmed of blank rows
+/// above and below the text.
 /// Port of Python's `create_text` + `get_wrapped_text` + `trimImage`.
-pub fn render_text_to_image(text: &str, font_path: &str, font_size: f32) -> Result<DynamicImage, String> {
-    let font_data = std::fs::read(font_path)
-        .map_err(|e| format!("Failed to read font {}: {}", font_path, e))?;
+///
+/// Empty or whitespace-only text is an error rather than a blank page.
+///
+/// Text taller than MAX_PAGE_HEIGHT is split on line boundaries into several
+/// images, printed in order. With `options.highlight` each page is inverted
+/// into a black bar, and with `options.mirror` flipped left to right, once
+/// it's laid out.
+pub fn render_text_to_images(text: &str, options: &TextOptions) -> Result<Vec<DynamicImage>, String> {
+    let mut pages = render_pages(text, options)?;
+    if options.highlight {
+        pages = pages.into_iter().map(highlight_page).collect();
+    }
+    if !options.mirror {
+        return Ok(pages);
+    }
+    Ok(pages.iter().map(|page| DynamicImage::ImageRgba8(imageops::flip_horizontal(page))).collect())
+}
 
+fn render_pages(text: &str, options: &TextOptions) -> Result<Vec<DynamicImage>, String> {
+    let TextOptions { ref font_path, face_index, font_size, line_spacing, alignment, .. } = *options;
     // FontRef requires a static lifetime; use FontVec instead for owned data
-    let font = ab_glyph::FontVec::try_from_vec(font_data)
-        .map_err(|e| format!("Failed to parse font: {}", e))?;
+    let primary = load_font_cached(font_path, face_index)?;
+    let font = FontStack::with_fallbacks(&primary);
 
     let scale = PxScale::from(font_size);
+    let text = normalize_line_endings(text);
+    let text = with_timestamp(&text, options);
+    if text.trim().is_empty() {
+        return Err("text is empty".to_string());
+    }
+    if options.markdown && !options.preformatted {
+        let lines = layout_markdown(&parse_markdown(&text), &font, scale, options);
+        return Ok(render_markdown_pages(&lines, &font, options));
+    }
+    let (scale, wrapped_lines) = layout_plain(&text, &font, scale, options);
+    let alignment = if options.preformatted { Alignment::Left } else { alignment };
+    let line_height = line_height(&primary, scale, line_spacing);
+    let lines_per_page = (MAX_PAGE_HEIGHT / line_height).max(1) as usize;
+
+    let pages = wrapped_lines
+        .chunks(lines_per_page)
+        .map(|page| {
+            let img = draw_plain_page(page, &font, scale, line_height, alignment, options);
+            trim_image(DynamicImage::ImageRgb8(img), options.white_threshold)
+        })
+        .collect();
+    Ok(pages)
+}
+
+/// Draw one page of wrapped lines onto a canvas just tall enough to hold them.
+fn draw_plain_page(
+    page: &[(f32, String)],
+    font: &FontStack,
+    scale: PxScale,
+    line_height: u32,
+    alignment: Alignment,
+    options: &TextOptions,
+) -> RgbImage {
+    // The last line's glyphs can reach below its line height when spacing is tight
+    let glyph_height = self::line_height(font.fonts[0], scale, 1.0).max(line_height);
+    let content_height = (page.len() as u32).saturating_sub(1) * line_height + glyph_height;
+    let mut img = blank_canvas(content_height, options);
+
+    // Draw text line by line to track Y position
+    let mut y = baseline_offset(options);
+    let (left, width) = options.text_area();
+    for (indent, line) in page {
+        let x = line_x(measure_text_width(font, scale, line), alignment, (left + indent, width - indent));
+        draw_line(&mut img, font, scale, x, y, line);
+        y += line_height as i32;
+    }
+    img
+}
 
-    // Word-wrap each line of input text
-    let mut wrapped_lines: Vec<String> = Vec::new();
+fn baseline_offset(options: &TextOptions) -> i32 {
+    options.baseline_offset.clamp(-TextOptions::MAX_BASELINE_OFFSET, TextOptions::MAX_BASELINE_OFFSET)
+}
+
+/// White canvas for `content_height` rows of text, with room for a downward
+/// baseline offset and the bottom padding kept by `trim_image`.
+fn blank_canvas(content_height: u32, options: &TextOptions) -> RgbImage {
+    let height = content_height + baseline_offset(options).max(0) as u32 + BOTTOM_PADDING;
+    RgbImage::from_pixel(PRINTER_WIDTH, height, Rgb([255u8, 255, 255]))
+}
+
+/// Size of a block of text once wrapped to the paper width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextEstimate {
+    pub words: usize,
+    pub lines: usize,
+    /// Printed height in pixels (printer dots).
+    pub height_px: u32,
+}
+
+impl TextEstimate {
+    /// Approximate paper length in centimetres.
+    pub fn length_cm(&self) -> f32 {
+        self.height_px as f32 / DOTS_PER_MM / 10.0
+    }
+}
+
+/// Estimate how much paper `text` would use with `options`, wrapping it the
+/// same way `render_text_to_images` does but without drawing anything.
+pub fn estimate_text(text: &str, options: &TextOptions) -> Result<TextEstimate, String> {
+    let primary = load_font_cached(&options.font_path, options.face_index)?;
+    let font = FontStack::with_fallbacks(&primary);
+    let scale = PxScale::from(options.font_size);
+    let text = normalize_line_endings(text);
+    let text = with_timestamp(&text, options);
+    if options.markdown && !options.preformatted {
+        let md_lines = layout_markdown(&parse_markdown(&text), &font, scale, options);
+        let height_px = md_lines.iter().map(|l| l.gap_before + l.height).sum();
+        return Ok(TextEstimate { words: text.split_whitespace().count(), lines: md_lines.len(), height_px });
+    }
+    let (scale, lines) = layout_plain(&text, &font, scale, options);
+    let lines = lines.len();
+    let height_px = lines as u32 * line_height(&primary, scale, options.line_spacing);
+    Ok(TextEstimate { words: text.split_whitespace().count(), lines, height_px })
+}
+
+/// Lay out non-markdown text: word-wrapped, or with `preformatted` one line
+/// per input line at a scale small enough for the widest to fit. Returns the
+/// scale to draw at along with the lines.
+fn layout_plain(text: &str, font: &FontStack, scale: PxScale, options: &TextOptions) -> (PxScale, Vec<(f32, String)>) {
+    if !options.preformatted {
+        return (scale, wrap_lines(text, font, scale, options));
+    }
+    let lines: Vec<String> = text.lines().map(|line| expand_tabs(line, options.tab_width).trim_end().to_string()).collect();
+    let (_, max_width) = options.text_area();
+    let widest = lines.iter().map(|line| measure_text_width(font, scale, line)).fold(0.0, f32::max);
+    let scale = if widest > max_width { PxScale::from(scale.y * max_width / widest) } else { scale };
+    (scale, lines.into_iter().map(|line| (0.0, line)).collect())
+}
+
+/// Word-wrap each line of input text to the text area between the margins.
+/// Each wrapped line comes with its indent in pixels: the hanging indent of
+/// a list item's continuation lines, otherwise 0.
+fn wrap_lines(text: &str, font: &FontStack, scale: PxScale, options: &TextOptions) -> Vec<(f32, String)> {
+    let (_, max_width) = options.text_area();
+    let mut wrapped_lines = Vec::new();
+    let mut item = 0;
     for line in text.lines() {
-        let wrapped = get_wrapped_text(line, &font, scale, PRINTER_WIDTH as f32);
-        wrapped_lines.push(wrapped);
+        let marker = if line.trim().is_empty() {
+            None
+        } else {
+            item += 1;
+            options.list_style.marker(item)
+        };
+        let Some(marker) = marker else {
+            wrapped_lines.extend(wrap_line(line, font, scale, max_width, options).into_iter().map(|l| (0.0, l)));
+            continue;
+        };
+        let hanging = measure_text_width(font, scale, &marker);
+        let pieces = wrap_line(line.trim_start(), font, scale, max_width - hanging, options);
+        for (i, piece) in pieces.into_iter().enumerate() {
+            wrapped_lines.push(if i == 0 { (0.0, format!("{}{}", marker, piece)) } else { (hanging, piece) });
+        }
     }
-    let full_text = wrapped_lines.join("\n");
+    wrapped_lines
+}
 
-    // Create white canvas
-    let mut img = RgbImage::from_pixel(PRINTER_WIDTH, CANVAS_HEIGHT, Rgb([255u8, 255, 255]));
+/// Word-wrap one line of input text to `max_width`.
+fn wrap_line(line: &str, font: &FontStack, scale: PxScale, max_width: f32, options: &TextOptions) -> Vec<String> {
+    if options.preserve_indent {
+        let line = expand_tabs(line, options.tab_width);
+        get_wrapped_text_preserving(&line, font, scale, max_width)
+    } else {
+        get_wrapped_text(line, font, scale, max_width).split('\n').map(str::to_string).collect()
+    }
+}
 
-    // Draw text line by line to track Y position
+/// Distance between baselines: the font's natural line height times `line_spacing`.
+fn line_height(font: &FontVec, scale: PxScale, line_spacing: f32) -> u32 {
     let scaled = font.as_scaled(scale);
-    let line_height = (scaled.ascent() - scaled.descent() + scaled.line_gap()).ceil() as i32;
+    let natural_height = scaled.ascent() - scaled.descent() + scaled.line_gap();
+    (natural_height * line_spacing).ceil().max(1.0) as u32
+}
 
-    let mut y = 0i32;
-    for line in full_text.lines() {
-        draw_text_mut(&mut img, Rgb([0u8, 0, 0]), 0, y, scale, &font, line);
-        y += line_height;
-        if y >= CANVAS_HEIGHT as i32 {
-            break;
-        }
+/// A glyph's font index in the stack, its id and its advance width.
+type GlyphAdvance = (usize, GlyphId, f32);
+
+/// The chosen font followed by fallbacks used for characters it has no glyph
+/// for, so CJK text or symbols don't come out blank.
+struct FontStack<'a> {
+    fonts: Vec<&'a FontVec>,
+    /// Glyph lookup and advance width per (scale, char), filled in as text is
+    /// measured. A stack lives for one render, so word-wrapping only resolves
+    /// each character once.
+    advances: RefCell<HashMap<(u32, u32, char), GlyphAdvance>>,
+}
+
+impl<'a> FontStack<'a> {
+    fn new(fonts: Vec<&'a FontVec>) -> Self {
+        Self { fonts, advances: RefCell::default() }
     }
 
-    let img = DynamicImage::ImageRgb8(img);
-    Ok(trim_image(img))
+    /// `primary` followed by every loadable font in FALLBACK_FONT_PATHS.
+    fn with_fallbacks(primary: &'a FontVec) -> Self {
+        let mut fonts = vec![primary];
+        fonts.extend(fallback_fonts());
+        Self::new(fonts)
+    }
+
+    /// `glyph(c)` plus its horizontal advance at `scale`, cached.
+    fn advance(&self, scale: PxScale, c: char) -> GlyphAdvance {
+        *self.advances.borrow_mut().entry((scale.x.to_bits(), scale.y.to_bits(), c)).or_insert_with(|| {
+            let (idx, glyph_id) = self.glyph(c);
+            (idx, glyph_id, self.fonts[idx].as_scaled(scale).h_advance(glyph_id))
+        })
+    }
+
+    /// The first font that has a glyph for `c` (index into `fonts`) and that
+    /// glyph. Falls back to the primary font's notdef if none do.
+    fn glyph(&self, c: char) -> (usize, GlyphId) {
+        self.fonts
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (i, f.glyph_id(c)))
+            .find(|&(_, id)| id.0 != 0)
+            .unwrap_or((0, self.fonts[0].glyph_id(c)))
+    }
+}
+
+/// Fallback fonts, loaded once on first use.
+fn fallback_fonts() -> &'static [FontVec] {
+    static FALLBACKS: OnceLock<Vec<FontVec>> = OnceLock::new();
+    FALLBACKS.get_or_init(|| {
+        FALLBACK_FONT_PATHS
+            .iter()
+            .filter(|path| Path::new(path).exists())
+            .filter_map(|path| load_font(path, 0).map_err(|e| log::warn!("Skipping fallback font: {}", e)).ok())
+            .collect()
+    })
+}
+
+/// Draw one line of black text with its top-left corner at (x, y), picking
+/// each glyph from the font stack. All glyphs share the primary font's baseline.
+fn draw_line(img: &mut RgbImage, font: &FontStack, scale: PxScale, x: i32, y: i32, text: &str) {
+    let baseline = y as f32 + font.fonts[0].as_scaled(scale).ascent();
+    let mut caret = x as f32;
+    let mut prev: Option<(usize, GlyphId)> = None;
+
+    for c in text.chars() {
+        let (idx, id) = font.glyph(c);
+        let scaled = font.fonts[idx].as_scaled(scale);
+        if let Some((prev_idx, prev_id)) = prev {
+            if prev_idx == idx {
+                caret += scaled.kern(prev_id, id);
+            }
+        }
+        let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+        if let Some(outlined) = font.fonts[idx].outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px >= 0 && py >= 0 && (px as u32) < img.width() && (py as u32) < img.height() {
+                    let pixel = img.get_pixel_mut(px as u32, py as u32);
+                    let coverage = coverage.clamp(0.0, 1.0);
+                    for ch in pixel.0.iter_mut() {
+                        *ch = (*ch as f32 * (1.0 - coverage)).round() as u8;
+                    }
+                }
+            });
+        }
+        caret += scaled.h_advance(id);
+        prev = Some((idx, id));
+    }
 }
 
 /// Word-wrap text to fit within `max_width` pixels.
 /// Port of Python's `get_wrapped_text`.
-fn get_wrapped_text<F: Font>(text: &str, font: &F, scale: PxScale, max_width: f32) -> String {
+fn get_wrapped_text(text: &str, font: &FontStack, scale: PxScale, max_width: f32) -> String {
     let mut lines: Vec<String> = vec![String::new()];
 
     for word in text.split_whitespace() {
@@ -59,8 +403,18 @@ fn get_wrapped_text<F: Font>(text: &str, font: &F, scale: PxScale, max_width: f3
 
         if measure_text_width(font, scale, &candidate) <= max_width {
             *lines.last_mut().unwrap() = candidate;
-        } else {
+        } else if measure_text_width(font, scale, word) <= max_width {
             lines.push(word.to_string());
+        } else {
+            // Oversized word (e.g. a URL): break it across lines between glyphs
+            let mut pieces = break_word(word, font, scale, max_width).into_iter();
+            let first = pieces.next().unwrap_or_default();
+            if lines.last().unwrap().is_empty() {
+                *lines.last_mut().unwrap() = first;
+            } else {
+                lines.push(first);
+            }
+            lines.extend(pieces);
         }
     }
 
@@ -72,42 +426,785 @@ fn get_wrapped_text<F: Font>(text: &str, font: &F, scale: PxScale, max_width: f3
     lines.join("\n")
 }
 
-/// Measure the pixel width of a string using glyph advance widths.
-fn measure_text_width<F: Font>(font: &F, scale: PxScale, text: &str) -> f32 {
-    let scaled = font.as_scaled(scale);
+/// Replace each tab with spaces up to the next multiple of `tab_width` columns.
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut out = String::with_capacity(line.len());
+    let mut col = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = tab_width - col % tab_width;
+            out.extend(std::iter::repeat_n(' ', spaces));
+            col += spaces;
+        } else {
+            out.push(c);
+            col += 1;
+        }
+    }
+    out
+}
+
+/// Wrap a line without collapsing whitespace: the leading indent and inner
+/// runs of spaces are kept, and breaks happen only where a word would cross
+/// `max_width`. Continuation lines repeat the indent (unless it is so wide
+/// that nothing else would fit).
+fn get_wrapped_text_preserving(text: &str, font: &FontStack, scale: PxScale, max_width: f32) -> Vec<String> {
+    let body = text.trim_start_matches(' ');
+    let indent = &text[..text.len() - body.len()];
+    let hanging = if measure_text_width(font, scale, indent) < max_width / 2.0 { indent } else { "" };
+
+    let mut lines = vec![indent.to_string()];
+    // Tokens are words with the spaces that precede them, e.g. ["foo", "  bar"]
+    let mut rest = body;
+    while !rest.is_empty() {
+        let word_start = rest.len() - rest.trim_start_matches(' ').len();
+        let word_end = rest[word_start..].find(' ').map_or(rest.len(), |i| word_start + i);
+        let (token, tail) = rest.split_at(word_end);
+        rest = tail;
+
+        let current = lines.last_mut().unwrap();
+        let candidate = format!("{}{}", current, token);
+        if measure_text_width(font, scale, &candidate) <= max_width {
+            *current = candidate;
+            continue;
+        }
+
+        // Move the word to a new (hanging-indented) line, unless the current
+        // one holds only indentation; break it up if it still doesn't fit.
+        let word = token.trim_start_matches(' ');
+        let only_indent = current.trim().is_empty();
+        let prefix = if only_indent { candidate[..candidate.len() - word.len()].to_string() } else { hanging.to_string() };
+        let available = max_width - measure_text_width(font, scale, &prefix);
+        let mut pieces = break_word(word, font, scale, available).into_iter();
+        let first = format!("{}{}", prefix, pieces.next().unwrap_or_default());
+        if only_indent {
+            *current = first;
+        } else {
+            lines.push(first);
+        }
+        lines.extend(pieces.map(|p| format!("{}{}", hanging, p)));
+    }
+    lines
+}
+
+/// Split a word into pieces that each fit within `max_width`, breaking after
+/// the last glyph that still fits. Every piece holds at least one character,
+/// so a glyph wider than the line still makes progress.
+fn break_word(word: &str, font: &FontStack, scale: PxScale, max_width: f32) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    for c in word.chars() {
+        let current = pieces.last_mut().unwrap();
+        current.push(c);
+        if current.chars().count() > 1 && measure_text_width(font, scale, current) > max_width {
+            current.pop();
+            pieces.push(c.to_string());
+        }
+    }
+    pieces
+}
+
+/// Starting x for a line of `line_width` pixels within the text `area`
+/// (left edge, width). Lines wider than the area start at its left edge
+/// rather than going further left.
+fn line_x(line_width: f32, alignment: Alignment, (left, width): (f32, f32)) -> i32 {
+    let slack = (width - line_width).max(0.0);
+    let offset = match alignment {
+        Alignment::Left => 0.0,
+        Alignment::Center => (slack / 2.0).floor(),
+        Alignment::Right => slack.floor(),
+    };
+    (left + offset) as i32
+}
+
+/// Measure the pixel width of a string using glyph advance widths, taking
+/// each glyph from whichever font in the stack provides it.
+fn measure_text_width(font: &FontStack, scale: PxScale, text: &str) -> f32 {
     let mut width = 0.0f32;
-    let mut prev_glyph_id = None;
+    let mut prev: Option<(usize, GlyphId)> = None;
 
     for c in text.chars() {
-        let glyph_id = scaled.glyph_id(c);
-        if let Some(prev) = prev_glyph_id {
-            width += scaled.kern(prev, glyph_id);
+        let (idx, glyph_id, advance) = font.advance(scale, c);
+        if let Some((prev_idx, prev_id)) = prev {
+            if prev_idx == idx {
+                width += font.fonts[idx].as_scaled(scale).kern(prev_id, glyph_id);
+            }
         }
-        width += scaled.h_advance(glyph_id);
-        prev_glyph_id = Some(glyph_id);
+        width += advance;
+        prev = Some((idx, glyph_id));
     }
     width
 }
 
-/// Trim trailing whitespace rows from the bottom of an image, keeping 10px padding.
-/// Port of Python's `trimImage`.
-fn trim_image(img: DynamicImage) -> DynamicImage {
+// ── Markdown ──────────────────────────────────────────────────────────────────
+
+/// Indent per nested list level, in pixels.
+const LIST_INDENT_PX: f32 = 24.0;
+
+/// A word of markdown text; `space_before` is false when it directly follows
+/// the previous word (e.g. "**bold**," splits into "bold" and ",").
+#[derive(Debug, Clone, PartialEq)]
+struct MdWord {
+    text: String,
+    bold: bool,
+    space_before: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum MdBlock {
+    /// A paragraph, heading (`heading` 1–6, 0 for body text) or list item
+    /// (`marker` such as "•" or "2.", nested `depth` levels deep).
+    Text { heading: u8, marker: Option<String>, depth: usize, words: Vec<MdWord> },
+    /// Horizontal rule.
+    Rule,
+}
+
+/// Parse the subset of markdown the printer supports: headings, bold,
+/// bullet/numbered lists and horizontal rules. Italic, links and inline code
+/// are printed as plain text.
+fn parse_markdown(text: &str) -> Vec<MdBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<MdBlock> = None;
+    // Next number for each open list (None for bullet lists)
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut bold = 0usize;
+    let mut pending_space = false;
+
+    fn flush(blocks: &mut Vec<MdBlock>, current: &mut Option<MdBlock>, pending_space: &mut bool) {
+        if let Some(block @ MdBlock::Text { .. }) = current.take() {
+            if matches!(&block, MdBlock::Text { words, .. } if !words.is_empty()) {
+                blocks.push(block);
+            }
+        }
+        *pending_space = false;
+    }
+
+    for event in Parser::new(text) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                flush(&mut blocks, &mut current, &mut pending_space);
+                current = Some(MdBlock::Text { heading: level as u8, marker: None, depth: 0, words: Vec::new() });
+            }
+            Event::Start(Tag::Paragraph) => {
+                // Loose list items wrap their text in a paragraph; keep the marker
+                let empty_item = matches!(&current, Some(MdBlock::Text { words, .. }) if words.is_empty());
+                if !empty_item {
+                    flush(&mut blocks, &mut current, &mut pending_space);
+                    current = Some(MdBlock::Text { heading: 0, marker: None, depth: lists.len(), words: Vec::new() });
+                }
+            }
+            Event::Start(Tag::List(start)) => {
+                flush(&mut blocks, &mut current, &mut pending_space);
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                flush(&mut blocks, &mut current, &mut pending_space);
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                flush(&mut blocks, &mut current, &mut pending_space);
+                let marker = match lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                    _ => "•".to_string(),
+                };
+                current = Some(MdBlock::Text { heading: 0, marker: Some(marker), depth: lists.len(), words: Vec::new() });
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item) => {
+                flush(&mut blocks, &mut current, &mut pending_space);
+            }
+            Event::Start(Tag::Strong) => bold += 1,
+            Event::End(TagEnd::Strong) => bold = bold.saturating_sub(1),
+            Event::Text(t) | Event::Code(t) => {
+                let MdBlock::Text { words, .. } = current.get_or_insert_with(|| MdBlock::Text {
+                    heading: 0,
+                    marker: None,
+                    depth: lists.len(),
+                    words: Vec::new(),
+                }) else {
+                    continue;
+                };
+                let mut space = pending_space || t.starts_with(char::is_whitespace);
+                for word in t.split_whitespace() {
+                    words.push(MdWord { text: word.to_string(), bold: bold > 0, space_before: space && !words.is_empty() });
+                    space = true;
+                }
+                pending_space = t.ends_with(char::is_whitespace);
+            }
+            Event::SoftBreak | Event::HardBreak => pending_space = true,
+            Event::Rule => {
+                flush(&mut blocks, &mut current, &mut pending_space);
+                blocks.push(MdBlock::Rule);
+            }
+            _ => {}
+        }
+    }
+    flush(&mut blocks, &mut current, &mut pending_space);
+    blocks
+}
+
+/// Size multiplier for a heading level (0 = body text).
+fn heading_scale(heading: u8) -> f32 {
+    match heading {
+        1 => 1.8,
+        2 => 1.5,
+        3 => 1.25,
+        4 => 1.1,
+        _ => 1.0,
+    }
+}
+
+/// One laid-out line of markdown: text runs at absolute x positions.
+#[derive(Debug, Clone, PartialEq)]
+struct MdLine {
+    scale: PxScale,
+    height: u32,
+    /// Blank space above the line separating it from the previous block.
+    gap_before: u32,
+    /// Draw a horizontal rule over this x range instead of text.
+    rule: Option<(u32, u32)>,
+    /// (x, text, bold)
+    runs: Vec<(f32, String, bool)>,
+}
+
+/// Wrap markdown blocks to the text area. Headings are larger and bold,
+/// list items hang-indent under their marker.
+fn layout_markdown(blocks: &[MdBlock], font: &FontStack, body: PxScale, options: &TextOptions) -> Vec<MdLine> {
+    let (left, max_width) = options.text_area();
+    let body_height = line_height(font.fonts[0], body, options.line_spacing);
+    let mut lines: Vec<MdLine> = Vec::new();
+    let mut prev_was_item = false;
+
+    for (i, block) in blocks.iter().enumerate() {
+        let MdBlock::Text { heading, marker, depth, words } = block else {
+            let gap_before = if i == 0 { 0 } else { body_height / 2 };
+            let rule = Some((left as u32, (left + max_width) as u32));
+            lines.push(MdLine { scale: body, height: body_height, gap_before, rule, runs: Vec::new() });
+            prev_was_item = false;
+            continue;
+        };
+        let scale = PxScale::from(body.x * heading_scale(*heading));
+        let height = line_height(font.fonts[0], scale, options.line_spacing);
+        let is_item = marker.is_some();
+        let gap = if i == 0 || (is_item && prev_was_item) { 0 } else { body_height / 2 };
+        prev_was_item = is_item;
+
+        let indent = depth.saturating_sub(1) as f32 * LIST_INDENT_PX;
+        let bullet_width = measure_text_width(font, scale, "• ");
+        let mut runs = Vec::new();
+        let hanging = match marker {
+            Some(marker) => {
+                let label = format!("{} ", marker);
+                runs.push((indent, marker.clone(), false));
+                indent + measure_text_width(font, scale, &label).max(bullet_width)
+            }
+            None if *depth > 0 => indent + bullet_width,
+            None => 0.0,
+        };
+        let space = measure_text_width(font, scale, " ");
+        let mut caret = hanging;
+        let mut first_line = true;
+        let finish = |runs: Vec<(f32, String, bool)>, width: f32, first: bool, lines: &mut Vec<MdLine>| {
+            // Only plain paragraphs and headings follow the alignment setting
+            let shift = if is_item || *depth > 0 { left } else { line_x(width, options.alignment, (left, max_width)) as f32 };
+            let runs = runs.into_iter().map(|(x, t, b)| (x + shift, t, b)).collect();
+            lines.push(MdLine { scale, height, gap_before: if first { gap } else { 0 }, rule: None, runs });
+        };
+
+        for word in words {
+            let bold = word.bold || *heading > 0;
+            let width = measure_text_width(font, scale, &word.text);
+            let gap_px = if word.space_before && caret > hanging { space } else { 0.0 };
+            if caret + gap_px + width <= max_width {
+                runs.push((caret + gap_px, word.text.clone(), bold));
+                caret += gap_px + width;
+                continue;
+            }
+            // Start a new line; break the word up if it is wider than a line
+            for piece in break_word(&word.text, font, scale, max_width - hanging) {
+                if caret > hanging {
+                    finish(std::mem::take(&mut runs), caret, first_line, &mut lines);
+                    first_line = false;
+                    caret = hanging;
+                }
+                caret += measure_text_width(font, scale, &piece);
+                runs.push((hanging, piece, bold));
+            }
+        }
+        finish(runs, caret, first_line, &mut lines);
+    }
+    lines
+}
+
+/// Draw laid-out markdown lines onto as many pages of up to MAX_PAGE_HEIGHT
+/// as needed. Bold text is drawn twice, 1px apart.
+fn render_markdown_pages(lines: &[MdLine], font: &FontStack, options: &TextOptions) -> Vec<DynamicImage> {
+    // Assign each line a page and a top edge first, so each canvas is sized to fit
+    let mut pages: Vec<Vec<(u32, &MdLine)>> = vec![Vec::new()];
+    let mut y = 0u32;
+    for line in lines {
+        let mut top = if y == 0 { 0 } else { y + line.gap_before };
+        if top + line.height > MAX_PAGE_HEIGHT && y > 0 {
+            pages.push(Vec::new());
+            top = 0;
+        }
+        pages.last_mut().expect("at least one page").push((top, line));
+        y = top + line.height;
+    }
+
+    let offset = baseline_offset(options);
+    pages
+        .iter()
+        .map(|page| {
+            let content_height = page.iter().map(|(top, line)| top + line.height).max().unwrap_or(1);
+            let mut img = blank_canvas(content_height, options);
+            for &(top, line) in page {
+                if let Some((from, to)) = line.rule {
+                    let mid = top + line.height / 2;
+                    for x in from..to {
+                        img.put_pixel(x, mid, Rgb([0, 0, 0]));
+                        img.put_pixel(x, mid + 1, Rgb([0, 0, 0]));
+                    }
+                }
+                for (x, text, bold) in &line.runs {
+                    draw_line(&mut img, font, line.scale, *x as i32, top as i32 + offset, text);
+                    if *bold {
+                        draw_line(&mut img, font, line.scale, *x as i32 + 1, top as i32 + offset, text);
+                    }
+                }
+            }
+            trim_image(DynamicImage::ImageRgb8(img), options.white_threshold)
+        })
+        .collect()
+}
+
+/// Turn a trimmed page into white text on black, with the same padding above
+/// the text as below it so the bar is balanced.
+fn highlight_page(page: DynamicImage) -> DynamicImage {
+    let mut bar = RgbImage::from_pixel(page.width(), page.height() + BOTTOM_PADDING, Rgb([255u8, 255, 255]));
+    imageops::replace(&mut bar, &page.to_rgb8(), 0, BOTTOM_PADDING as i64);
+    imageops::invert(&mut bar);
+    DynamicImage::ImageRgb8(bar)
+}
+
+/// Trim blank rows from the top and bottom of an image, keeping 10px padding
+/// at the bottom. A row is blank if every channel of every pixel is at least
+/// `white_threshold`. Port of Python's `trimImage`, which only trimmed the bottom.
+fn trim_image(img: DynamicImage, white_threshold: u8) -> DynamicImage {
     let rgb = img.to_rgb8();
     let (width, height) = rgb.dimensions();
+    let has_content = |y: u32| (0..width).any(|x| rgb.get_pixel(x, y).0.iter().any(|&c| c < white_threshold));
+
+    let first_content_row = (0..height).find(|&y| has_content(y)).unwrap_or(0);
+    let last_content_row = (0..height).rev().find(|&y| has_content(y)).unwrap_or(0);
+
+    // Crop with bottom padding, but don't exceed image height
+    let crop_bottom = (last_content_row + BOTTOM_PADDING + 1).min(height);
+    DynamicImage::ImageRgb8(rgb).crop_imm(0, first_content_row, width, crop_bottom - first_content_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// First monospace font found on this machine (macOS or common Linux paths).
+    fn test_font() -> Option<&'static str> {
+        [
+            "/System/Library/Fonts/Menlo.ttc",
+            "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+        ]
+        .into_iter()
+        .find(|p| std::path::Path::new(p).exists())
+    }
+
+    const DEJAVU_SANS: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+    /// Left-aligned 24px options for `font` with the given line spacing.
+    fn options(font: &str, line_spacing: f32) -> TextOptions {
+        TextOptions {
+            font_path: font.to_string(),
+            face_index: 0,
+            font_size: 24.0,
+            line_spacing,
+            alignment: Alignment::Left,
+            preserve_indent: false,
+            tab_width: TextOptions::DEFAULT_TAB_WIDTH,
+            preformatted: false,
+            timestamp_format: None,
+            markdown: false,
+            left_margin: 0,
+            right_margin: 0,
+            mirror: false,
+            highlight: false,
+            white_threshold: TextOptions::DEFAULT_WHITE_THRESHOLD,
+            baseline_offset: 0,
+            list_style: ListStyle::None,
+        }
+    }
+
+    /// Leftmost column containing ink.
+    fn first_ink_column(img: &DynamicImage) -> Option<u32> {
+        let gray = img.to_luma8();
+        (0..gray.width()).find(|&x| (0..gray.height()).any(|y| gray.get_pixel(x, y)[0] < 128))
+    }
+
+    #[test]
+    fn test_line_spacing_scales_height() {
+        let Some(font) = test_font() else { return };
+        let text = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight";
+
+        let single = &render_text_to_images(text, &options(font, 1.0)).unwrap()[0];
+        let double = &render_text_to_images(text, &options(font, 2.0)).unwrap()[0];
+
+        let ratio = double.height() as f32 / single.height() as f32;
+        assert!((1.7..=2.1).contains(&ratio), "height ratio was {}", ratio);
+    }
+
+    #[test]
+    fn test_estimate_matches_wrapping() {
+        let Some(font) = test_font() else { return };
+        let short = estimate_text("hello world", &options(font, 1.0)).unwrap();
+        assert_eq!((short.words, short.lines), (2, 1));
+
+        let long = estimate_text(&"word ".repeat(100), &options(font, 1.0)).unwrap();
+        assert_eq!(long.words, 100);
+        assert!(long.lines > 1);
+        assert_eq!(long.height_px, long.lines as u32 * short.height_px);
+        assert!((long.length_cm() - long.height_px as f32 / 80.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_advance_cache_keeps_wrapping_unchanged() {
+        let Some(font) = test_font() else { return };
+        let primary = load_font_cached(font, 0).unwrap();
+        let scale = PxScale::from(24.0);
+        // Measure straight from the font, as before the cache existed
+        let uncached = |text: &str| {
+            let scaled = primary.as_scaled(scale);
+            let mut prev = None;
+            text.chars().fold(0.0f32, |width, c| {
+                let id = primary.glyph_id(c);
+                let kern = prev.map_or(0.0, |p| scaled.kern(p, id));
+                prev = Some(id);
+                width + kern + scaled.h_advance(id)
+            })
+        };
+        let stack = FontStack::new(vec![&primary]);
+        let text = "The quick brown fox jumps over the lazy dog. AV To Wa ".repeat(20);
+        for word in text.split(' ') {
+            assert_eq!(measure_text_width(&stack, scale, word), uncached(word));
+        }
+        // Measuring the same text again hits the cache and wraps identically
+        let opts = options(font, 1.0);
+        let first = wrap_lines(&text, &stack, scale, &opts);
+        assert_eq!(wrap_lines(&text, &stack, scale, &opts), first);
+        assert_eq!(wrap_lines(&text, &FontStack::new(vec![&primary]), scale, &opts), first);
+        assert!(first.len() > 1);
+    }
 
-    // Find the last non-white row from the bottom
-    let mut last_content_row = 0u32;
-    for y in 0..height {
-        for x in 0..width {
-            let p = rgb.get_pixel(x, y);
-            if p[0] < 255 || p[1] < 255 || p[2] < 255 {
-                last_content_row = y;
-                break;
+    #[test]
+    fn test_mixed_line_endings() {
+        assert_eq!(normalize_line_endings("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert!(matches!(normalize_line_endings("a\nb"), Cow::Borrowed(_)));
+
+        let Some(font) = test_font() else { return };
+        let mixed = estimate_text("one\r\ntwo\rthree\nfour\r\rsix", &options(font, 1.0)).unwrap();
+        assert_eq!(mixed.lines, 6);
+        let unix = estimate_text("one\ntwo\nthree\nfour\n\nsix", &options(font, 1.0)).unwrap();
+        assert_eq!(mixed, unix);
+    }
+
+    #[test]
+    fn test_empty_text_is_an_error() {
+        let Some(font) = test_font() else { return };
+        for text in ["", "   ", "\n\t \r\n"] {
+            assert_eq!(render_text_to_images(text, &options(font, 1.0)).unwrap_err(), "text is empty");
+            let markdown = TextOptions { markdown: true, ..options(font, 1.0) };
+            assert!(render_text_to_images(text, &markdown).is_err());
+        }
+        // A timestamp header alone is something to print
+        let stamped = TextOptions { timestamp_format: Some("%H:%M".into()), ..options(font, 1.0) };
+        assert_eq!(render_text_to_images(" ", &stamped).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_long_text_split_into_pages() {
+        let Some(font) = test_font() else { return };
+        let text = (1..=400).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+
+        let pages = render_text_to_images(&text, &options(font, 1.0)).unwrap();
+        assert!(pages.len() > 1, "got {} page(s)", pages.len());
+        assert!(pages.iter().all(|p| p.height() <= MAX_PAGE_HEIGHT));
+    }
+
+    #[test]
+    fn test_canvas_sized_to_content() {
+        let Some(font) = test_font() else { return };
+        let primary = load_font_cached(font, 0).unwrap();
+        let stack = FontStack::new(vec![&primary]);
+        let opts = options(font, 1.0);
+        let scale = PxScale::from(opts.font_size);
+        let height = line_height(&primary, scale, 1.0);
+
+        // One line allocates roughly one line of canvas, not a full page
+        let one = draw_plain_page(&[(0.0, "hello".into())], &stack, scale, height, Alignment::Left, &opts);
+        assert_eq!(one.width(), PRINTER_WIDTH);
+        assert!(one.height() <= height + BOTTOM_PADDING, "one line allocated {}px", one.height());
+
+        // Descenders on the last line still fit, and the trimmed page keeps its padding
+        let page = vec![(0.0, "top".to_string()), (0.0, "gjpqy".to_string())];
+        let img = DynamicImage::ImageRgb8(draw_plain_page(&page, &stack, scale, height, Alignment::Left, &opts));
+        let trimmed = trim_image(img.clone(), opts.white_threshold);
+        assert!(trimmed.height() < img.height());
+    }
+
+    #[test]
+    fn test_preserve_indent_keeps_leading_spaces() {
+        assert_eq!(expand_tabs("\tx\ty", 4), "    x   y");
+
+        let Some(font) = test_font() else { return };
+        let data = std::fs::read(font).unwrap();
+        let parsed = FontVec::try_from_vec(data).unwrap();
+        let stack = FontStack::new(vec![&parsed]);
+        let lines = get_wrapped_text_preserving("    let x  = 1;", &stack, PxScale::from(24.0), PRINTER_WIDTH as f32);
+        assert_eq!(lines, vec!["    let x  = 1;"]);
+
+        let flat = &render_text_to_images("    indented", &options(font, 1.0)).unwrap()[0];
+        let kept = &render_text_to_images(
+            "    indented",
+            &TextOptions { preserve_indent: true, ..options(font, 1.0) },
+        )
+        .unwrap()[0];
+        let (flat_x, kept_x) = (first_ink_column(flat).unwrap(), first_ink_column(kept).unwrap());
+        assert!(kept_x > flat_x + 20, "indented text starts at {} vs {}", kept_x, flat_x);
+    }
+
+    #[test]
+    fn test_long_word_broken_to_fit_width() {
+        let Some(font) = test_font() else { return };
+        let parsed = FontVec::try_from_vec(std::fs::read(font).unwrap()).unwrap();
+        let stack = FontStack::new(vec![&parsed]);
+        let scale = PxScale::from(48.0);
+        let word = "https://example.com/".repeat(3);
+        assert_eq!(word.len(), 60);
+
+        let text = format!("see {} now", word);
+        let wrapped = get_wrapped_text(&text, &stack, scale, PRINTER_WIDTH as f32);
+        let preserved = get_wrapped_text_preserving(&format!("  {}", text), &stack, scale, PRINTER_WIDTH as f32);
+        for lines in [wrapped.lines().map(str::to_string).collect::<Vec<_>>(), preserved] {
+            assert!(lines.len() > 3);
+            for line in &lines {
+                let width = measure_text_width(&stack, scale, line);
+                assert!(width <= PRINTER_WIDTH as f32, "{:?} is {}px wide", line, width);
+            }
+            // Nothing lost in the breaking
+            assert_eq!(lines.concat().replace(' ', ""), text.replace(' ', ""));
+        }
+    }
+
+    #[test]
+    fn test_font_stack_falls_back_for_missing_glyphs() {
+        let (Some(mono), true) = (test_font(), std::path::Path::new(DEJAVU_SANS).exists()) else { return };
+        let mono = FontVec::try_from_vec(std::fs::read(mono).unwrap()).unwrap();
+        let sans = FontVec::try_from_vec(std::fs::read(DEJAVU_SANS).unwrap()).unwrap();
+        let stack = FontStack::new(vec![&mono, &sans]);
+
+        assert_eq!(stack.glyph('A').0, 0);
+        // Any visible character only the second font covers is taken from it
+        let only_in_sans = |c: char| {
+            let id = sans.glyph_id(c);
+            mono.glyph_id(c).0 == 0 && id.0 != 0 && sans.h_advance_unscaled(id) > 0.0
+        };
+        let Some(c) = ('\u{2000}'..'\u{3000}').find(|&c| only_in_sans(c)) else { return };
+        assert_eq!(stack.glyph(c).0, 1);
+        assert!(measure_text_width(&stack, PxScale::from(24.0), &c.to_string()) > 0.0);
+        // Characters nobody covers stay on the primary font
+        assert_eq!(stack.glyph('\u{10FFFD}'), (0, GlyphId(0)));
+    }
+
+    #[test]
+    fn test_line_x_alignment() {
+        let full = (0.0, PRINTER_WIDTH as f32);
+        assert_eq!(line_x(100.0, Alignment::Left, full), 0);
+        assert_eq!(line_x(100.0, Alignment::Center, full), 142);
+        assert_eq!(line_x(100.0, Alignment::Right, full), 284);
+        // Overlong lines never start left of the paper edge
+        assert_eq!(line_x(500.0, Alignment::Center, full), 0);
+        assert_eq!(line_x(500.0, Alignment::Right, full), 0);
+        // Margins shift the area: 20px left, 44px right leaves 320px
+        let area = TextOptions { left_margin: 20, right_margin: 44, ..options("", 1.0) }.text_area();
+        assert_eq!(area, (20.0, 320.0));
+        assert_eq!(line_x(100.0, Alignment::Left, area), 20);
+        assert_eq!(line_x(100.0, Alignment::Right, area), 240);
+        assert_eq!(line_x(500.0, Alignment::Right, area), 20);
+    }
+
+    #[test]
+    fn test_timestamp_format_validation() {
+        use chrono::TimeZone;
+        let now = Local.with_ymd_and_hms(2024, 3, 5, 9, 7, 0).unwrap();
+        assert!(is_valid_timestamp_format("%A %d %B"));
+        assert!(!is_valid_timestamp_format("%Q"));
+        assert!(!is_valid_timestamp_format("  "));
+        assert_eq!(timestamp_line("%d/%m/%Y", now), "05/03/2024");
+        // Invalid formats fall back to the default instead of panicking
+        assert_eq!(timestamp_line("%Q", now), "2024-03-05 09:07");
+    }
+
+    #[test]
+    fn test_parse_markdown_blocks() {
+        let blocks = parse_markdown("# Title\n\nSome **bold** text,\nwrapped.\n\n- one\n- two\n\n---\n\n1. first");
+        fn words(block: &MdBlock) -> Vec<(&str, bool, bool)> {
+            match block {
+                MdBlock::Text { words, .. } => words.iter().map(|w| (w.text.as_str(), w.bold, w.space_before)).collect(),
+                MdBlock::Rule => Vec::new(),
             }
         }
+        assert_eq!(blocks.len(), 6);
+        assert!(matches!(&blocks[0], MdBlock::Text { heading: 1, .. }));
+        assert_eq!(
+            words(&blocks[1]),
+            vec![("Some", false, false), ("bold", true, true), ("text,", false, true), ("wrapped.", false, true)]
+        );
+        assert!(matches!(&blocks[2], MdBlock::Text { marker: Some(m), depth: 1, .. } if m == "•"));
+        assert!(matches!(&blocks[3], MdBlock::Text { marker: Some(m), .. } if m == "•"));
+        assert_eq!(blocks[4], MdBlock::Rule);
+        assert!(matches!(&blocks[5], MdBlock::Text { marker: Some(m), .. } if m == "1."));
+    }
+
+    #[test]
+    fn test_markdown_layout_wraps_and_scales_headings() {
+        let Some(font) = test_font() else { return };
+        let parsed = FontVec::try_from_vec(std::fs::read(font).unwrap()).unwrap();
+        let stack = FontStack::new(vec![&parsed]);
+        let opts = TextOptions { markdown: true, ..options(font, 1.0) };
+        let text = format!("# Heading\n\n- {}", "word ".repeat(40));
+        let lines = layout_markdown(&parse_markdown(&text), &stack, PxScale::from(24.0), &opts);
+
+        assert!(lines[0].height > lines[1].height);
+        assert!(lines.len() > 2, "bullet text should wrap");
+        // Continuation lines hang under the text, not the bullet
+        let hanging = lines[1].runs[1].0;
+        assert_eq!(lines[2].runs[0].0, hanging);
+        assert!(lines.iter().flat_map(|l| &l.runs).all(|(x, t, _)| x + measure_text_width(&stack, PxScale::from(24.0), t) <= PRINTER_WIDTH as f32 + 0.5));
+
+        let pages = render_text_to_images(&text, &opts).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(estimate_text(&text, &opts).unwrap().lines, lines.len());
+    }
+
+    #[test]
+    fn test_margins_keep_text_inside() {
+        let Some(font) = test_font() else { return };
+        let opts = TextOptions { left_margin: 40, right_margin: 60, ..options(font, 1.0) };
+        let page = &render_text_to_images(&"wrapping words ".repeat(20), &opts).unwrap()[0];
+        assert_eq!(page.width(), PRINTER_WIDTH);
+        assert!(first_ink_column(page).unwrap() >= 40);
+        let gray = page.to_luma8();
+        let last_ink = (0..gray.width()).rev().find(|&x| (0..gray.height()).any(|y| gray.get_pixel(x, y)[0] < 128));
+        assert!(last_ink.unwrap() < PRINTER_WIDTH - 60, "ink at column {:?}", last_ink);
+    }
+
+    #[test]
+    fn test_trim_ignores_near_white_specks() {
+        let mut img = RgbImage::from_pixel(PRINTER_WIDTH, 200, Rgb([255, 255, 255]));
+        for y in 50..60 {
+            img.put_pixel(10, y, Rgb([0, 0, 0]));
+        }
+        img.put_pixel(300, 180, Rgb([252, 252, 252]));
+        let img = DynamicImage::ImageRgb8(img);
+
+        // Top rows above the ink are cut, the speck doesn't count as content
+        let trimmed = trim_image(img.clone(), TextOptions::DEFAULT_WHITE_THRESHOLD);
+        assert_eq!(trimmed.height(), 10 + 10);
+        assert_eq!(trimmed.to_luma8().get_pixel(10, 0)[0], 0);
+        // With only pure white as blank, the speck keeps the bottom
+        assert_eq!(trim_image(img, 255).height(), 180 + 11 - 50);
+    }
+
+    #[test]
+    fn test_baseline_offset() {
+        let Some(font) = test_font() else { return };
+        let height = |offset| {
+            render_text_to_images("Hi", &TextOptions { baseline_offset: offset, ..options(font, 1.0) }).unwrap()[0].height()
+        };
+        // Moving down only adds blank rows, which are trimmed; moving up clips the tops
+        assert_eq!(height(8), height(0));
+        assert!(height(-12) < height(0));
+    }
+
+    #[test]
+    fn test_list_mode_hanging_indent() {
+        let Some(font) = test_font() else { return };
+        let primary = load_font_cached(font, 0).unwrap();
+        let stack = FontStack::with_fallbacks(&primary);
+        let scale = PxScale::from(24.0);
+        let opts = TextOptions { list_style: ListStyle::Numbers, ..options(font, 1.0) };
+
+        let text = format!("milk\n\n{}\neggs", "bread ".repeat(20));
+        let lines = wrap_lines(&text, &stack, scale, &opts);
+        assert_eq!(lines[0], (0.0, "1. milk".to_string()));
+        assert_eq!(lines[1], (0.0, String::new()));
+        assert!(lines[2].1.starts_with("2. bread"));
+        // Continuation lines hang under the text, not the number
+        let hanging = measure_text_width(&stack, scale, "2. ");
+        assert!(lines[3].0 == hanging && lines[3].1.starts_with("bread"));
+        assert_eq!(lines.last().unwrap(), &(0.0, "3. eggs".to_string()));
+
+        let bullets = wrap_lines("a\nb", &stack, scale, &TextOptions { list_style: ListStyle::Bullets, ..opts });
+        assert_eq!(bullets, vec![(0.0, "• a".to_string()), (0.0, "• b".to_string())]);
+    }
+
+    #[test]
+    fn test_preformatted_keeps_lines_and_shrinks_to_fit() {
+        let Some(font) = test_font() else { return };
+        let primary = load_font_cached(font, 0).unwrap();
+        let stack = FontStack::with_fallbacks(&primary);
+        let scale = PxScale::from(24.0);
+        let opts = TextOptions { preformatted: true, ..options(font, 1.0) };
+
+        // Short rows keep the size and their spacing; tabs become columns
+        let (fitted, lines) = layout_plain("a\tb\nccc  d", &stack, scale, &opts);
+        assert_eq!(fitted, scale);
+        assert_eq!(lines, vec![(0.0, "a   b".to_string()), (0.0, "ccc  d".to_string())]);
+
+        // A row too wide for the paper shrinks the block instead of wrapping
+        let wide = format!("{}\nshort", "x".repeat(60));
+        let (fitted, lines) = layout_plain(&wide, &stack, scale, &opts);
+        assert_eq!(lines.len(), 2);
+        assert!(fitted.y < scale.y);
+        assert!(measure_text_width(&stack, fitted, &lines[0].1) <= PRINTER_WIDTH as f32 + 0.5);
+        assert_eq!(estimate_text(&wide, &opts).unwrap().lines, 2);
+    }
+
+    #[test]
+    fn test_highlight_draws_white_text_on_black() {
+        let Some(font) = test_font() else { return };
+        let plain = &render_text_to_images("Hi", &options(font, 1.0)).unwrap()[0];
+        let bar = &render_text_to_images("Hi", &TextOptions { highlight: true, ..options(font, 1.0) }).unwrap()[0];
+        assert_eq!(bar.width(), PRINTER_WIDTH);
+        assert_eq!(bar.height(), plain.height() + BOTTOM_PADDING);
+
+        // The background is solid black edge to edge, with white glyphs in it
+        let gray = bar.to_luma8();
+        for (x, y) in [(0, 0), (PRINTER_WIDTH - 1, 0), (0, gray.height() - 1), (PRINTER_WIDTH - 1, gray.height() - 1)] {
+            assert_eq!(gray.get_pixel(x, y)[0], 0);
+        }
+        let white = gray.pixels().filter(|p| p[0] > 128).count();
+        assert!(white > 0 && white < gray.pixels().count() / 4, "{} white pixels", white);
     }
 
-    // Crop with 10px bottom padding, but don't exceed image height
-    let crop_height = (last_content_row + 10 + 1).min(height);
-    DynamicImage::ImageRgb8(rgb).crop_imm(0, 0, width, crop_height)
+    #[test]
+    fn test_mirror_flips_pages() {
+        let Some(font) = test_font() else { return };
+        let plain = &render_text_to_images("Hi", &options(font, 1.0)).unwrap()[0];
+        let mirrored = &render_text_to_images("Hi", &TextOptions { mirror: true, ..options(font, 1.0) }).unwrap()[0];
+        assert_eq!((plain.width(), plain.height()), (mirrored.width(), mirrored.height()));
+        // Left-aligned ink ends up against the right edge
+        let left = first_ink_column(plain).unwrap();
+        let gray = mirrored.to_luma8();
+        let last_ink = (0..gray.width()).rev().find(|&x| (0..gray.height()).any(|y| gray.get_pixel(x, y)[0] < 128));
+        assert_eq!(last_ink, Some(PRINTER_WIDTH - 1 - left));
+    }
 }
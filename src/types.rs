@@ -1,20 +1,56 @@
 use image::DynamicImage;
-use std::sync::{OnceLock, Mutex};
-use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, Mutex};
+use std::time::SystemTime;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
 
+use crate::barcode::Symbology;
+use crate::escpos::RasterOptions;
+use crate::jobfile::JobFile;
+use crate::printer::PrintTiming;
+use crate::qr::QrEcLevel;
+use crate::text_render::TextOptions;
+
 // BLE UUIDs
+/// Transparent-UART service the printer advertises; holds both characteristics.
+pub const SERVICE_UUID: &str = "49535343-fe7d-4ae5-8fa9-9fafd205e455";
 pub const WRITE_CHAR_UUID: &str = "49535343-8841-43f4-a8d4-ecbe34729bb3";
 pub const NOTIFY_CHAR_UUID: &str = "49535343-1e4d-4bd9-ba61-23c647249616";
 
 // Printer configuration
 pub const PRINTER_WIDTH: u32 = 384;
-pub const CHUNK_SIZE: usize = 182; // Conservative MTU-3 on macOS (btleplug doesn't expose MTU)
+/// Print resolution: 203 dpi ≈ 8 dots per millimetre.
+pub const DOTS_PER_MM: f32 = 8.0;
+/// Images taller than this (after scaling) need an explicit confirmation to print.
+pub const TALL_IMAGE_PX: u32 = 3000;
+pub const CHUNK_SIZE: usize = 182; // Fallback write size: conservative MTU-3 on macOS (btleplug doesn't expose MTU)
 
-// LiPo voltage range for the CTP500 battery
+// Default LiPo voltage range for the CTP500 battery (see BatteryCurve)
 pub const BATT_MIN_MV: u32 = 3300; // 0%
 pub const BATT_MAX_MV: u32 = 4200; // 100%
+/// Below this charge the UI warns that prints may brown out the printer.
+pub const DEFAULT_LOW_BATTERY_PCT: u8 = 10;
+
+/// Typical single-cell LiPo discharge curve as (mV, %) on the default
+/// BATT_MIN_MV..BATT_MAX_MV range; rescaled for other ranges.
+const LIPO_CURVE: [(u32, f64); 12] = [
+    (3300, 0.0),
+    (3600, 5.0),
+    (3690, 10.0),
+    (3730, 20.0),
+    (3770, 30.0),
+    (3800, 40.0),
+    (3840, 50.0),
+    (3870, 60.0),
+    (3950, 70.0),
+    (4020, 80.0),
+    (4110, 90.0),
+    (4200, 100.0),
+];
 
 // Printer name regex: matches "S Blue Printer", "S Pink Printer", etc.
 static PRINTER_NAME_RE: OnceLock<Regex> = OnceLock::new();
@@ -24,6 +60,16 @@ pub fn printer_name_regex() -> &'static Regex {
     })
 }
 
+/// Compile a user-supplied printer name pattern (plain substring or regex),
+/// matched case-insensitively. An empty pattern yields the built-in regex.
+pub fn compile_printer_name_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Ok(printer_name_regex().clone());
+    }
+    Regex::new(&format!("(?i){}", pattern))
+}
+
 // Battery voltage regex: matches "VOLT=4000mv"
 static BATTERY_RE: OnceLock<Regex> = OnceLock::new();
 pub fn battery_regex() -> &'static Regex {
@@ -32,67 +78,328 @@ pub fn battery_regex() -> &'static Regex {
     })
 }
 
-/// Parse battery percentage from printer status response.
+/// Parse the battery voltage in millivolts from printer status response.
 /// Response format: "HV=V1.0A,SV=V1.01,VOLT=4000mv,DPI=384,"
-/// Returns 0-100 or None if not found.
-pub fn parse_battery(data: &[u8]) -> Option<u8> {
+/// Returns None if not found; see BatteryCurve for the percentage.
+pub fn parse_battery_mv(data: &[u8]) -> Option<u32> {
     let text = String::from_utf8_lossy(data);
     let caps = battery_regex().captures(&text)?;
-    let mv: u32 = caps[1].parse().ok()?;
-    let pct = ((mv.saturating_sub(BATT_MIN_MV)) as f64
-        / (BATT_MAX_MV - BATT_MIN_MV) as f64
-        * 100.0) as i32;
-    Some(pct.clamp(0, 100) as u8)
+    caps[1].parse().ok()
+}
+
+/// How battery voltage maps to a charge percentage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BatteryMode {
+    /// Straight line from empty to full.
+    #[default]
+    Linear,
+    /// Follow a typical LiPo discharge curve, which is flat in the middle.
+    LipoCurve,
+}
+
+impl BatteryMode {
+    pub const ALL: [BatteryMode; 2] = [BatteryMode::Linear, BatteryMode::LipoCurve];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BatteryMode::Linear => "Linear",
+            BatteryMode::LipoCurve => "LiPo curve",
+        }
+    }
+}
+
+/// Battery calibration: the voltages read as empty and full, and the curve
+/// between them. Adjustable for clones with different battery packs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatteryCurve {
+    pub mode: BatteryMode,
+    pub min_mv: u32,
+    pub max_mv: u32,
+}
+
+impl Default for BatteryCurve {
+    fn default() -> Self {
+        BatteryCurve { mode: BatteryMode::default(), min_mv: BATT_MIN_MV, max_mv: BATT_MAX_MV }
+    }
+}
+
+impl BatteryCurve {
+    /// Charge percentage (0-100) for a battery voltage in millivolts.
+    pub fn percent(&self, mv: u32) -> u8 {
+        if self.max_mv <= self.min_mv {
+            return if mv >= self.max_mv { 100 } else { 0 };
+        }
+        // Position within the empty..full range, 0.0..=1.0
+        let pos = (mv.saturating_sub(self.min_mv) as f64 / (self.max_mv - self.min_mv) as f64).min(1.0);
+        let pct = match self.mode {
+            BatteryMode::Linear => pos * 100.0,
+            BatteryMode::LipoCurve => {
+                let mv = BATT_MIN_MV as f64 + pos * (BATT_MAX_MV - BATT_MIN_MV) as f64;
+                LIPO_CURVE
+                    .windows(2)
+                    .find(|w| mv <= w[1].0 as f64)
+                    .map_or(100.0, |w| {
+                        let ((v0, p0), (v1, p1)) = (w[0], w[1]);
+                        p0 + (mv - v0 as f64) / (v1 - v0) as f64 * (p1 - p0)
+                    })
+            }
+        };
+        pct.clamp(0.0, 100.0) as u8
+    }
+}
+
+/// Hardware/firmware details reported in the printer status response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PrinterInfo {
+    pub hw_version: Option<String>,
+    pub sw_version: Option<String>,
+    pub dpi: Option<u32>,
+}
+
+/// Parse `HV=`, `SV=`, and `DPI=` fields from a printer status response.
+/// Response format: "HV=V1.0A,SV=V1.01,VOLT=4000mv,DPI=384,"
+/// Returns None if none of the fields are present.
+pub fn parse_printer_info(data: &[u8]) -> Option<PrinterInfo> {
+    let text = String::from_utf8_lossy(data);
+    let mut info = PrinterInfo::default();
+    for (key, value) in text.split(',').filter_map(|field| field.trim().split_once('=')) {
+        match key {
+            "HV" => info.hw_version = Some(value.to_string()),
+            "SV" => info.sw_version = Some(value.to_string()),
+            "DPI" => info.dpi = value.parse().ok(),
+            _ => {}
+        }
+    }
+    (info != PrinterInfo::default()).then_some(info)
+}
+
+/// Totals for a finished print, carried by `AppEvent::PrintComplete`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PrintSummary {
+    /// Raster bytes sent (header and image data).
+    pub bytes: usize,
+    /// Printed size in dots.
+    pub width: u32,
+    pub height: u32,
+    /// Time from the first write until the end sequence was sent.
+    pub elapsed_ms: u64,
+}
+
+impl PrintSummary {
+    /// Paper used by the image itself, excluding the feed afterwards.
+    pub fn paper_mm(&self) -> f32 {
+        self.height as f32 / DOTS_PER_MM
+    }
+}
+
+impl std::fmt::Display for PrintSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} bytes, {}x{} dots, {:.1} cm of paper, {:.1} s",
+            self.bytes,
+            self.width,
+            self.height,
+            self.paper_mm() / 10.0,
+            self.elapsed_ms as f32 / 1000.0
+        )
+    }
+}
+
+/// Printer condition reported in status notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrinterStatus {
+    #[default]
+    Ready,
+    PaperOut,
+    CoverOpen,
+    OverTemperature,
+}
+
+impl PrinterStatus {
+    pub fn is_fault(self) -> bool {
+        self != PrinterStatus::Ready
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PrinterStatus::Ready => "Ready",
+            PrinterStatus::PaperOut => "Out of paper",
+            PrinterStatus::CoverOpen => "Cover open",
+            PrinterStatus::OverTemperature => "Print head overheated",
+        }
+    }
+}
+
+/// Substrings (upper-case) that may report fault conditions. These are a best
+/// guess: no captured payload or firmware source shows how the printer
+/// reports a fault, and the only format seen so far is the one parsed by
+/// `parse_printer_info`. Payloads `is_known_status` rejects are logged so real
+/// fault strings can be collected and this list corrected.
+const FAULT_PATTERNS: &[(&str, PrinterStatus)] = &[
+    ("NO PAPER", PrinterStatus::PaperOut),
+    ("PAPER OUT", PrinterStatus::PaperOut),
+    ("PAPER=0", PrinterStatus::PaperOut),
+    ("COVER OPEN", PrinterStatus::CoverOpen),
+    ("OVERHEAT", PrinterStatus::OverTemperature),
+    ("TEMP HIGH", PrinterStatus::OverTemperature),
+    ("HOT=1", PrinterStatus::OverTemperature),
+];
+
+/// Fields of the status format seen from real printers, e.g.
+/// "HV=V1.0A,SV=V1.01,VOLT=4000mv,DPI=384,".
+const KNOWN_STATUS_FIELDS: &[&str] = &["HV", "SV", "VOLT", "DPI"];
+
+/// Whether a status response holds only fields of the known format.
+pub fn is_known_status(data: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(data);
+    let mut fields = text.split(',').map(str::trim).filter(|field| !field.is_empty()).peekable();
+    fields.peek().is_some()
+        && fields.all(|field| field.split_once('=').is_some_and(|(key, _)| KNOWN_STATUS_FIELDS.contains(&key)))
+}
+
+/// Parse the fault state from a printer status response (case-insensitive).
+/// Any payload without a known fault substring means the printer is ready.
+pub fn parse_printer_status(data: &[u8]) -> PrinterStatus {
+    let text = String::from_utf8_lossy(data).to_uppercase();
+    FAULT_PATTERNS
+        .iter()
+        .find(|(pattern, _)| text.contains(pattern))
+        .map(|&(_, status)| status)
+        .unwrap_or_default()
 }
 
 // ── Font choices available to the user ────────────────────────────────────────
 
 /// A monospace font available for text printing.
+#[derive(Debug, Clone, PartialEq)]
 pub struct FontChoice {
     /// Display label shown in the selector.
-    pub label: &'static str,
+    pub label: Cow<'static, str>,
     /// Absolute path to the font file on disk (loaded by ab_glyph + WebView @font-face).
-    pub path: &'static str,
+    pub path: Cow<'static, str>,
     /// CSS font-family value used in the textarea (must match the @font-face family name).
-    pub css_family: &'static str,
+    pub css_family: Cow<'static, str>,
 }
 
-/// All monospace fonts offered in the UI, in display order.
+impl FontChoice {
+    /// A built-in font whose @font-face rule lives in the app's static CSS.
+    const fn system(label: &'static str, path: &'static str, css_family: &'static str) -> Self {
+        Self { label: Cow::Borrowed(label), path: Cow::Borrowed(path), css_family: Cow::Borrowed(css_family) }
+    }
+
+    /// A user-picked font file. Fails if ab_glyph can't parse it, so a broken
+    /// file never reaches the selector.
+    pub fn from_file(path: &Path, css_family: String) -> Result<Self, String> {
+        load_font(&path.to_string_lossy(), 0)?;
+        let label = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        Ok(Self {
+            label: Cow::Owned(label),
+            path: Cow::Owned(path.to_string_lossy().into_owned()),
+            css_family: Cow::Owned(css_family),
+        })
+    }
+}
+
+/// Built-in monospace fonts offered in the UI, in display order.
 pub const FONT_CHOICES: &[FontChoice] = &[
-    FontChoice { label: "Menlo",          path: "/System/Library/Fonts/Menlo.ttc",                              css_family: "MenloPrinter" },
-    FontChoice { label: "Monaco",         path: "/System/Library/Fonts/Monaco.ttf",                             css_family: "MonacoPrinter" },
-    FontChoice { label: "SF Mono",        path: "/System/Library/Fonts/SFNSMono.ttf",                           css_family: "SFMonoPrinter" },
-    FontChoice { label: "PT Mono",        path: "/System/Library/Fonts/Supplemental/PTMono.ttc",               css_family: "PTMonoPrinter" },
-    FontChoice { label: "Courier New",    path: "/System/Library/Fonts/Supplemental/Courier New.ttf",           css_family: "CourierNewPrinter" },
-    FontChoice { label: "JetBrains Mono", path: "/Users/quintonpham/Library/Fonts/JetBrainsMonoNerdFont-Regular.ttf", css_family: "JetBrainsMonoPrinter" },
-    FontChoice { label: "Fira Code",      path: "/Users/quintonpham/Library/Fonts/FiraCodeNerdFont-Regular.ttf",     css_family: "FiraCodePrinter" },
+    FontChoice::system("Menlo",          "/System/Library/Fonts/Menlo.ttc",                              "MenloPrinter"),
+    FontChoice::system("Monaco",         "/System/Library/Fonts/Monaco.ttf",                             "MonacoPrinter"),
+    FontChoice::system("SF Mono",        "/System/Library/Fonts/SFNSMono.ttf",                           "SFMonoPrinter"),
+    FontChoice::system("PT Mono",        "/System/Library/Fonts/Supplemental/PTMono.ttc",               "PTMonoPrinter"),
+    FontChoice::system("Courier New",    "/System/Library/Fonts/Supplemental/Courier New.ttf",           "CourierNewPrinter"),
+    FontChoice::system("JetBrains Mono", "/Users/quintonpham/Library/Fonts/JetBrainsMonoNerdFont-Regular.ttf", "JetBrainsMonoPrinter"),
+    FontChoice::system("Fira Code",      "/Users/quintonpham/Library/Fonts/FiraCodeNerdFont-Regular.ttf",     "FiraCodePrinter"),
 ];
 
-// Cache of loaded FontVec keyed by font path, so we don't re-read from disk on every render.
-static FONT_CACHE: OnceLock<Mutex<HashMap<&'static str, FontVec>>> = OnceLock::new();
+/// Fonts consulted, in order, for characters the chosen font has no glyph for
+/// (CJK, symbols, monochrome emoji). Missing files are skipped.
+pub const FALLBACK_FONT_PATHS: &[&str] = &[
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/System/Library/Fonts/Apple Symbols.ttf",
+    "/Library/Fonts/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/noto/NotoEmoji-Regular.ttf",
+];
 
-fn font_cache() -> &'static Mutex<HashMap<&'static str, FontVec>> {
+/// Read and parse a TrueType/OpenType font (`.ttf`, `.ttc`, `.otf`).
+/// `face_index` selects the face within a `.ttc` collection (0 otherwise).
+///
+/// ab_glyph reads both TrueType and CFF outlines, so OTF files load the same
+/// way as TTF. The error names the file and, for OTF, the likely cause.
+pub fn load_font(path: &str, face_index: u32) -> Result<FontVec, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read font {}: {}", path, e))?;
+    FontVec::try_from_vec_and_index(data, face_index).map_err(|e| {
+        let is_otf = Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("otf"));
+        if is_otf {
+            format!("Not a usable OpenType font ({}): {} — CFF2/variable OTF fonts aren't supported", path, e)
+        } else {
+            format!("Not a usable font ({}): {}", path, e)
+        }
+    })
+}
+
+/// Number of faces in a font file: the `ttcf` header count for collections,
+/// 1 for single-face fonts or files that can't be read.
+pub fn font_face_count(path: &str) -> u32 {
+    use std::io::Read;
+    let mut header = [0u8; 12];
+    let read = std::fs::File::open(path).and_then(|mut f| f.read_exact(&mut header));
+    if read.is_err() || &header[..4] != b"ttcf" {
+        return 1;
+    }
+    u32::from_be_bytes([header[8], header[9], header[10], header[11]]).max(1)
+}
+
+/// A parsed font and the modification time of the file it came from.
+struct CachedFont {
+    modified: Option<SystemTime>,
+    font: Arc<FontVec>,
+}
+
+// Cache of loaded FontVec keyed by font path and face index, so we don't re-read from disk on every render.
+static FONT_CACHE: OnceLock<Mutex<HashMap<(String, u32), CachedFont>>> = OnceLock::new();
+
+fn font_cache() -> &'static Mutex<HashMap<(String, u32), CachedFont>> {
     FONT_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
+/// `load_font`, but parsed once and shared: later calls for the same path and
+/// face return the cached font unless the file's modification time changed.
+pub fn load_font_cached(path: &str, face_index: u32) -> Result<Arc<FontVec>, String> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let key = (path.to_string(), face_index);
+    if let Some(cached) = font_cache().lock().unwrap().get(&key) {
+        if cached.modified == modified {
+            return Ok(cached.font.clone());
+        }
+    }
+    // Parse outside the lock; a racing load of the same font just wins last
+    let font = Arc::new(load_font(path, face_index)?);
+    font_cache().lock().unwrap().insert(key, CachedFont { modified, font: font.clone() });
+    Ok(font)
+}
+
 /// Compute the number of characters that fit across PRINTER_WIDTH pixels for
 /// a given font file and point size.  Uses the same ab_glyph `h_advance` path
 /// as `text_render::get_wrapped_text` so the textarea width exactly matches
 /// what will be printed on the 384px-wide printer.
 ///
-/// The font file is read once and cached; subsequent calls with the same path
-/// only pay the cost of a lock + glyph advance lookup.
-pub fn chars_per_line(font_path: &'static str, font_size: f32) -> u32 {
-    let mut cache = font_cache().lock().unwrap();
-    if !cache.contains_key(font_path) {
-        match std::fs::read(font_path).and_then(|d| {
-            FontVec::try_from_vec(d).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
-        }) {
-            Ok(font) => { cache.insert(font_path, font); }
-            Err(_) => return 26, // fallback: Menlo@28px measured value
-        }
-    }
-    let font = &cache[font_path];
+/// The font file is read once per face and cached (see `load_font_cached`);
+/// subsequent calls only pay the cost of a lock + glyph advance lookup.
+pub fn chars_per_line(font_path: &str, face_index: u32, font_size: f32) -> u32 {
+    let font = match load_font_cached(font_path, face_index) {
+        Ok(font) => font,
+        Err(e) => {
+            log::warn!("{}", e);
+            return 26; // fallback: Menlo@28px measured value
+        }
+    };
     let scale = PxScale::from(font_size);
     let scaled = font.as_scaled(scale);
     // '0' is the reference glyph for the CSS `ch` unit — use it so the
@@ -103,26 +410,592 @@ pub fn chars_per_line(font_path: &'static str, font_size: f32) -> u32 {
     (PRINTER_WIDTH as f32 / advance).floor() as u32
 }
 
+/// Horizontal placement of each rendered text line within PRINTER_WIDTH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    pub const ALL: [Alignment; 3] = [Alignment::Left, Alignment::Center, Alignment::Right];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Alignment::Left => "Left",
+            Alignment::Center => "Center",
+            Alignment::Right => "Right",
+        }
+    }
+}
+
+/// Marker put in front of each non-empty line of plain text in list mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ListStyle {
+    #[default]
+    None,
+    Bullets,
+    Numbers,
+}
+
+impl ListStyle {
+    pub const ALL: [ListStyle; 3] = [ListStyle::None, ListStyle::Bullets, ListStyle::Numbers];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ListStyle::None => "None",
+            ListStyle::Bullets => "Bullets",
+            ListStyle::Numbers => "Numbers",
+        }
+    }
+
+    /// Marker for the `n`th item (1-based), including the space after it.
+    pub fn marker(self, n: usize) -> Option<String> {
+        match self {
+            ListStyle::None => None,
+            ListStyle::Bullets => Some("• ".to_string()),
+            ListStyle::Numbers => Some(format!("{}. ", n)),
+        }
+    }
+}
+
+/// Severity of an activity-log entry. Ordered so the log filter can hide
+/// everything below the chosen level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Protocol chatter (sequences sent, scan hits, status notifications).
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "Debug",
+            LogLevel::Info => "Info",
+            LogLevel::Warn => "Warning",
+            LogLevel::Error => "Error",
+        }
+    }
+
+    /// CSS class used to color the entry in the log box.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "log-debug",
+            LogLevel::Info => "log-info",
+            LogLevel::Warn => "log-warn",
+            LogLevel::Error => "log-error",
+        }
+    }
+}
+
+/// Region of an image to print, in normalized (0.0–1.0) coordinates so it
+/// survives preview scaling. The default covers the whole image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Default for CropRect {
+    fn default() -> Self {
+        Self { x: 0.0, y: 0.0, w: 1.0, h: 1.0 }
+    }
+}
+
+impl CropRect {
+    /// Rectangle spanning two corner points (in any order), clamped to the image.
+    pub fn from_points(a: (f32, f32), b: (f32, f32)) -> Self {
+        let (x0, x1) = (a.0.min(b.0).clamp(0.0, 1.0), a.0.max(b.0).clamp(0.0, 1.0));
+        let (y0, y1) = (a.1.min(b.1).clamp(0.0, 1.0), a.1.max(b.1).clamp(0.0, 1.0));
+        Self { x: x0, y: y0, w: x1 - x0, h: y1 - y0 }
+    }
+
+    pub fn is_full(self) -> bool {
+        self == Self::default()
+    }
+
+    /// Pixel rectangle `(x, y, w, h)` for an image of the given size, clamped to
+    /// its bounds and at least 1×1 so the crop is never empty.
+    pub fn to_pixels(self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let x = ((self.x.clamp(0.0, 1.0) * width as f32) as u32).min(width.saturating_sub(1));
+        let y = ((self.y.clamp(0.0, 1.0) * height as f32) as u32).min(height.saturating_sub(1));
+        let w = ((self.w.max(0.0) * width as f32).round() as u32).clamp(1, width - x);
+        let h = ((self.h.max(0.0) * height as f32).round() as u32).clamp(1, height - y);
+        (x, y, w, h)
+    }
+
+    /// Crop `img` to this rectangle (a full rect returns the same shared image).
+    pub fn apply(self, img: Arc<DynamicImage>) -> Arc<DynamicImage> {
+        if self.is_full() || img.width() == 0 || img.height() == 0 {
+            return img;
+        }
+        let (x, y, w, h) = self.to_pixels(img.width(), img.height());
+        Arc::new(img.crop_imm(x, y, w, h))
+    }
+}
+
+/// Bounded undo stack of earlier text-box contents, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextHistory {
+    entries: VecDeque<String>,
+}
+
+impl TextHistory {
+    /// Most undo steps kept; older ones are dropped.
+    pub const LIMIT: usize = 50;
+
+    /// Record `previous` as an undo point. Repeats of the latest entry are skipped.
+    pub fn push(&mut self, previous: String) {
+        if self.entries.back() == Some(&previous) {
+            return;
+        }
+        if self.entries.len() == Self::LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(previous);
+    }
+
+    /// Take the most recent undo point, if any.
+    pub fn undo(&mut self) -> Option<String> {
+        self.entries.pop_back()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.entries.is_empty()
+    }
+}
+
+/// A matching printer seen while scanning.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiscoveredDevice {
+    /// Platform peripheral id, as sent back in `BleCommand::ConnectDevice`.
+    pub id: String,
+    pub name: String,
+    /// Signal strength in dBm (closer to 0 is stronger), if reported.
+    pub rssi: Option<i16>,
+}
+
+/// Insert or refresh `device` in `devices`, keeping the list sorted
+/// strongest-signal first (devices without an RSSI go last).
+pub fn upsert_device(devices: &mut Vec<DiscoveredDevice>, device: DiscoveredDevice) {
+    match devices.iter_mut().find(|d| d.id == device.id) {
+        Some(existing) => *existing = device,
+        None => devices.push(device),
+    }
+    devices.sort_by_key(|d| std::cmp::Reverse(d.rssi.unwrap_or(i16::MIN)));
+}
+
+/// Whether a user-typed address names the peripheral with this platform `id`
+/// or MAC `address`. Case and separators are ignored, so "aa:bb:cc:dd:ee:ff",
+/// "AA-BB-CC-DD-EE-FF" and a macOS UUID with or without dashes all match.
+pub fn address_matches(input: &str, id: &str, address: &str) -> bool {
+    let normalize = |s: &str| s.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect::<String>();
+    let input = normalize(input);
+    !input.is_empty() && (input == normalize(id) || input == normalize(address))
+}
+
+/// A printer the BLE task currently holds a connection to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConnectedPrinter {
+    /// Platform peripheral id; the key for `PrintTarget::Printer`.
+    pub id: String,
+    pub name: String,
+}
+
+/// Which connected printer(s) receive print jobs, feeds and raw commands.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PrintTarget {
+    /// Every connected printer gets its own copy of each job.
+    #[default]
+    All,
+    /// Only the printer with this peripheral id.
+    Printer(String),
+}
+
+impl PrintTarget {
+    /// Ids of the printers, out of the `connected` ones, this target selects.
+    pub fn resolve<'a>(&self, connected: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+        connected
+            .into_iter()
+            .filter(|id| match self {
+                PrintTarget::All => true,
+                PrintTarget::Printer(target) => *id == target,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
 /// Commands sent from the UI thread to the BLE thread.
 #[derive(Debug)]
 pub enum BleCommand {
+    /// Report the system's Bluetooth adapters (answered with `AppEvent::Adapters`).
+    ListAdapters,
+    /// Scan and connect using the adapter at this index of the `Adapters` list.
+    SetAdapter(usize),
     ScanAndConnect,
+    /// Abort a running `ScanAndConnect` scan without connecting.
+    StopScan,
+    /// Connect to one of the printers reported by the last scan (by id).
+    ConnectDevice(String),
+    /// Connect straight to a previously used printer (by id), skipping the full scan.
+    ConnectLast(String),
+    /// Connect to the printer with this MAC address or platform id, typed by
+    /// the user. Only devices the adapter already knows are found; no scan runs.
+    ConnectByAddress(String),
+    /// Disconnect every printer.
     Disconnect,
-    PrintImage(DynamicImage),
-    /// font_path: absolute path to the .ttf/.ttc file used by ab_glyph
-    /// font_size: point size used when rendering
-    PrintText { text: String, font_path: String, font_size: f32 },
+    /// Disconnect just the printer with this id, keeping any others.
+    DisconnectDevice(String),
+    /// Choose which connected printer(s) later jobs go to.
+    SetPrintTarget(PrintTarget),
+    /// crop: region of `img` to print; the default prints the whole image.
+    /// segment_height: split into jobs of at most this many dots, fed apart.
+    /// copies: how many times to print it, one after another.
+    PrintImage { img: Arc<DynamicImage>, options: RasterOptions, crop: CropRect, segment_height: Option<u32>, copies: u8 },
+    /// Print each image as its own queued job, in order.
+    PrintBatch { images: Vec<DynamicImage>, options: RasterOptions },
+    /// Render text with the given font/layout options and print it `copies` times.
+    PrintText { text: String, options: TextOptions, copies: u8 },
+    /// Encode text as a QR code (module_px: pixels per module) and print it.
+    PrintQr { text: String, ec_level: QrEcLevel, module_px: u32 },
+    /// Encode data as a 1D barcode with a caption rendered in font_path, and print it.
+    PrintBarcode { data: String, symbology: Symbology, font_path: String },
+    /// Print the pages of an imported `.ctpjob` file exactly as exported.
+    PrintJobFile(JobFile),
+    /// Print the built-in diagnostic page (ruler, gray ramp, font samples, status).
+    PrintTestPage,
+    /// Send only the initialize sequence (ESC @) to recover a stuck printer.
+    ResetPrinter,
+    /// Write these bytes to the printer as-is (advanced "send raw" panel).
+    SendRaw(Vec<u8>),
+    /// Advance the paper by N line feeds without printing.
+    Feed(u8),
+    /// Advance the paper by N dot-lines (1/8 mm each) for precise tearing.
+    FeedDots(u8),
+    /// Print the last job again from its cached raster bytes.
+    Reprint,
+    /// Abort the in-flight image data transfer (the end sequence is still sent).
+    CancelPrint,
+    /// Retry the connection with backoff when the printer drops off unexpectedly.
+    SetAutoReconnect(bool),
+    /// Custom name substring/regex used when scanning; empty restores the default.
+    SetPrinterNamePattern(String),
+    /// Line feeds sent after each print (before the end marker) for tearing off.
+    SetFeedLines(u8),
+    /// Use write-without-response for image data (periodically flushed).
+    SetWriteWithoutResponse(bool),
+    /// Pauses between the init/start/data/end steps of each print.
+    SetPrintTiming(PrintTiming),
+    /// Write print jobs to `.bin`/`.png` files instead of sending them over BLE.
+    SetSimulate(bool),
+    /// Send a status request every N seconds while idle to stop the printer
+    /// auto-sleeping; None turns the keep-alive off.
+    SetKeepAlive(Option<u32>),
 }
 
-/// Events sent from the BLE thread back to the UI thread.
-#[derive(Debug)]
+impl BleCommand {
+    /// Whether this command queues a print job.
+    pub fn is_print(&self) -> bool {
+        matches!(
+            self,
+            BleCommand::PrintImage { .. }
+                | BleCommand::PrintBatch { .. }
+                | BleCommand::PrintText { .. }
+                | BleCommand::PrintQr { .. }
+                | BleCommand::PrintBarcode { .. }
+                | BleCommand::PrintJobFile(_)
+                | BleCommand::PrintTestPage
+                | BleCommand::Reprint
+        )
+    }
+}
+
+/// Events sent from the BLE thread back to the UI thread. Serialized as
+/// `{"event": "print_progress", "data": {...}}` for the CLI's `--json` stream.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
 pub enum AppEvent {
-    Log(String),
+    Log(LogLevel, String),
+    /// Names of the available Bluetooth adapters, in selection-index order.
+    Adapters(Vec<String>),
+    /// A printer connected; others may already have been.
     Connected,
+    /// Every printer connected right now, sent whenever the set changes or a
+    /// connect attempt ends.
+    ConnectedPrinters(Vec<ConnectedPrinter>),
+    /// Peripheral id and advertised name of the printer just connected to.
+    PrinterIdentity { id: String, name: String },
+    /// No printer is connected any more (or a connect attempt found none).
     Disconnected,
-    BatteryLevel(u8),
+    /// A printer's link dropped without the user disconnecting it.
+    ConnectionLost,
+    /// Battery voltage in millivolts from the status response; the UI turns
+    /// it into a percentage with the user's BatteryCurve.
+    BatteryVoltage(u32),
+    PrinterInfo(PrinterInfo),
+    /// Fault state changed; `PrinterStatus::Ready` means the fault has cleared.
+    PrinterFault(PrinterStatus),
     PrintProgress { sent: usize, total: usize },
+    /// Copy `copy` (1-based) of a multi-copy print is starting.
+    CopyProgress { copy: u8, copies: u8 },
+    /// Rolling average write round-trip time in milliseconds.
+    LinkQuality(u32),
     Error(String),
     ScanStarted,
-    PrintComplete,
+    /// Connecting straight to a known printer, without a full scan, has started.
+    ConnectStarted,
+    /// A matching printer was found (or its signal strength changed) during a scan.
+    DeviceDiscovered(DiscoveredDevice),
+    PrintComplete(PrintSummary),
+    PrintCancelled,
+    /// A print identical to the one just submitted was skipped as an
+    /// accidental double click. `busy` is whether a print is still running.
+    DuplicateSkipped { busy: bool },
+    /// A print was rendered and cached, so `BleCommand::Reprint` can resend all of it.
+    ReprintAvailable,
+    /// Number of print jobs waiting behind the one currently printing.
+    QueueLength(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_STATUS: &str = "HV=V1.0A,SV=V1.01,VOLT=4000mv,DPI=384,";
+
+    #[test]
+    fn test_compile_printer_name_regex() {
+        let default = compile_printer_name_regex("  ").unwrap();
+        assert!(default.is_match("S Blue Printer"));
+
+        let custom = compile_printer_name_regex("thermal").unwrap();
+        assert!(custom.is_match("Acme THERMAL 58"));
+        assert!(!custom.is_match("S Blue Printer"));
+
+        assert!(compile_printer_name_regex("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_battery_curves() {
+        assert_eq!(parse_battery_mv(SAMPLE_STATUS.as_bytes()), Some(4000));
+
+        let linear = BatteryCurve::default();
+        let lipo = BatteryCurve { mode: BatteryMode::LipoCurve, ..linear };
+        // A LiPo at 3.7V is mostly drained, though a straight line says nearly half
+        assert_eq!(linear.percent(3700), 44);
+        assert_eq!(lipo.percent(3700), 12);
+        for curve in [linear, lipo] {
+            assert_eq!((curve.percent(3000), curve.percent(BATT_MAX_MV), curve.percent(4500)), (0, 100, 100));
+        }
+
+        // A custom range rescales the curve
+        let clone = BatteryCurve { min_mv: 3500, max_mv: 4350, ..linear };
+        assert_eq!(clone.percent(3925), 50);
+    }
+
+    #[test]
+    fn test_parse_printer_info() {
+        let info = parse_printer_info(SAMPLE_STATUS.as_bytes()).unwrap();
+        assert_eq!(info.hw_version.as_deref(), Some("V1.0A"));
+        assert_eq!(info.sw_version.as_deref(), Some("V1.01"));
+        assert_eq!(info.dpi, Some(384));
+    }
+
+    #[test]
+    fn test_parse_printer_status_faults() {
+        // Guessed payloads, not captured ones (see FAULT_PATTERNS)
+        assert_eq!(parse_printer_status(b"NO PAPER"), PrinterStatus::PaperOut);
+        assert_eq!(parse_printer_status(b"HV=V1.0A,Paper Out,"), PrinterStatus::PaperOut);
+        assert_eq!(parse_printer_status(b"PAPER=0,VOLT=4000mv,"), PrinterStatus::PaperOut);
+        assert_eq!(parse_printer_status(b"cover open"), PrinterStatus::CoverOpen);
+        assert_eq!(parse_printer_status(b"OVERHEAT"), PrinterStatus::OverTemperature);
+        assert_eq!(parse_printer_status(b"TEMP HIGH"), PrinterStatus::OverTemperature);
+        assert_eq!(parse_printer_status(b"HOT=1,"), PrinterStatus::OverTemperature);
+    }
+
+    #[test]
+    fn test_parse_printer_status_ready() {
+        assert_eq!(parse_printer_status(SAMPLE_STATUS.as_bytes()), PrinterStatus::Ready);
+        assert_eq!(parse_printer_status(b"PAPER=1,HOT=0,"), PrinterStatus::Ready);
+        assert!(!PrinterStatus::Ready.is_fault());
+        assert!(PrinterStatus::PaperOut.is_fault());
+    }
+
+    #[test]
+    fn test_is_known_status() {
+        assert!(is_known_status(SAMPLE_STATUS.as_bytes()));
+        assert!(is_known_status(b"VOLT=3900mv,"));
+        assert!(!is_known_status(b"HV=V1.0A,PAPER=0,"));
+        assert!(!is_known_status(b"\x01\x02"));
+        assert!(!is_known_status(b""));
+    }
+
+    #[test]
+    fn test_print_summary_display() {
+        let summary = PrintSummary { bytes: 9608, width: 384, height: 200, elapsed_ms: 2450 };
+        assert_eq!(summary.paper_mm(), 25.0);
+        assert_eq!(summary.to_string(), "9608 bytes, 384x200 dots, 2.5 cm of paper, 2.5 s");
+    }
+
+    #[test]
+    fn test_print_target_resolve() {
+        let connected = ["pink".to_string(), "blue".to_string()];
+        assert_eq!(PrintTarget::All.resolve(&connected), vec!["pink", "blue"]);
+        assert_eq!(PrintTarget::Printer("blue".into()).resolve(&connected), vec!["blue"]);
+        assert!(PrintTarget::Printer("gone".into()).resolve(&connected).is_empty());
+        assert!(PrintTarget::All.resolve(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_crop_rect_clamped_and_non_empty() {
+        let full = CropRect::default();
+        assert_eq!(full.to_pixels(200, 100), (0, 0, 200, 100));
+
+        // Dragged right-to-left and past the edge
+        let rect = CropRect::from_points((1.3, 0.5), (0.5, -0.2));
+        assert_eq!(rect.to_pixels(200, 100), (100, 0, 100, 50));
+
+        // A click without a drag still yields a 1x1 crop inside the image
+        let point = CropRect::from_points((1.0, 1.0), (1.0, 1.0));
+        assert_eq!(point.to_pixels(200, 100), (199, 99, 1, 1));
+
+        let img = Arc::new(DynamicImage::new_luma8(200, 100));
+        let cropped = CropRect { x: 0.25, y: 0.0, w: 0.5, h: 0.5 }.apply(img.clone());
+        assert_eq!((cropped.width(), cropped.height()), (100, 50));
+        assert!(Arc::ptr_eq(&CropRect::default().apply(img.clone()), &img));
+    }
+
+    #[test]
+    fn test_text_history_bounded() {
+        let mut history = TextHistory::default();
+        assert!(!history.can_undo());
+        history.push("a".into());
+        history.push("a".into());
+        history.push("ab".into());
+        assert_eq!(history.undo().as_deref(), Some("ab"));
+        assert_eq!(history.undo().as_deref(), Some("a"));
+        assert_eq!(history.undo(), None);
+
+        for i in 0..TextHistory::LIMIT + 10 {
+            history.push(i.to_string());
+        }
+        let mut undone = 0;
+        while history.undo().is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, TextHistory::LIMIT);
+    }
+
+    #[test]
+    fn test_address_matches() {
+        let mac = "AA:BB:CC:DD:EE:0F";
+        assert!(address_matches("aa:bb:cc:dd:ee:0f", "hci0/dev_AA_BB_CC_DD_EE_0F", mac));
+        assert!(address_matches(" AA-BB-CC-DD-EE-0F ", "hci0/dev_AA_BB_CC_DD_EE_0F", mac));
+        let uuid = "5A0B3C1E-9F2D-4E8B-A1C7-0D6E2F3B4C5D";
+        assert!(address_matches("5a0b3c1e9f2d4e8ba1c70d6e2f3b4c5d", uuid, "00:00:00:00:00:00"));
+        assert!(!address_matches("AA:BB:CC:DD:EE:10", "hci0/dev_AA_BB_CC_DD_EE_0F", mac));
+        assert!(!address_matches("", "", ""));
+    }
+
+    #[test]
+    fn test_upsert_device_sorts_by_signal() {
+        let dev = |id: &str, rssi| DiscoveredDevice { id: id.into(), name: "S Blue Printer".into(), rssi };
+        let mut devices = Vec::new();
+        upsert_device(&mut devices, dev("a", Some(-80)));
+        upsert_device(&mut devices, dev("b", None));
+        upsert_device(&mut devices, dev("c", Some(-50)));
+        let ids: Vec<_> = devices.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, ["c", "a", "b"]);
+
+        // A fresh reading replaces the old one and re-sorts
+        upsert_device(&mut devices, dev("a", Some(-40)));
+        let ids: Vec<_> = devices.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn test_otf_font_chars_per_line() {
+        // A minimal CFF-outline OpenType font: every printable ASCII character
+        // is a 500x700 box on a 600-unit advance (1000 units per em)
+        let otf = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/box.otf");
+        let font = load_font(otf, 0).unwrap();
+        let glyph = font.glyph_id('A');
+        assert_ne!(glyph.0, 0);
+        assert!(font.outline(glyph).is_some(), "CFF outline should parse");
+        // 384px / (0.6 * 32px) per character
+        assert_eq!(chars_per_line(otf, 0, 32.0), 20);
+    }
+
+    #[test]
+    fn test_load_font_reports_unparseable_otf() {
+        let path = std::env::temp_dir().join("ctp500-not-a-font.otf");
+        std::fs::write(&path, b"OTTO not really a font").unwrap();
+        let err = load_font(&path.to_string_lossy(), 0).unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(err.contains("OpenType"), "{}", err);
+        assert!(load_font("/nonexistent/font.otf", 0).unwrap_err().starts_with("Failed to read font"));
+    }
+
+    #[test]
+    fn test_font_cache_reuses_until_modified() {
+        let Some(src) = ["/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf", "/System/Library/Fonts/Menlo.ttc"]
+            .into_iter()
+            .find(|p| Path::new(p).exists())
+        else {
+            return;
+        };
+        let path = std::env::temp_dir().join("ctp500-cached-font.ttf");
+        std::fs::copy(src, &path).unwrap();
+        let path_str = path.to_string_lossy();
+
+        let first = load_font_cached(&path_str, 0).unwrap();
+        // Second load is served from the cache: same parsed font, no re-read
+        assert!(Arc::ptr_eq(&first, &load_font_cached(&path_str, 0).unwrap()));
+
+        let later = SystemTime::now() + std::time::Duration::from_secs(60);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        let reloaded = load_font_cached(&path_str, 0).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(!Arc::ptr_eq(&first, &reloaded));
+    }
+
+    #[test]
+    fn test_font_face_count() {
+        let path = std::env::temp_dir().join("ctp500-collection.ttc");
+        let mut header = b"ttcf\x00\x02\x00\x00".to_vec();
+        header.extend_from_slice(&3u32.to_be_bytes());
+        std::fs::write(&path, header).unwrap();
+        let count = font_face_count(&path.to_string_lossy());
+        std::fs::remove_file(&path).ok();
+        assert_eq!(count, 3);
+        assert_eq!(font_face_count("/nonexistent/font.ttc"), 1);
+
+        let menlo = "/System/Library/Fonts/Menlo.ttc";
+        if Path::new(menlo).exists() {
+            let faces = font_face_count(menlo);
+            assert!(faces > 1);
+            assert!(load_font(menlo, faces - 1).is_ok());
+            assert!(load_font(menlo, faces).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_printer_info_missing_fields() {
+        assert_eq!(parse_printer_info(b"VOLT=4000mv,"), None);
+        let info = parse_printer_info(b"SV=V2.00,").unwrap();
+        assert_eq!(info.hw_version, None);
+        assert_eq!(info.sw_version.as_deref(), Some("V2.00"));
+    }
 }